@@ -0,0 +1,59 @@
+//! How long a frame of the logs takes with 500k rows in the pane, far more
+//! than the store hands the UI at a time, so that any cost that grows with
+//! the rows rather than the screen shows up. The rows are scrolled through a
+//! line a frame in a 120x40 terminal, as holding ↓ does. Run it with
+//! `cargo bench --bench render`.
+#![allow(dead_code, unused_imports)]
+
+#[path = "../src/input/mod.rs"]
+mod input;
+#[path = "../src/store/mod.rs"]
+mod store;
+#[path = "../src/ui/mod.rs"]
+mod ui;
+
+use std::time::Instant;
+
+use ratatui::backend::TestBackend;
+use ratatui::Terminal;
+
+const ROWS: usize = 500_000;
+const FRAMES: u32 = 200;
+
+/// Rows of one to forty words, the same ones every run
+fn rows() -> Vec<ui::LogLine> {
+    let words = ["request", "handled", "user", "session", "timeout", "connection", "retry", "cache", "miss", "queue"];
+    let levels = ["INFO", "INFO", "DEBUG", "WARN", "ERROR"];
+    let mut seed: u64 = 1;
+    let mut next = |n: usize| {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (seed >> 33) as usize % n
+    };
+    (0..ROWS)
+        .map(|n| {
+            let level = levels[next(levels.len())];
+            let text: Vec<&str> = (0..1 + next(40)).map(|_| words[next(words.len())]).collect();
+            let id = n as i64;
+            ui::LogLine { id, order: (0, id), text: format!("{} line {} {}", level, n, text.join(" ")), ..Default::default() }
+        })
+        .collect()
+}
+
+fn main() {
+    let mut app = ui::App::default();
+    app.wrap.tab_width = 8;
+    app.set_window(0, 0, rows(), ROWS);
+    let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+
+    // which lays out every row once, to count its screen lines
+    let start = Instant::now();
+    terminal.draw(|frame| app.render(frame)).unwrap();
+    println!("first frame: {:?}", start.elapsed());
+
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        app.scroll_down(1);
+        terminal.draw(|frame| app.render(frame)).unwrap();
+    }
+    println!("{} rows, {} frames scrolling a line at a time: {:?} a frame", ROWS, FRAMES, start.elapsed() / FRAMES);
+}
@@ -0,0 +1,85 @@
+use std::io::{self, Write};
+use std::process::Stdio;
+use std::sync::mpsc::{self, SyncSender};
+use std::time::{Duration, Instant};
+
+use filewatch_rs::file_watch;
+
+/// Alerts waiting for the command while it runs for an earlier one, beyond
+/// which they're dropped rather than piling up
+const QUEUE: usize = 16;
+
+/// How long the command gets for an alert before it's killed, so one that
+/// hangs doesn't keep the rest from running
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often a running command is checked on
+const POLL: Duration = Duration::from_millis(50);
+
+/// Ring the terminal's bell, which it may show instead, or not at all
+pub fn bell() -> Result<(), String> {
+    let mut stdout = io::stdout();
+    stdout.write_all(b"\x07")
+        .and_then(|()| stdout.flush())
+        .map_err(|e| e.to_string())
+}
+
+/// Runs `Args::alert_exec` for each alert with its line on stdin, one at a
+/// time in a thread of its own, so that a slow command never holds up the
+/// UI. It stops once this is dropped and the alerts waiting are done.
+pub struct Exec {
+    lines: SyncSender<String>,
+}
+
+impl Exec {
+    pub fn spawn(command: String) -> Self {
+        let (lines, rx) = mpsc::sync_channel::<String>(QUEUE);
+        std::thread::spawn(move || {
+            for line in rx {
+                if let Err(e) = run(&command, line) {
+                    log::error!("Failed to run '{}' for an alert: {}", command, e);
+                }
+            }
+        });
+        Exec { lines }
+    }
+
+    /// Run the command for `line`, once it's done with the alerts before.
+    /// Returns false if too many are waiting already, so it won't be.
+    pub fn send(&self, line: &str) -> bool {
+        // disconnected only if the thread panicked
+        self.lines.try_send(line.to_owned()).is_ok()
+    }
+}
+
+/// Run `command` with `line` on its stdin and nothing on its stdout or
+/// stderr, which is where the screen is, killing it after `TIMEOUT`
+fn run(command: &str, line: String) -> io::Result<()> {
+    let mut child = file_watch::shell_command(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    // apart from waiting, in case it doesn't read it all, and a command
+    // that doesn't read it at all is fine
+    std::thread::spawn(move || {
+        let _ = stdin.write_all(line.as_bytes()).and_then(|()| stdin.write_all(b"\n"));
+    });
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            if !status.success() {
+                log::warn!("'{}' exited for an alert ({})", command, status);
+            }
+            return Ok(());
+        }
+        if started.elapsed() >= TIMEOUT {
+            log::warn!("'{}' took longer than {}s for an alert, killing it", command, TIMEOUT.as_secs());
+            file_watch::kill(&mut child)?;
+            child.wait()?;
+            return Ok(());
+        }
+        std::thread::sleep(POLL);
+    }
+}
@@ -0,0 +1,92 @@
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// Where copied text goes, see `Args::clipboard`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    /// OSC 52 over SSH, otherwise the system's clipboard if there is one
+    Auto,
+    /// The terminal's, see `copy_osc52`
+    Osc52,
+    /// The system's, through the window system
+    System,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(Backend::Auto),
+            "osc52" => Ok(Backend::Osc52),
+            "system" => Ok(Backend::System),
+            _ => Err(format!("unknown value '{}', expected auto, osc52 or system", s)),
+        }
+    }
+}
+
+/// Copies text by `Backend`. The system's clipboard is kept open once it's
+/// used, as on X11 what's copied is only there while it is.
+pub struct Clipboard {
+    backend: Backend,
+    system: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    pub fn new(backend: Backend) -> Self {
+        Clipboard { backend, system: None }
+    }
+
+    /// Put `text` on the clipboard. `Backend::Auto` falls back on OSC 52
+    /// when there's no system clipboard, e.g. without a display.
+    pub fn copy(&mut self, text: &str) -> Result<(), String> {
+        match self.backend {
+            Backend::Osc52 => copy_osc52(text),
+            Backend::System => self.copy_system(text),
+            Backend::Auto if over_ssh() => copy_osc52(text),
+            Backend::Auto => self.copy_system(text).or_else(|_| copy_osc52(text)),
+        }
+    }
+
+    fn copy_system(&mut self, text: &str) -> Result<(), String> {
+        let system = match &mut self.system {
+            Some(system) => system,
+            None => self.system.insert(arboard::Clipboard::new().map_err(|e| e.to_string())?),
+        };
+        system.set_text(text).map_err(|e| e.to_string())
+    }
+}
+
+/// The program was started in an SSH session, so the system clipboard, if
+/// any, is the remote machine's rather than the one in front of the user
+fn over_ssh() -> bool {
+    std::env::var_os("SSH_TTY").is_some() || std::env::var_os("SSH_CONNECTION").is_some()
+}
+
+/// Put `text` on the clipboard with an OSC 52 escape sequence, which the
+/// terminal acts on, so that it works over SSH too. Terminals that don't
+/// support it, or don't allow it, ignore it.
+fn copy_osc52(text: &str) -> Result<(), String> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))
+        .and_then(|()| stdout.flush())
+        .map_err(|e| e.to_string())
+}
+
+/// `bytes` in standard base64, with padding, as OSC 52 takes them
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| group | u32::from(*byte) << (16 - 8 * i));
+        // a char for every 6 bits there are, then `=` for the rest
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
@@ -0,0 +1,76 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::ui::{self, Highlight, LevelStyle};
+
+/// What the config file sets, in the order it's written. It's made of
+/// sections of `KEY = VALUE` lines, with `#` comments:
+///
+/// ```text
+/// [highlights]
+/// req-[0-9a-f]{8} = cyan
+/// panicked at = red bold reversed
+///
+/// [styles]
+/// debug = dim
+/// ```
+///
+/// `[highlights]` has a regex and the style to show its matches in, the
+/// same as `--highlight`, and `[styles]` a level and how its lines are
+/// shown, the same as `--style`. Values are split off at the last `=`, so
+/// regexes can have them too.
+#[derive(Default)]
+pub struct Config {
+    pub highlights: Vec<Highlight>,
+    pub level_styles: Vec<LevelStyle>,
+}
+
+/// `$XDG_CONFIG_HOME/filewatch/config`, or under `~/.config` without it
+pub fn default_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        // as with `default_db_dir`, relative paths are ignored
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|config_home| config_home.join("filewatch/config"))
+}
+
+/// Read the config file at `path`, which is no config at all if it doesn't
+/// exist and isn't `required`. The error says which line is wrong and how,
+/// for showing as is.
+pub fn read(path: &Path, required: bool) -> Result<Config, String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound && !required => return Ok(Config::default()),
+        Err(e) => return Err(format!("cannot read config {}: {}", path.display(), e)),
+    };
+    let mut config = Config::default();
+    let mut section = None;
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let at = |msg: String| format!("{}:{}: {}", path.display(), i + 1, msg);
+        if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            section = match name {
+                "highlights" | "styles" => Some(name.to_owned()),
+                _ => return Err(at(format!("unknown section [{}], expected [highlights] or [styles]", name))),
+            };
+            continue;
+        }
+        let (key, value) = line.rsplit_once('=')
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .ok_or_else(|| at(format!("expected KEY = VALUE, not '{}'", line)))?;
+        match section.as_deref() {
+            Some("highlights") => {
+                let style = Highlight::parse_style(value).map_err(at)?;
+                config.highlights.push(Highlight::new(key, style).map_err(at)?);
+            }
+            Some(_) => config.level_styles.push(LevelStyle { level: key.parse().map_err(at)?, style: ui::parse_style(value).map_err(at)? }),
+            None => return Err(at(String::from("expected a [highlights] or [styles] section first"))),
+        }
+    }
+    Ok(config)
+}
@@ -0,0 +1,90 @@
+use std::str::FromStr;
+
+use regex::Regex;
+
+use filewatch_rs::file_watch::{TextEncoding, EXEC_PREFIX};
+
+/// A file argument: a path or glob pattern, plus options that only apply to
+/// the files it matches, written as `PATH[=LABEL][:KEY=VALUE]...`, or
+/// `PATH:json` to render JSON lines
+#[derive(Clone, Debug)]
+pub struct FileSpec {
+    pub pattern: String,
+    pub encoding: Option<TextEncoding>,
+    /// Shown and stored instead of the path, from `PATH=LABEL` or `:label=LABEL`
+    pub label: Option<String>,
+    pub json: bool,
+    /// From `:filter=REGEX`, added to `--filter`
+    pub filters: Vec<Regex>,
+    /// From `:exclude=REGEX`, added to `--exclude`
+    pub excludes: Vec<Regex>,
+    /// From `:level=REGEX`, used instead of `--level-regex`
+    pub level_regex: Option<Regex>,
+}
+
+impl FromStr for FileSpec {
+    type Err = String;
+
+    fn from_str(arg: &str) -> Result<Self, Self::Err> {
+        let mut pattern = arg;
+        let mut encoding = None;
+        let mut label = None;
+        let mut json = false;
+        let mut filters = vec![];
+        let mut excludes = vec![];
+        let mut level_regex = None;
+        // options are the trailing `:key=value` segments with a known key,
+        // anything else is part of the path, so paths containing ':' still work
+        while let Some((rest, option)) = pattern.rsplit_once(':') {
+            if option == "json" {
+                json = true;
+                pattern = rest;
+                continue;
+            }
+            let Some((key, value)) = option.split_once('=') else {
+                break;
+            };
+            match key {
+                "encoding" => encoding = Some(value.parse()?),
+                "label" => label = Some(value.to_owned()),
+                "filter" => filters.push(parse_regex(value)?),
+                "exclude" => excludes.push(parse_regex(value)?),
+                "level" => level_regex = Some(parse_regex(value)?),
+                _ => break,
+            }
+            pattern = rest;
+        }
+        // commands are full of '=', so they can only be labelled with `:label=`
+        if label.is_none() && !pattern.starts_with(EXEC_PREFIX) {
+            if let Some((path, path_label)) = split_label(pattern) {
+                pattern = path;
+                label = Some(path_label.to_owned());
+            }
+        }
+        Ok(FileSpec {
+            pattern: pattern.replace("\\=", "="),
+            encoding,
+            label,
+            json,
+            filters,
+            excludes,
+            level_regex,
+        })
+    }
+}
+
+fn parse_regex(value: &str) -> Result<Regex, String> {
+    Regex::new(value).map_err(|e| format!("invalid regex '{}': {}", value, e))
+}
+
+/// Splits `PATH=LABEL` on the last '=' that isn't escaped as `\=`. A label
+/// can't contain '/', so `dir=x/app.log` is still just a path.
+fn split_label(arg: &str) -> Option<(&str, &str)> {
+    let (at, _) = arg.rmatch_indices('=')
+        .find(|(i, _)| !arg[..*i].ends_with('\\'))?;
+    let (path, label) = (&arg[..at], &arg[at + 1..]);
+    if path.is_empty() || label.is_empty() || label.contains('/') {
+        return None;
+    }
+    Some((path, label))
+}
@@ -0,0 +1,145 @@
+use std::borrow::Cow;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+
+/// `line` without terminal escape sequences: CSI sequences like colors and
+/// cursor movement, OSC sequences like window titles, other string
+/// sequences (DCS, APC, ...) and two or three char escapes like `ESC ( B`.
+/// An escape cut off by the end of the line is dropped too.
+pub fn strip_ansi(line: &str) -> Cow<'_, str> {
+    if !line.contains(ESC) {
+        return Cow::Borrowed(line);
+    }
+    Cow::Owned(scan(line, |_, _| {}))
+}
+
+/// `line` without escape sequences, as `strip_ansi`, and the parameters of
+/// the SGR sequences (colors and text attributes, e.g. `1;31`) that were in
+/// it, with the char index in the stripped line each one applies from
+pub fn split_sgr(line: &str) -> (String, Vec<(usize, &str)>) {
+    let mut sgr = vec![];
+    let text = scan(line, |at, params| sgr.push((at, params)));
+    (text, sgr)
+}
+
+/// Copies `line` without its escape sequences, passing each SGR sequence to
+/// `on_sgr` along with how many chars have been copied before it
+fn scan<'a>(line: &'a str, mut on_sgr: impl FnMut(usize, &'a str)) -> String {
+    let mut stripped = String::with_capacity(line.len());
+    let mut copied = 0;
+    let mut chars = line.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        if c != ESC {
+            stripped.push(c);
+            copied += 1;
+            continue;
+        }
+        match chars.next() {
+            Some((at, '[')) => {
+                let params_start = at + 1;
+                if let Some((end, 'm')) = skip_csi(&mut chars) {
+                    let params = &line[params_start..end];
+                    if params.bytes().all(|b| b.is_ascii_digit() || b == b';' || b == b':') {
+                        on_sgr(copied, params);
+                    }
+                }
+            }
+            Some((_, ']' | 'P' | 'X' | '^' | '_')) => skip_string(&mut chars),
+            Some((_, ' '..='/')) => {
+                // intermediates, then the final char
+                while chars.next_if(|(_, c)| matches!(c, ' '..='/')).is_some() {}
+                chars.next();
+            }
+            // a single char escape like `ESC 7`, or a stray ESC
+            _ => {}
+        }
+    }
+    stripped
+}
+
+/// Parameters and intermediates, then a final char from `@` to `~`, which
+/// is returned with its index
+fn skip_csi(chars: &mut Peekable<CharIndices>) -> Option<(usize, char)> {
+    while chars.next_if(|(_, c)| matches!(c, '0'..='?' | ' '..='/')).is_some() {}
+    chars.next_if(|(_, c)| matches!(c, '@'..='~'))
+}
+
+/// Anything up to BEL or `ESC \`. Any other escape ends the string without
+/// being skipped, as terminals treat it.
+fn skip_string(chars: &mut Peekable<CharIndices>) {
+    while let Some((_, c)) = chars.next_if(|(_, c)| *c != ESC) {
+        if c == BEL {
+            return;
+        }
+    }
+    let mut ahead = chars.clone();
+    ahead.next();
+    if ahead.next().is_some_and(|(_, c)| c == '\\') {
+        *chars = ahead;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_captured_output() {
+        let cases = [
+            // `ls --color=always`
+            ("\x1b[0m\x1b[01;34msub\x1b[0m", "sub"),
+            // `grep --color=always`, with erase in line after each color
+            ("\x1b[01;31m\x1b[Kerror\x1b[m\x1b[K: bad", "error: bad"),
+            // `git log --oneline --decorate --color=always`
+            ("\x1b[33m566bbc0\x1b[m\x1b[33m (\x1b[m\x1b[1;36mHEAD -> \x1b[m\x1b[1;32mmaster\x1b[m\x1b[33m)\x1b[m fix", "566bbc0 (HEAD -> master) fix"),
+            // `ls --hyperlink=always`, OSC 8 links ended by BEL
+            ("\x1b]8;;file://vm/tmp/run/lsd/a.log\x07a.log\x1b]8;;\x07", "a.log"),
+            // a shell setting the window title, ended by BEL and by ST
+            ("\x1b]0;user@host: ~\x07$ make", "$ make"),
+            ("\x1b]2;building\x1b\\done", "done"),
+            // 256 colors and truecolor, with `:` as well as `;`
+            ("\x1b[38;5;208morange\x1b[0m \x1b[38:2:255:0:0mred\x1b[0m", "orange red"),
+            // cursor movement from a progress bar
+            ("\x1b[2K\x1b[1G[=====>    ] 50%", "[=====>    ] 50%"),
+        ];
+        for (line, stripped) in cases {
+            assert_eq!(strip_ansi(line), stripped, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn strips_bare_and_short_escapes() {
+        let cases = [
+            // charset selection, with an intermediate
+            ("\x1b(Bplain", "plain"),
+            // save and restore the cursor
+            ("\x1b7saved\x1b8", "saved"),
+            // a stray ESC, and one cut off by the end of the line
+            ("a\x1bb", "a"),
+            ("cut off\x1b", "cut off"),
+            ("cut off \x1b[1;3", "cut off "),
+            // an OSC that's never ended runs to the end of the line
+            ("\x1b]0;no end", ""),
+            // another escape ends it without being skipped
+            ("\x1b]0;title\x1b[1mbold\x1b[0m", "bold"),
+        ];
+        for (line, stripped) in cases {
+            assert_eq!(strip_ansi(line), stripped, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn line_without_escapes_is_borrowed() {
+        assert!(matches!(strip_ansi("no escapes [1m"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn sgr_params_with_where_they_apply() {
+        let (text, sgr) = split_sgr("\x1b[01;31m\x1b[Kerror\x1b[m\x1b[K: \x1b]0;t\x07bad");
+        assert_eq!(text, "error: bad");
+        assert_eq!(sgr, [(0, "01;31"), (5, "")]);
+    }
+}
@@ -0,0 +1,149 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use regex::Regex;
+
+use super::{watch_file, Backend, DEFAULT_CHANNEL_CAPACITY, LogsMessage, Shutdown, Tail, TextEncoding, WatchError, WatchOptions};
+
+/// Sets up a watcher for a single file, for use outside of the filewatch UI.
+///
+/// ```no_run
+/// use filewatch_rs::{FileWatcherBuilder, SourceEvent};
+///
+/// let (watcher, rx) = FileWatcherBuilder::new("app.log")
+///     .from_end(true)
+///     .filter(regex::Regex::new("ERROR").unwrap())
+///     .spawn()?;
+/// for message in rx.iter().take(10) {
+///     match message.event {
+///         SourceEvent::Lines { lines, .. } => {
+///             for line in lines {
+///                 println!("{}: {}", message.file_id, line);
+///             }
+///         }
+///         event => eprintln!("{}: {}", message.file_id, event.describe()),
+///     }
+/// }
+/// watcher.stop()?;
+/// # Ok::<(), filewatch_rs::WatchError>(())
+/// ```
+pub struct FileWatcherBuilder {
+    path: String,
+    options: WatchOptions,
+    channel_capacity: usize,
+}
+
+impl FileWatcherBuilder {
+    pub fn new(path: impl Into<String>) -> Self {
+        FileWatcherBuilder { path: path.into(), options: WatchOptions::default(), channel_capacity: DEFAULT_CHANNEL_CAPACITY }
+    }
+
+    /// Start from the end of the file, skipping what's already in it
+    pub fn from_end(mut self, from_end: bool) -> Self {
+        self.options.tail = from_end.then_some(Tail::Nothing);
+        self
+    }
+
+    /// Only read the last `lines` lines of what's already in the file
+    pub fn tail_lines(mut self, lines: u64) -> Self {
+        self.options.tail = Some(Tail::Lines(lines));
+        self
+    }
+
+    /// Only keep lines matching `regex`. Can be given more than once, lines
+    /// matching any of them are kept.
+    pub fn filter(mut self, regex: Regex) -> Self {
+        self.options.filters.push(regex);
+        self
+    }
+
+    /// Drop lines matching `regex`
+    pub fn exclude(mut self, regex: Regex) -> Self {
+        self.options.excludes.push(regex);
+        self
+    }
+
+    /// Tag messages with `label` instead of the path
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.options.label = Some(label.into());
+        self
+    }
+
+    pub fn encoding(mut self, encoding: TextEncoding) -> Self {
+        self.options.encoding = encoding;
+        self
+    }
+
+    /// Poll for changes every `interval` instead of using native notifications
+    pub fn poll(mut self, interval: Duration) -> Self {
+        self.options.backend = Backend::Poll(interval);
+        self
+    }
+
+    /// Keep reading the opened file after it's renamed or deleted
+    pub fn follow_descriptor(mut self, follow_descriptor: bool) -> Self {
+        self.options.follow_descriptor = follow_descriptor;
+        self
+    }
+
+    /// Join lines that don't match `regex` to the line before
+    pub fn multiline_start(mut self, regex: Regex) -> Self {
+        self.options.multiline_start = Some(regex);
+        self
+    }
+
+    /// Save how far the file was read to `offsets_file`, and start from
+    /// there next time if it's still the same file
+    pub fn resume(mut self, offsets_file: impl Into<PathBuf>) -> Self {
+        self.options.resume = Some(offsets_file.into());
+        self
+    }
+
+    /// How many messages can wait in the channel before the watcher stops
+    /// reading until there's room, see `watch_files`
+    pub fn channel_capacity(mut self, capacity: usize) -> Self {
+        self.channel_capacity = capacity;
+        self
+    }
+
+    /// Replace all options at once, e.g. to set ones without a method here
+    pub fn options(mut self, options: WatchOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Start watching on a new thread. Fails straight away if the file
+    /// doesn't exist, unless `WatchOptions::wait` is set.
+    pub fn spawn(self) -> Result<(WatchHandle, Receiver<LogsMessage>), WatchError> {
+        if !self.options.wait {
+            fs::metadata(&self.path)
+                .map_err(|source| WatchError::Open { path: self.path.clone(), source })?;
+        }
+        let (tx, rx) = mpsc::sync_channel(self.channel_capacity);
+        let shutdown = Shutdown::default();
+        let thread_shutdown = shutdown.clone();
+        let thread = thread::spawn(move || watch_file(&self.path, &self.options, thread_shutdown, tx));
+        Ok((WatchHandle { shutdown, thread }, rx))
+    }
+}
+
+/// A watcher started by `FileWatcherBuilder::spawn`. Dropping it leaves the
+/// watcher running, use `stop` to end it. A watcher waiting for room in a
+/// full channel only stops once there is some, so drop the receiver first if
+/// it's not being read.
+pub struct WatchHandle {
+    shutdown: Shutdown,
+    thread: JoinHandle<Result<(), WatchError>>,
+}
+
+impl WatchHandle {
+    /// Stop watching and wait for the watcher thread to finish, returning
+    /// the error that stopped it early, if any
+    pub fn stop(self) -> Result<(), WatchError> {
+        self.shutdown.trigger();
+        self.thread.join().map_err(|_| WatchError::Panicked)?
+    }
+}
@@ -0,0 +1,13 @@
+use std::io;
+
+/// Why a source couldn't be watched. The same problem is also sent on the
+/// channel as a `SourceEvent::Error`, for callers that only read that.
+#[derive(Debug, thiserror::Error)]
+pub enum WatchError {
+    #[error("cannot open {path}: {source}")]
+    Open { path: String, source: io::Error },
+    #[error("watcher thread panicked")]
+    Panicked,
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
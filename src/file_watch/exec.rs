@@ -0,0 +1,142 @@
+use std::io::{self, BufRead, BufReader, Read};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::SyncSender;
+use std::time::Duration;
+
+use super::{send_event, LogsMessage, SourceEvent, Shutdown, WatchError, WatchOptions, SHUTDOWN_CHECK_INTERVAL};
+
+pub const EXEC_PREFIX: &str = "exec:";
+
+/// How long to wait before restarting a command that exited
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// Runs `command` through the shell and tails its stdout and stderr, tagged
+/// `<program>[out]` and `<program>[err]` (or the label instead of the
+/// program), until it exits or `shutdown` is triggered, in which case it's
+/// killed.
+pub fn watch_exec(command: &str, options: &WatchOptions, shutdown: Shutdown, tx: SyncSender<LogsMessage>) -> Result<(), WatchError> {
+    let program = command.split_whitespace()
+        .next()
+        .and_then(|p| p.rsplit(['/', '\\']).next())
+        .unwrap_or(command);
+    let program = options.label.as_deref().unwrap_or(program);
+    let id = options.id_or(&format!("{}{}", EXEC_PREFIX, command));
+    loop {
+        let mut child = shell_command(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .inspect_err(|e| send_event(&tx, &id, SourceEvent::Error(format!("cannot run '{}': {}", command, e))))?;
+        let readers = [
+            child.stdout.take().map(|out| spawn_reader(out, format!("{}[out]", program), options, &tx)),
+            child.stderr.take().map(|err| spawn_reader(err, format!("{}[err]", program), options, &tx)),
+        ];
+
+        let status = wait_or_kill(&mut child, &shutdown)?;
+        for reader in readers.into_iter().flatten() {
+            if reader.join().is_err() {
+                log::error!("Output reader for '{}' panicked", command);
+            }
+        }
+        match status {
+            Some(status) => send_event(&tx, &id, SourceEvent::Notice(format!("'{}' exited ({})", command, status))),
+            // killed because we're shutting down
+            None => break,
+        }
+        if !options.exec_restart || shutdown.wait_timeout(RESTART_DELAY) {
+            break;
+        }
+        send_event(&tx, &id, SourceEvent::Notice(format!("restarting '{}'", command)));
+    }
+    log::debug!("Stopped running '{}'", command);
+    Ok(())
+}
+
+/// `command` run through the shell, `sh` or `cmd` on Windows, in a process
+/// group of its own on Unix so that `kill` gets everything it starts
+#[cfg(unix)]
+pub fn shell_command(command: &str) -> Command {
+    use std::os::unix::process::CommandExt;
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command);
+    // own process group, so everything the shell starts can be killed with it
+    cmd.process_group(0);
+    cmd
+}
+
+#[cfg(not(unix))]
+pub fn shell_command(command: &str) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.arg("/C").arg(command);
+    cmd
+}
+
+/// Returns the child's exit status, or None if it was killed due to `shutdown`
+fn wait_or_kill(child: &mut Child, shutdown: &Shutdown) -> Result<Option<std::process::ExitStatus>, io::Error> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if shutdown.wait_timeout(SHUTDOWN_CHECK_INTERVAL) {
+            kill(child)?;
+            child.wait()?;
+            return Ok(None);
+        }
+    }
+}
+
+/// Stop `child`, and on Unix the rest of its process group, see
+/// `shell_command`
+#[cfg(unix)]
+pub fn kill(child: &mut Child) -> Result<(), io::Error> {
+    // negative pid: the whole process group, e.g. both sides of a pipe
+    let pgid = child.id() as libc::pid_t;
+    if unsafe { libc::kill(-pgid, libc::SIGTERM) } == 0 {
+        Ok(())
+    } else {
+        child.kill()
+    }
+}
+
+#[cfg(not(unix))]
+pub fn kill(child: &mut Child) -> Result<(), io::Error> {
+    child.kill()
+}
+
+fn spawn_reader<R: Read + Send + 'static>(output: R, id: String, options: &WatchOptions, tx: &SyncSender<LogsMessage>) -> std::thread::JoinHandle<()> {
+    let encoding = options.encoding.initial();
+    let options = options.clone();
+    let tx = tx.clone();
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(output);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) => {
+                    send_event(&tx, &id, SourceEvent::Error(format!("error reading output: {}", e)));
+                    break;
+                }
+            }
+            if buf.ends_with(b"\n") {
+                buf.pop();
+            }
+            let (line, _) = encoding.decode_without_bom_handling(&buf);
+            let lines: Vec<String> = options.clean_line(line.into_owned())
+                .into_iter()
+                .filter(|line| options.keeps(line))
+                .collect();
+            if lines.is_empty() {
+                continue;
+            }
+            let msg = LogsMessage::new(id.clone(), SourceEvent::lines(lines, &options));
+            if tx.send(msg).is_err() {
+                log::error!("Output reader {} failed to send", id);
+                break;
+            }
+        }
+    })
+}
@@ -0,0 +1,107 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::mpsc::SyncSender;
+use std::time::Duration;
+
+use super::{send_event, LogsMessage, SourceEvent, Shutdown, WatchError, WatchOptions};
+
+/// How often an idle pipe is checked for new data
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[cfg(unix)]
+pub fn is_fifo(path: &str) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    std::fs::metadata(path).is_ok_and(|m| m.file_type().is_fifo())
+}
+
+#[cfg(not(unix))]
+pub fn is_fifo(_path: &str) -> bool {
+    false
+}
+
+/// Reads lines from a named pipe until `shutdown` is triggered. A pipe has no
+/// length to compare against, so rather than waiting on file events it's read
+/// directly, and reopened whenever the writer on the other end goes away.
+pub fn watch_fifo(path: &String, options: &WatchOptions, shutdown: Shutdown, tx: SyncSender<LogsMessage>) -> Result<(), WatchError> {
+    let id = options.id_or(path);
+    let encoding = options.encoding.initial();
+    let mut pipe = open_nonblocking(Path::new(path))
+        .inspect_err(|e| send_event(&tx, &id, SourceEvent::Error(format!("cannot open {}: {}", path, e))))?;
+    let mut pending = Vec::new();
+    let mut chunk = [0; 8192];
+    let mut connected = false;
+    while !shutdown.is_triggered() {
+        match pipe.read(&mut chunk) {
+            Ok(0) => {
+                // no writer, either one hasn't connected yet or it went away
+                if connected {
+                    connected = false;
+                    if !pending.is_empty() {
+                        send_lines(&tx, &id, options, encoding, vec![std::mem::take(&mut pending)]);
+                    }
+                    send_event(&tx, &id, SourceEvent::Notice(String::from("pipe writer disconnected")));
+                    pipe = open_nonblocking(Path::new(path))
+                        .inspect_err(|e| send_event(&tx, &id, SourceEvent::Error(format!("cannot reopen {}: {}", path, e))))?;
+                }
+                shutdown.wait_timeout(POLL_INTERVAL);
+            }
+            Ok(read) => {
+                if !connected {
+                    connected = true;
+                    send_event(&tx, &id, SourceEvent::Notice(String::from("pipe writer connected")));
+                }
+                pending.extend_from_slice(&chunk[..read]);
+                if let Some(end) = pending.iter().rposition(|b| *b == b'\n') {
+                    let rest = pending.split_off(end + 1);
+                    let mut complete = std::mem::replace(&mut pending, rest);
+                    complete.pop();
+                    let lines = complete.split(|b| *b == b'\n')
+                        .map(<[u8]>::to_vec)
+                        .collect();
+                    send_lines(&tx, &id, options, encoding, lines);
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                shutdown.wait_timeout(POLL_INTERVAL);
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => {
+                send_event(&tx, &id, SourceEvent::Error(format!("error reading {}: {}", path, e)));
+                return Err(e.into());
+            }
+        }
+    }
+    log::debug!("Stopped reading {}", path);
+    Ok(())
+}
+
+fn send_lines(tx: &SyncSender<LogsMessage>, id: &str, options: &WatchOptions, encoding: &'static encoding_rs::Encoding, raw_lines: Vec<Vec<u8>>) {
+    let lines: Vec<String> = raw_lines.iter()
+        .flat_map(|raw| options.clean_line(encoding.decode_without_bom_handling(raw).0.into_owned()))
+        .filter(|line| options.keeps(line))
+        .collect();
+    if lines.is_empty() {
+        return;
+    }
+    let msg = LogsMessage::new(id.to_owned(), SourceEvent::lines(lines, options));
+    if tx.send(msg).is_err() {
+        log::error!("Pipe reader {} failed to send", id);
+    }
+}
+
+/// Opening a pipe for reading normally blocks until there's a writer, which
+/// would stop the thread from noticing a shutdown
+#[cfg(unix)]
+fn open_nonblocking(path: &Path) -> Result<File, io::Error> {
+    use std::os::unix::fs::OpenOptionsExt;
+    std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)
+}
+
+#[cfg(not(unix))]
+fn open_nonblocking(path: &Path) -> Result<File, io::Error> {
+    File::open(path)
+}
@@ -0,0 +1,161 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
+use std::sync::mpsc::SyncSender;
+
+use flate2::read::MultiGzDecoder;
+
+use super::{decode_line, newline_bytes, read_line_bytes, send_event, LogsMessage, SourceEvent, Shutdown, Tail, WatchError, WatchOptions, READ_CHUNK_LINES};
+
+/// The first bytes of every gzip file
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Whether `path` is a gzip archive, e.g. a log rotated to `app.log.1.gz`,
+/// going by its extension or else its first bytes
+pub fn is_gzip(path: &str) -> bool {
+    if Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gz")) {
+        return true;
+    }
+    let mut magic = [0; 2];
+    File::open(path).and_then(|mut file| file.read_exact(&mut magic)).is_ok() && magic == GZIP_MAGIC
+}
+
+/// Reads the lines of a gzip archive as history, a chunk at a time, until
+/// the end or until `shutdown` is triggered. An archive doesn't change, so
+/// it's not watched afterwards. With `WatchOptions::tail` only the end of
+/// it is kept, though all of it still has to be decompressed to get there.
+///
+/// If the archive turns out to be damaged part way, the lines before that
+/// are kept and the error is reported after them.
+pub fn read_gzip(path: &str, options: &WatchOptions, shutdown: Shutdown, tx: SyncSender<LogsMessage>) -> Result<(), WatchError> {
+    let id = options.id_or(path);
+    if let Some(Tail::Nothing) = options.tail {
+        send_event(&tx, &id, SourceEvent::Notice(String::from("history skipped, nothing new is ever written to an archive")));
+        return Ok(());
+    }
+    let file = File::open(path)
+        .inspect_err(|e| send_event(&tx, &id, SourceEvent::Error(format!("cannot open {}: {}", path, e))))?;
+    let total = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let mut reader = BufReader::new(MultiGzDecoder::new(file));
+    let encoding = options.encoding.initial();
+    let newline = newline_bytes(encoding);
+    let mut tail = TailLines::new(options.tail);
+    let mut lines = Vec::new();
+    let mut read_lines = 0;
+    let mut invalid = 0;
+    let mut buf = Vec::new();
+    let mut error = None;
+    while !shutdown.is_triggered() {
+        buf.clear();
+        let read = match read_line_bytes(&mut reader, newline, &mut buf) {
+            Ok(read) => read,
+            Err(e) => {
+                error = Some(e);
+                break;
+            }
+        };
+        if read == 0 {
+            break;
+        }
+        if buf.ends_with(newline) {
+            buf.truncate(buf.len() - newline.len());
+        }
+        read_lines += 1;
+        match decode_line(&buf, encoding, options) {
+            Some(decoded) => lines.extend(decoded),
+            None => invalid += 1,
+        }
+        if read_lines % READ_CHUNK_LINES == 0 {
+            if let Some(chunk) = tail.push(std::mem::take(&mut lines)) {
+                if !send_lines(&tx, &id, options, chunk) {
+                    return Ok(());
+                }
+            }
+            // how far through the compressed file, it's all there is to go by
+            let done = reader.get_mut().get_mut().stream_position().unwrap_or(0);
+            send_event(&tx, &id, SourceEvent::Loading { done: done.min(total), total });
+        }
+    }
+    if shutdown.is_triggered() {
+        log::debug!("Stopped reading {}", path);
+        return Ok(());
+    }
+    send_lines(&tx, &id, options, tail.finish(lines));
+    if invalid > 0 {
+        send_event(&tx, &id, SourceEvent::InvalidLines { count: invalid, encoding: encoding.name() });
+    }
+    if read_lines >= READ_CHUNK_LINES {
+        send_event(&tx, &id, SourceEvent::Loading { done: total, total });
+    }
+    if let Some(e) = error {
+        send_event(&tx, &id, SourceEvent::Error(format!("error decompressing {}: {}, keeping the {} lines before it", path, e, read_lines)));
+        return Err(e.into());
+    }
+    send_event(&tx, &id, SourceEvent::Notice(format!("read {} lines from archive, not watching it for changes", read_lines)));
+    Ok(())
+}
+
+/// Sends the lines that get past the filters, after `TailLines` so that
+/// the tail is counted in lines of the archive, as it is for files. Returns
+/// false if the receiver is gone.
+fn send_lines(tx: &SyncSender<LogsMessage>, id: &str, options: &WatchOptions, mut lines: Vec<String>) -> bool {
+    lines.retain(|line| options.keeps(line));
+    if lines.is_empty() {
+        return true;
+    }
+    let msg = LogsMessage::new(id.to_owned(), SourceEvent::lines(lines, options));
+    if tx.send(msg).is_err() {
+        log::error!("Archive reader {} failed to send", id);
+        return false;
+    }
+    true
+}
+
+/// Holds on to the last lines of an archive for `WatchOptions::tail`, as
+/// where they start isn't known until it's all been read
+struct TailLines {
+    tail: Option<Tail>,
+    lines: VecDeque<String>,
+    bytes: u64,
+}
+
+impl TailLines {
+    fn new(tail: Option<Tail>) -> Self {
+        TailLines { tail, lines: VecDeque::new(), bytes: 0 }
+    }
+
+    /// Lines to send now, which is all of them unless only the tail is wanted
+    fn push(&mut self, lines: Vec<String>) -> Option<Vec<String>> {
+        let Some(tail) = self.tail else {
+            return Some(lines);
+        };
+        for line in lines {
+            self.bytes += line.len() as u64 + 1;
+            self.lines.push_back(line);
+        }
+        loop {
+            let over = match tail {
+                Tail::Nothing => true,
+                Tail::Lines(max) => self.lines.len() as u64 > max,
+                Tail::Bytes(max) => self.bytes > max,
+            };
+            if !over {
+                break;
+            }
+            let Some(line) = self.lines.pop_front() else {
+                break;
+            };
+            self.bytes -= line.len() as u64 + 1;
+        }
+        None
+    }
+
+    /// The lines to send once the whole archive has been read
+    fn finish(&mut self, lines: Vec<String>) -> Vec<String> {
+        match self.push(lines) {
+            Some(lines) => lines,
+            None => std::mem::take(&mut self.lines).into(),
+        }
+    }
+}
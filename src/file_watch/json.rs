@@ -0,0 +1,92 @@
+use std::str::FromStr;
+
+use serde_json::{Map, Value};
+
+/// How JSON lines are shown: a template with `{field}` placeholders, where a
+/// field can be a dotted path into nested objects, e.g. `{error.kind}`.
+/// Without a template every top-level field is shown as `key=value`.
+#[derive(Clone, Debug, Default)]
+pub struct JsonFormat {
+    template: Option<Vec<Part>>,
+}
+
+#[derive(Clone, Debug)]
+enum Part {
+    Text(String),
+    Field(Vec<String>),
+}
+
+impl FromStr for JsonFormat {
+    type Err = String;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let mut parts = vec![];
+        let mut rest = template;
+        while let Some(open) = rest.find('{') {
+            if open > 0 {
+                parts.push(Part::Text(rest[..open].to_owned()));
+            }
+            let Some(close) = rest[open..].find('}') else {
+                return Err(format!("unclosed '{{' in '{}'", template));
+            };
+            let field = &rest[open + 1..open + close];
+            if field.is_empty() {
+                return Err(format!("empty field name in '{}'", template));
+            }
+            parts.push(Part::Field(field.split('.').map(str::to_owned).collect()));
+            rest = &rest[open + close + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_owned()));
+        }
+        Ok(JsonFormat { template: Some(parts) })
+    }
+}
+
+impl JsonFormat {
+    /// The line as it should be shown, or None if it isn't a JSON object
+    pub fn render(&self, line: &str) -> Option<String> {
+        if !line.trim_start().starts_with('{') {
+            return None;
+        }
+        let Ok(Value::Object(object)) = serde_json::from_str::<Value>(line) else {
+            return None;
+        };
+        let Some(parts) = &self.template else {
+            return Some(render_all(&object));
+        };
+        let mut rendered = String::new();
+        for part in parts {
+            match part {
+                Part::Text(text) => rendered.push_str(text),
+                Part::Field(path) => {
+                    if let Some(value) = lookup(&object, path) {
+                        rendered.push_str(&value_text(value));
+                    }
+                }
+            }
+        }
+        Some(rendered)
+    }
+}
+
+fn lookup<'a>(object: &'a Map<String, Value>, path: &[String]) -> Option<&'a Value> {
+    let (first, rest) = path.split_first()?;
+    rest.iter().try_fold(object.get(first)?, |value, key| value.get(key))
+}
+
+/// Strings without their quotes, anything else as JSON
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn render_all(object: &Map<String, Value>) -> String {
+    object.iter()
+        .map(|(key, value)| format!("{}={}", key, value_text(value)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,87 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+/// Finds level names in upper case, `level=...` fields in any case, and
+/// syslog `<PRI>` prefixes
+static DEFAULT_LEVEL_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^<(\d{1,3})>|\b(TRACE|DEBUG|INFO|NOTICE|WARN|WARNING|ERROR|ERR|CRIT|CRITICAL|ALERT|EMERG|FATAL|PANIC)\b|\b(?i:level)"?\s*[=:]\s*"?([A-Za-z]+)"#)
+        .expect("default level regex is valid")
+});
+
+/// Severity of a log line, least severe first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl Level {
+    /// Every level, least severe first
+    pub const ALL: [Level; 6] = [Level::Trace, Level::Debug, Level::Info, Level::Warn, Level::Error, Level::Fatal];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Level::Trace => "TRACE",
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warn => "WARN",
+            Level::Error => "ERROR",
+            Level::Fatal => "FATAL",
+        }
+    }
+
+    /// The level of `line`, from the first group of `regex` that matched, or
+    /// the whole match if it has no groups. `regex` defaults to one that
+    /// finds the usual level names.
+    pub fn detect(line: &str, regex: Option<&Regex>) -> Option<Level> {
+        let regex = regex.unwrap_or(&DEFAULT_LEVEL_REGEX);
+        let captures = regex.captures(line)?;
+        let text = captures.iter().skip(1).flatten().next().or_else(|| captures.get(0))?;
+        text.as_str().parse().ok()
+    }
+
+    /// From the severity in a syslog priority, which is `facility * 8 + severity`
+    fn from_syslog_priority(priority: u8) -> Level {
+        match priority & 7 {
+            0..=2 => Level::Fatal,
+            3 => Level::Error,
+            4 => Level::Warn,
+            5 | 6 => Level::Info,
+            _ => Level::Debug,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    /// A level name in any case, including syslog's and single letters like
+    /// logcat's, or a syslog priority
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(priority) = s.parse::<u8>() {
+            return Ok(Level::from_syslog_priority(priority));
+        }
+        match s.to_ascii_uppercase().as_str() {
+            "TRACE" | "T" | "V" => Ok(Level::Trace),
+            "DEBUG" | "D" => Ok(Level::Debug),
+            "INFO" | "I" | "NOTICE" => Ok(Level::Info),
+            "WARN" | "W" | "WARNING" => Ok(Level::Warn),
+            "ERROR" | "E" | "ERR" => Ok(Level::Error),
+            "FATAL" | "F" | "CRIT" | "CRITICAL" | "ALERT" | "EMERG" | "PANIC" => Ok(Level::Fatal),
+            _ => Err(format!("unknown level '{}'", s)),
+        }
+    }
+}
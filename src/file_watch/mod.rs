@@ -1,138 +1,1944 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::{BufRead, BufReader, Seek};
+use std::io::{BufRead, BufReader, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::{fs, io};
-use std::sync::mpsc::Sender;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender, SyncSender};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime};
 
-use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+mod ansi;
+mod builder;
+mod error;
+mod exec;
+mod fifo;
+mod gzip;
+mod json;
+mod level;
+mod offsets;
+mod rate_limit;
+mod stdin;
+mod syslog;
+mod tcp;
+mod timestamp;
+
+pub use ansi::{split_sgr, strip_ansi};
+pub use builder::{FileWatcherBuilder, WatchHandle};
+pub use error::WatchError;
+pub use exec::{kill, shell_command, watch_exec, EXEC_PREFIX};
+pub use fifo::{is_fifo, watch_fifo};
+pub use gzip::{is_gzip, read_gzip};
+pub use json::JsonFormat;
+pub use level::Level;
+pub use rate_limit::thousands;
+pub use stdin::{watch_stdin, STDIN_ID};
+pub use syslog::{watch_syslog, SYSLOG_PREFIX};
+pub use tcp::{watch_tcp, TCP_PREFIX};
+pub use timestamp::TimestampFormat;
+
+use encoding_rs::Encoding;
+use regex::Regex;
+use notify::{PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use offsets::SavedOffsets;
+use rate_limit::RateLimit;
 
 pub struct LogsMessage {
-    pub lines: Vec<String>,
     pub file_id: String,
+    /// When the lines were read, or the event happened
+    pub received_at: SystemTime,
+    pub event: SourceEvent,
 }
 
-pub fn watch_file(path: &String, tx: Sender<LogsMessage>) -> Result<RecommendedWatcher, io::Error> {
-    let mut file_handle = fs::File::open(path)
-        .unwrap();
-    let id = path.clone();
+impl LogsMessage {
+    pub fn new(file_id: String, event: SourceEvent) -> Self {
+        LogsMessage { file_id, received_at: SystemTime::now(), event }
+    }
+}
 
-    // first event, read existing file
-    let file_len = file_handle.metadata().unwrap().len();
-    let result = get_lines_for_interval(&mut file_handle, 0, file_len);
-    let last_read = match result {
-        Some(lines) => {
-            let msg = LogsMessage {
-                file_id: id.clone(),
-                lines: lines,
-            };
-            match tx.send(msg) {
-                Ok(_) => { file_len },
-                Err(_) => { log::error!("File event handler {} failed to send", &id); 0 }
+/// What a watcher has to report: lines read from its source, or something
+/// that happened to the source itself
+#[derive(Debug)]
+pub enum SourceEvent {
+    Lines {
+        lines: Vec<String>,
+        /// The lines as they should be shown, if that's not as they are,
+        /// e.g. fields picked out of JSON
+        display: Option<Vec<String>>,
+        /// When each line says it was logged, in milliseconds since the
+        /// epoch, if `WatchOptions::timestamp` is set and it could be parsed
+        timestamps: Option<Vec<Option<i64>>>,
+        /// The severity of each line, if one could be found in it
+        levels: Vec<Option<Level>>,
+    },
+    /// The file got shorter, so it's read again from the start
+    Truncated(u64),
+    /// The file was truncated and written past its old length in between two
+    /// reads, so it's read again from the start
+    Rewritten,
+    /// Another file was moved to the watched path
+    Rotated,
+    /// The file at the watched path was deleted
+    Removed,
+    /// The file at the watched path was created again after being deleted
+    Recreated,
+    /// The path now refers to another file, without an event saying so
+    Replaced,
+    /// The watched path is a symlink that now points to another file
+    Retargeted,
+    /// Lines that weren't valid in the file's encoding were dropped, see
+    /// `WatchOptions::strict_utf8`
+    InvalidLines { count: u64, encoding: &'static str },
+    /// Something went wrong reading the source
+    Error(String),
+    /// Anything else worth knowing, e.g. a connection being opened
+    Notice(String),
+    /// How much has been read from the source so far, sent now and then
+    Stats(ReadStats),
+    /// How far through what was already in the file the watcher has read,
+    /// in bytes, sent after each chunk when that takes more than one
+    Loading { done: u64, total: u64 },
+}
+
+/// Running totals of what's been read from a file
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ReadStats {
+    pub lines: u64,
+    pub bytes: u64,
+    /// When lines were last read
+    pub last_line_at: Option<SystemTime>,
+}
+
+impl SourceEvent {
+    fn lines(lines: Vec<String>, options: &WatchOptions) -> Self {
+        SourceEvent::Lines {
+            display: options.display(&lines),
+            timestamps: options.timestamp.as_ref()
+                .map(|format| lines.iter().map(|line| format.parse(&strip_ansi(line))).collect()),
+            levels: lines.iter().map(|line| Level::detect(&strip_ansi(line), options.level_regex.as_ref())).collect(),
+            lines,
+        }
+    }
+
+    /// Short name for the kind of event, as stored in the db. None for lines.
+    pub fn kind(&self) -> Option<&'static str> {
+        match self {
+            SourceEvent::Lines { .. } => None,
+            SourceEvent::Truncated(_) => Some("truncated"),
+            SourceEvent::Rewritten => Some("rewritten"),
+            SourceEvent::Rotated => Some("rotated"),
+            SourceEvent::Removed => Some("removed"),
+            SourceEvent::Recreated => Some("recreated"),
+            SourceEvent::Replaced => Some("replaced"),
+            SourceEvent::Retargeted => Some("retargeted"),
+            SourceEvent::InvalidLines { .. } => Some("invalid"),
+            SourceEvent::Error(_) => Some("error"),
+            SourceEvent::Notice(_) => Some("notice"),
+            SourceEvent::Stats(_) => Some("stats"),
+            SourceEvent::Loading { .. } => Some("loading"),
+        }
+    }
+
+    /// What happened, for showing to the user
+    pub fn describe(&self) -> String {
+        match self {
+            SourceEvent::Lines { lines, .. } => format!("{} lines", lines.len()),
+            SourceEvent::Truncated(len) => format!("file rotated (truncated to {} bytes)", len),
+            SourceEvent::Rewritten => String::from("file rewritten"),
+            SourceEvent::Rotated => String::from("file rotated"),
+            SourceEvent::Removed => String::from("file removed, waiting for recreation"),
+            SourceEvent::Recreated => String::from("file recreated"),
+            SourceEvent::Replaced => String::from("file replaced"),
+            SourceEvent::Retargeted => String::from("symlink retargeted"),
+            SourceEvent::InvalidLines { count: 1, encoding } => format!("dropped a line that is not valid {}", encoding),
+            SourceEvent::InvalidLines { count, encoding } => format!("dropped {} lines that are not valid {}", count, encoding),
+            SourceEvent::Error(text) | SourceEvent::Notice(text) => text.clone(),
+            SourceEvent::Stats(stats) => format!("{} lines, {} bytes read", stats.lines, stats.bytes),
+            SourceEvent::Loading { done, total } => format!("loading: {}%", done * 100 / (*total).max(1)),
+        }
+    }
+}
+
+/// How changes to watched files are detected
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    /// The platform's native notifications (inotify, FSEvents, ...)
+    Native,
+    /// Scan for changes at a fixed interval. Needed for network filesystems
+    /// (NFS, SSHFS) that don't deliver native notifications
+    Poll(Duration),
+}
+
+impl Backend {
+    fn new_watcher<H: notify::EventHandler>(self, event_handler: H) -> notify::Result<Box<dyn Watcher + Send>> {
+        match self {
+            Backend::Native => {
+                let watcher = RecommendedWatcher::new(event_handler, notify::Config::default())?;
+                Ok(Box::new(watcher))
+            }
+            Backend::Poll(interval) => {
+                let config = notify::Config::default().with_poll_interval(interval);
+                let watcher = PollWatcher::new(event_handler, config)?;
+                Ok(Box::new(watcher))
             }
         }
-        None => 0   
-    };
+    }
+}
+
+/// Tells watcher threads to stop. Clones share the same signal.
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    signal: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl Shutdown {
+    pub fn trigger(&self) {
+        let (lock, cvar) = &*self.signal;
+        *lock.lock().unwrap_or_else(PoisonError::into_inner) = true;
+        cvar.notify_all();
+    }
+
+    /// Block the current thread until `trigger` is called
+    pub fn wait(&self) {
+        let (lock, cvar) = &*self.signal;
+        let _stopped = cvar.wait_while(lock.lock().unwrap_or_else(PoisonError::into_inner), |stopped| !*stopped)
+            .unwrap_or_else(PoisonError::into_inner);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        let (lock, _) = &*self.signal;
+        *lock.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Like `wait`, but gives up after `timeout`. Returns true if triggered.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let (lock, cvar) = &*self.signal;
+        let (stopped, _) = cvar.wait_timeout_while(lock.lock().unwrap_or_else(PoisonError::into_inner), timeout, |stopped| !*stopped)
+            .unwrap_or_else(PoisonError::into_inner);
+        *stopped
+    }
+}
+
+/// Options that apply to every watched file
+#[derive(Clone, Debug)]
+pub struct WatchOptions {
+    pub backend: Backend,
+    /// Also watch files in subdirectories of watched directories
+    pub recursive: bool,
+    /// Keep retrying files that can't be opened until they appear
+    pub wait: bool,
+    /// How long a line without a trailing newline is held back, waiting for
+    /// the rest of it, before it's sent as is
+    pub partial_flush: Duration,
+    /// After a file is read, further modify events are coalesced into one
+    /// read at the end of this window
+    pub debounce: Duration,
+    /// Drop lines that aren't valid in the file's encoding, rather than
+    /// showing them with replacement characters
+    pub strict_utf8: bool,
+    pub encoding: TextEncoding,
+    /// Restart `exec:` commands when they exit
+    pub exec_restart: bool,
+    /// Only read the end of what's already in a file when it's first opened
+    pub tail: Option<Tail>,
+    /// Tag lines with this instead of the source's path or address
+    pub label: Option<String>,
+    /// Show JSON lines through this format rather than as they are
+    pub json: Option<JsonFormat>,
+    /// Parse the time each line was logged with this, for ordering lines
+    /// from different sources
+    pub timestamp: Option<TimestampFormat>,
+    /// Finds the severity of a line: the first group that matched, or the
+    /// whole match, is read as a level name or syslog priority. None uses
+    /// a regex that finds the usual level names.
+    pub level_regex: Option<Regex>,
+    /// Only lines matching one of these are kept, if there are any
+    pub filters: Vec<Regex>,
+    /// Lines matching any of these are dropped
+    pub excludes: Vec<Regex>,
+    /// Drop empty lines rather than showing them
+    pub skip_blank: bool,
+    pub carriage_returns: CarriageReturns,
+    /// Longer lines are cut short, so one huge line can't fill up the db
+    pub max_line_bytes: usize,
+    /// Read files that look binary anyway, with control characters replaced
+    pub force_binary: bool,
+    /// Leave terminal escape sequences, e.g. colors, in lines rather than
+    /// stripping them. Filters, levels and timestamps still see the lines
+    /// without them.
+    pub keep_ansi: bool,
+    /// Lines that don't match are continuations of the line before, and are
+    /// sent together with it as one multi-line record
+    pub multiline_start: Option<Regex>,
+    /// Keep reading the file that was opened, even once it's renamed or
+    /// deleted, rather than following whatever file is at the path (`tail -f`
+    /// rather than `tail -F`)
+    pub follow_descriptor: bool,
+    /// Save how far each file was read to this file, and when a file is
+    /// opened, carry on from where it was read to before if it's the same
+    /// file, rather than reading it from the start or `tail`
+    pub resume: Option<PathBuf>,
+    /// Drop lines from a file past this many a second, with bursts of up
+    /// to a second's worth let through. What's in the file when it's first
+    /// opened isn't limited.
+    pub max_lines_per_sec: Option<u32>,
+}
+
+/// The same defaults as the command line
+impl Default for WatchOptions {
+    fn default() -> Self {
+        WatchOptions {
+            backend: Backend::Native,
+            recursive: false,
+            wait: false,
+            partial_flush: Duration::from_millis(1000),
+            debounce: Duration::from_millis(50),
+            strict_utf8: false,
+            encoding: TextEncoding::Fixed(encoding_rs::UTF_8),
+            exec_restart: false,
+            tail: None,
+            label: None,
+            json: None,
+            timestamp: None,
+            level_regex: None,
+            filters: vec![],
+            excludes: vec![],
+            skip_blank: false,
+            carriage_returns: CarriageReturns::default(),
+            max_line_bytes: 64 * 1024,
+            force_binary: false,
+            keep_ansi: false,
+            multiline_start: None,
+            follow_descriptor: false,
+            resume: None,
+            max_lines_per_sec: None,
+        }
+    }
+}
+
+impl WatchOptions {
+    /// The id lines are tagged with: the label if there is one, otherwise `default`
+    fn id_or(&self, default: &str) -> String {
+        self.label.clone().unwrap_or_else(|| default.to_owned())
+    }
+
+    /// Strip terminal escape sequences unless `keep_ansi` is set, strip the
+    /// `\r` of a `\r\n` line ending, and handle any `\r` left in the line
+    /// according to `carriage_returns`
+    fn clean_line(&self, mut line: String) -> Vec<String> {
+        if !self.keep_ansi {
+            if let Cow::Owned(stripped) = strip_ansi(&line) {
+                line = stripped;
+            }
+        }
+        if line.ends_with('\r') {
+            line.pop();
+        }
+        if !line.contains('\r') {
+            return vec![line];
+        }
+        match self.carriage_returns {
+            CarriageReturns::Keep => vec![line],
+            CarriageReturns::Split => line.split('\r').map(str::to_owned).collect(),
+            CarriageReturns::Last => line.rsplit('\r').next().map(str::to_owned).into_iter().collect(),
+        }
+    }
+
+    /// Whether `line` gets past `skip_blank`, `filters` and `excludes`
+    fn keeps(&self, line: &str) -> bool {
+        if line.is_empty() {
+            return !self.skip_blank;
+        }
+        let line = &*strip_ansi(line);
+        (self.filters.is_empty() || self.filters.iter().any(|f| f.is_match(line)))
+            && !self.excludes.iter().any(|e| e.is_match(line))
+    }
+
+    /// `lines` as they should be shown, see `LogsMessage::display`. Lines
+    /// that can't be rendered are shown as they are.
+    fn display(&self, lines: &[String]) -> Option<Vec<String>> {
+        let format = self.json.as_ref()?;
+        Some(lines.iter()
+            .map(|line| format.render(line).unwrap_or_else(|| line.clone()))
+            .collect())
+    }
+}
+
+/// What to do with a `\r` in the middle of a line, as written by progress bars
+/// that redraw themselves. A `\r` right before the newline is always removed.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum CarriageReturns {
+    /// Only keep what comes after the last one, which is what a terminal shows
+    #[default]
+    Last,
+    /// Treat each one as a line break
+    Split,
+    /// Leave them in
+    Keep,
+}
+
+impl FromStr for CarriageReturns {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "last" => Ok(CarriageReturns::Last),
+            "split" => Ok(CarriageReturns::Split),
+            "keep" => Ok(CarriageReturns::Keep),
+            _ => Err(format!("unknown mode '{}', expected last, split or keep", s)),
+        }
+    }
+}
+
+/// How much of a file's existing content to read
+#[derive(Clone, Copy, Debug)]
+pub enum Tail {
+    /// Only read what's written from now on
+    Nothing,
+    Lines(u64),
+    /// Rounded up to the start of the next line
+    Bytes(u64),
+}
+
+/// How often a file's `ReadStats` are sent, if they changed
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Chunk size for scanning backwards from the end of a file
+const TAIL_CHUNK_SIZE: u64 = 64 * 1024;
+
+/// How much of a file, or of what's appended to it, is looked at to decide
+/// whether it's binary
+const BINARY_SNIFF_LEN: u64 = 8 * 1024;
+
+/// At most this many lines are read at a time, so a large file is read in
+/// chunks, with events for other files and a check for shutdown in between
+const READ_CHUNK_LINES: usize = 10_000;
+
+/// How many bytes before the cursor are compared to notice a file that was
+/// truncated and rewritten
+const TAIL_CHECK_LEN: u64 = 64;
+
+/// Character encoding of a watched file
+#[derive(Clone, Copy, Debug)]
+pub enum TextEncoding {
+    /// Use the file's byte order mark, falling back to UTF-8 when it has none
+    Auto,
+    Fixed(&'static Encoding),
+}
+
+impl FromStr for TextEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(TextEncoding::Auto);
+        }
+        // accept e.g. "utf16le" as well as the standard "utf-16le"
+        let label = s.to_ascii_lowercase()
+            .replace("utf16", "utf-16")
+            .replace("utf8", "utf-8");
+        Encoding::for_label(label.as_bytes())
+            .map(TextEncoding::Fixed)
+            .ok_or_else(|| format!("unknown encoding '{}'", s))
+    }
+}
 
-    let event_handler = FileEventHandler {
-        file_handle, tx,
-        id: id,
-        last_read_file_pos: last_read,
+impl TextEncoding {
+    /// The encoding to use until a byte order mark says otherwise
+    fn initial(self) -> &'static Encoding {
+        match self {
+            TextEncoding::Auto => encoding_rs::UTF_8,
+            TextEncoding::Fixed(encoding) => encoding,
+        }
+    }
+}
+
+/// A good capacity for the channel watchers send on, in messages. Each is a
+/// batch of lines, so this is plenty to smooth over a slow receiver.
+pub const DEFAULT_CHANNEL_CAPACITY: usize = 1024;
+
+/// How often watcher threads check whether they should stop
+const SHUTDOWN_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often pending files (see `WatchOptions::wait`) are tried again
+const WAIT_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often directories that couldn't be watched are tried again. Files in
+/// them are read at the same interval in the meantime.
+const WATCH_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How often read offsets are saved, see `WatchOptions::resume`. They're
+/// saved on shutdown too.
+const OFFSETS_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Tails `path` until `shutdown` is triggered, see `watch_files`.
+pub fn watch_file(path: &str, options: &WatchOptions, shutdown: Shutdown, tx: SyncSender<LogsMessage>) -> Result<(), WatchError> {
+    watch_files(&[(path.to_owned(), options.clone())], shutdown, tx)
+}
+
+/// Watches every file in `dir`, including files created after startup, see
+/// `watch_files`.
+pub fn watch_dir(dir: &str, options: &WatchOptions, shutdown: Shutdown, tx: SyncSender<LogsMessage>) -> Result<(), WatchError> {
+    watch_files(&[(dir.to_owned(), options.clone())], shutdown, tx)
+}
+
+/// Tails every file and directory in `sources` from the calling thread, with
+/// one watcher per backend however many there are, until `shutdown` is
+/// triggered.
+///
+/// Files found in a directory are tagged with their path, or with
+/// `<label>/<path in dir>` if the directory has a label.
+///
+/// Problems opening or watching a source are reported through `tx`, as the
+/// caller's terminal is usually taken over by the UI, and the other sources
+/// are still watched. An error is only returned if none of them could be.
+///
+/// Sending blocks while `tx` is full, so a receiver that falls behind holds
+/// back reading rather than letting messages pile up in memory. That goes for
+/// every `watch_*` function: keep receiving, or drop the receiver, until the
+/// watcher has stopped.
+pub fn watch_files(sources: &[(String, WatchOptions)], shutdown: Shutdown, tx: SyncSender<LogsMessage>) -> Result<(), WatchError> {
+    let (event_tx, event_rx) = mpsc::channel();
+    let mut event_handler = FileEventHandler {
+        tx,
+        event_tx,
+        watchers: vec![],
+        files: HashMap::new(),
+        canonical_paths: HashMap::new(),
+        link_targets: HashMap::new(),
+        dirs: vec![],
+        pending: vec![],
+        pending_checked_at: Instant::now(),
+        unwatched: vec![],
+        watch_retried_at: Instant::now(),
+        // one file for all sources, they're all given the same one
+        offsets: sources.iter()
+            .find_map(|(_, options)| options.resume.as_deref())
+            .map(SavedOffsets::load),
+        offsets_saved_at: Instant::now(),
     };
+    let mut first_error = None;
+    for (path, options) in sources {
+        let result = if fs::metadata(path).is_ok_and(|m| m.is_dir()) {
+            event_handler.add_dir(path, options)
+        } else {
+            event_handler.add_file(path, options)
+        };
+        if let Err(e) = result {
+            log::error!("Error watching {}: {}", path, e);
+            first_error.get_or_insert(e);
+        }
+    }
+    if let Some(e) = first_error.filter(|_| event_handler.is_empty()) {
+        return Err(e);
+    }
 
-    let mut watcher = RecommendedWatcher::new(event_handler, notify::Config::default())
-        .unwrap();
-    watcher.watch(path.as_ref(), RecursiveMode::NonRecursive)
-        .unwrap();
+    while !shutdown.is_triggered() {
+        match event_rx.recv_timeout(event_handler.next_timeout()) {
+            Ok(event) => event_handler.handle_event(event),
+            Err(RecvTimeoutError::Timeout) => {}
+            // the handler keeps a sender for new watchers, so this won't happen
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        event_handler.handle_timeouts();
+    }
+    // stop the watchers before the files they report on are closed
+    event_handler.watchers.clear();
+    event_handler.save_offsets();
+    log::debug!("Stopped watching {} files", event_handler.files.len());
+    Ok(())
+}
 
-    loop {}
+/// Report something that happened to a source, rather than lines read from it
+fn send_event(tx: &SyncSender<LogsMessage>, id: &str, event: SourceEvent) {
+    if tx.send(LogsMessage::new(id.to_owned(), event)).is_err() {
+        log::error!("File event handler {} failed to send (event)", id);
+    }
 }
 
+fn list_files(dir: &Path, recursive: bool) -> Result<Vec<PathBuf>, io::Error> {
+    let mut files = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() {
+            files.push(path);
+        }
+        else if recursive && path.is_dir() {
+            files.extend(list_files(&path, recursive)?);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
 
+/// Receives the events of every watcher and passes them on to the file they
+/// are about. Files are keyed by their absolute path, which is what the
+/// watchers report as they're given absolute paths to watch.
 struct FileEventHandler {
-    id: String,
-    tx: Sender<LogsMessage>,
-    file_handle: File,
-    last_read_file_pos: u64
+    tx: SyncSender<LogsMessage>,
+    /// Given to each new watcher
+    event_tx: Sender<notify::Result<notify::Event>>,
+    watchers: Vec<BackendWatcher>,
+    files: HashMap<PathBuf, FileState>,
+    /// Keys of `files` by their path with symlinks resolved, for platforms
+    /// that report events that way (FSEvents)
+    canonical_paths: HashMap<PathBuf, PathBuf>,
+    /// Keys of `files` that are symlinks, by the file they point to, whose
+    /// writes are reported under its own path
+    link_targets: HashMap<PathBuf, PathBuf>,
+    dirs: Vec<WatchedDir>,
+    /// Files that can't be opened yet, with `WatchOptions::wait`
+    pending: Vec<(String, WatchOptions)>,
+    pending_checked_at: Instant,
+    /// Directories the watchers refused, e.g. over the inotify watch limit,
+    /// with their backend and whether to watch them recursively
+    unwatched: Vec<(Backend, PathBuf, bool)>,
+    watch_retried_at: Instant,
+    /// With `WatchOptions::resume`
+    offsets: Option<SavedOffsets>,
+    offsets_saved_at: Instant,
+}
+
+/// One watcher per backend, and the directories it's been asked to watch
+struct BackendWatcher {
+    backend: Backend,
+    watcher: Box<dyn Watcher + Send>,
+    /// Whether each is watched recursively
+    dirs: HashMap<PathBuf, bool>,
+}
+
+/// A directory whose new files are watched as they're created
+struct WatchedDir {
+    /// As given, new files are tagged with paths under it
+    path: PathBuf,
+    absolute: PathBuf,
+    canonical: Option<PathBuf>,
+    options: WatchOptions,
 }
 
-impl notify::EventHandler for FileEventHandler {
+impl FileEventHandler {
+    fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.dirs.is_empty() && self.pending.is_empty()
+    }
+
+    fn add_file(&mut self, path: &String, options: &WatchOptions) -> Result<(), WatchError> {
+        let id = options.id_or(path);
+        let key = absolute_path(Path::new(path));
+        if self.files.contains_key(&key) {
+            return Ok(());
+        }
+        let file_handle = match fs::File::open(path) {
+            Ok(file_handle) => file_handle,
+            Err(e) if options.wait => {
+                log::debug!("Waiting for {} to be openable: {}", path, e);
+                self.pending.push((path.clone(), options.clone()));
+                return Ok(());
+            }
+            Err(e) => {
+                send_event(&self.tx, &id, SourceEvent::Error(format!("cannot open {}: {}", path, e)));
+                return Err(WatchError::Open { path: path.clone(), source: e });
+            }
+        };
+
+        let mut file = FileState {
+            reader: BufReader::new(file_handle),
+            tx: self.tx.clone(),
+            id,
+            path: PathBuf::from(path),
+            last_read_file_pos: 0,
+            removed: false,
+            identity: None,
+            link_target: link_target(Path::new(path)),
+            replace_checked_at: Instant::now(),
+            tail_bytes: Vec::new(),
+            record: None,
+            record_since: None,
+            binary: false,
+            stats: ReadStats::default(),
+            stats_sent: ReadStats::default(),
+            stats_sent_at: Instant::now(),
+            partial_since: None,
+            last_read_at: None,
+            read_pending: false,
+            encoding: options.encoding.initial(),
+            rate_limit: None,
+            more_to_read: false,
+            loading: None,
+            options: options.clone(),
+        };
+        let filter_count = options.filters.len() + options.excludes.len();
+        if filter_count > 0 {
+            file.send_event(SourceEvent::Notice(format!("{} filter{} active", filter_count, if filter_count == 1 { "" } else { "s" })));
+        }
+        // first event, read existing file
+        file.identity = file.reader.get_ref().metadata().ok().and_then(|m| file_identity(&m));
+        let resume_pos = self.offsets.as_ref()
+            .filter(|_| options.resume.is_some())
+            .zip(file.identity)
+            .and_then(|(offsets, identity)| offsets.get(&key, identity))
+            .filter(|pos| file.reader.get_ref().metadata().is_ok_and(|m| m.len() >= *pos));
+        if let Some(pos) = resume_pos {
+            if let Err(e) = file.skip_bom().and_then(|_| file.seek_to(pos)) {
+                file.send_event(SourceEvent::Error(format!("error reading {}: {}", path, e)));
+            }
+            file.send_event(SourceEvent::Notice(format!("resuming from byte {}", pos)));
+        } else if let Some(tail) = options.tail {
+            file.skip_history(tail);
+        }
+        // only the first chunk, the rest is read in between handling events
+        file.start_loading();
+        file.read_new_lines(false);
+
+        // watch the parent directory rather than the file, so that a file
+        // replaced by log rotation is noticed and reopened
+        let dir = parent_dir(&key).to_owned();
+        if let Err(e) = self.watch(options.backend, &dir, false) {
+            // still read now and then, see `retry_unwatched`
+            file.send_event(SourceEvent::Error(format!("cannot watch {}: {}", path, watch_error_text(&e))));
+        }
+        if let Some(canonical) = fs::canonicalize(&dir).ok().zip(key.file_name()).map(|(dir, name)| dir.join(name)) {
+            if canonical != key {
+                self.canonical_paths.insert(canonical, key.clone());
+            }
+        }
+        self.files.insert(key.clone(), file);
+        self.watch_link_target(&key);
+        Ok(())
+    }
+
+    /// Watch the file that the symlink at `key` points to, as writes to it
+    /// don't show up as events for the symlink. Done again once the symlink
+    /// points somewhere else.
+    fn watch_link_target(&mut self, key: &PathBuf) {
+        let Some(file) = self.files.get(key) else {
+            return;
+        };
+        let Some(target) = file.link_target.clone() else {
+            return;
+        };
+        if self.link_targets.get(&target) == Some(key) {
+            return;
+        }
+        self.link_targets.retain(|_, k| k != key);
+        self.link_targets.insert(target.clone(), key.clone());
+        let backend = file.options.backend;
+        if let Err(e) = self.watch(backend, parent_dir(&target), false) {
+            if let Some(file) = self.files.get_mut(key) {
+                file.send_event(SourceEvent::Error(format!("cannot watch {}: {}", target.display(), watch_error_text(&e))));
+            }
+        }
+    }
+
+    fn add_dir(&mut self, dir: &String, options: &WatchOptions) -> Result<(), WatchError> {
+        let id = options.id_or(dir);
+        let files = list_files(Path::new(dir), options.recursive)
+            .inspect_err(|e| send_event(&self.tx, &id, SourceEvent::Error(format!("cannot list {}: {}", dir, e))))?;
+        let watched_dir = WatchedDir {
+            path: PathBuf::from(dir),
+            absolute: absolute_path(Path::new(dir)),
+            canonical: fs::canonicalize(dir).ok(),
+            options: options.clone(),
+        };
+        for path in files {
+            let (path, options) = watched_dir.file_source(&path);
+            // errors are reported on the channel, and don't stop the others
+            let _ = self.add_file(&path, &options);
+        }
+
+        if let Err(e) = self.watch(options.backend, &watched_dir.absolute.clone(), options.recursive) {
+            send_event(&self.tx, &id, SourceEvent::Error(format!("cannot watch {}: {}", dir, watch_error_text(&e))));
+        }
+        self.dirs.push(watched_dir);
+        Ok(())
+    }
+
+    /// Watch `dir` with the watcher for `backend`, unless it's already
+    /// covered by an earlier call. Watching a path a second time replaces
+    /// the first watch, so a recursive watch is never made non-recursive.
+    ///
+    /// If that fails the directory is added to `unwatched`, to be tried again.
+    fn watch(&mut self, backend: Backend, dir: &Path, recursive: bool) -> notify::Result<()> {
+        let result = self.try_watch(backend, dir, recursive);
+        if result.is_err() && !self.unwatched.iter().any(|(b, d, r)| *b == backend && d == dir && *r == recursive) {
+            self.unwatched.push((backend, dir.to_owned(), recursive));
+        }
+        result
+    }
+
+    fn try_watch(&mut self, backend: Backend, dir: &Path, recursive: bool) -> notify::Result<()> {
+        let index = match self.watchers.iter().position(|w| w.backend == backend) {
+            Some(index) => index,
+            None => {
+                let watcher = backend.new_watcher(self.event_tx.clone())?;
+                self.watchers.push(BackendWatcher { backend, watcher, dirs: HashMap::new() });
+                self.watchers.len() - 1
+            }
+        };
+        let watcher = &mut self.watchers[index];
+        let covered = watcher.dirs.get(dir).is_some_and(|r| *r || !recursive)
+            || (!recursive && dir.ancestors().skip(1).any(|a| watcher.dirs.get(a) == Some(&true)));
+        if covered {
+            return Ok(());
+        }
+        let mode = if recursive { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+        watcher.watcher.watch(dir, mode)?;
+        watcher.dirs.insert(dir.to_owned(), recursive);
+        Ok(())
+    }
+
+    /// The key in `files` of the file at `path`, if it's watched
+    fn file_key(&self, path: &Path) -> Option<&PathBuf> {
+        match self.files.get_key_value(path) {
+            Some((key, _)) => Some(key),
+            None => self.canonical_paths.get(path),
+        }
+    }
+
     fn handle_event(&mut self, event: notify::Result<notify::Event>) {
-        if !should_handle_event(&event) {
-            log::debug!("Skip Event: {:?}", event);
+        let event = match event {
+            Ok(event) => event,
+            Err(error) => {
+                // e.g. the watch limit being reached while a recursive watch
+                // adds a new subdirectory
+                log::error!("Event error: {:?}", error);
+                let id = error.paths.first()
+                    .map_or_else(|| String::from("filewatch"), |path| path.display().to_string());
+                send_event(&self.tx, &id, SourceEvent::Error(watch_error_text(&error)));
+                return;
+            }
+        };
+        if let [path] = event.paths.as_slice() {
+            if let Some(key) = self.link_targets.get(path) {
+                // only writes matter, what happens to the symlink itself is
+                // reported under its own path
+                if classify_event(&event, &[true]) == Some(FileEvent::Modified) {
+                    if let Some(file) = self.files.get_mut(key) {
+                        file.handle_modified();
+                    }
+                }
+                // unless the file is watched under its own path as well
+                if !self.files.contains_key(path) {
+                    return;
+                }
+            }
+        }
+        let keys: Vec<Option<PathBuf>> = event.paths.iter()
+            .map(|path| self.file_key(path).cloned())
+            .collect();
+        if keys.iter().all(Option::is_none) {
+            if matches!(event.kind, notify::EventKind::Create(_)) {
+                self.handle_dir_event(&event);
+            }
+            else {
+                // other files in a watched directory, don't log at debug level
+                // as that would loop forever when the debug log lives next to a file
+                log::trace!("Skip Event: {:?}", event);
+            }
             return;
         }
-        log::debug!("Event: {:?}", event);
-        let pos = self.last_read_file_pos;
-        // ignore any event that didn't change the pos
-        let file_len = self.file_handle.metadata().unwrap().len();
-        if file_len == pos {
-            log::debug!("Ignoring event as file length = cursor position");
-        }
-        else if file_len < pos {
-            let msg = LogsMessage {
-                file_id: self.id.clone(),
-                lines: vec![format!("filewatch: File truncated to position {file_len}")],
+        let mut handled = HashSet::new();
+        for key in keys.iter().flatten() {
+            if !handled.insert(key) {
+                continue;
+            }
+            let watched: Vec<bool> = keys.iter().map(|k| k.as_ref() == Some(key)).collect();
+            if let Some(file) = self.files.get_mut(key) {
+                file.handle_event(&event, &watched);
+            }
+            self.watch_link_target(key);
+        }
+    }
+
+    /// A file created in a watched directory is watched from then on
+    fn handle_dir_event(&mut self, event: &notify::Event) {
+        log::debug!("Dir Event: {:?}", event);
+        for created in &event.paths {
+            if !created.is_file() {
+                continue;
+            }
+            let Some((path, options)) = self.dirs.iter().find_map(|dir| dir.created_file_source(created)) else {
+                continue;
             };
-            match self.tx.send(msg) {
-                Ok(_) => { /* noop */ },
-                Err(_) => log::error!("File event handler {} failed to send (meta)", &self.id)
+            // errors are reported on the channel
+            let _ = self.add_file(&path, &options);
+        }
+    }
+
+    /// Deadlines of every file, and retrying pending ones
+    fn handle_timeouts(&mut self) {
+        for file in self.files.values_mut() {
+            file.handle_timeout();
+            file.check_replaced();
+            file.send_stats();
+        }
+        let links: Vec<PathBuf> = self.files.iter()
+            .filter(|(_, file)| file.link_target.is_some())
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in links {
+            self.watch_link_target(&key);
+        }
+        if self.offsets.is_some() && self.offsets_saved_at.elapsed() >= OFFSETS_SAVE_INTERVAL {
+            self.offsets_saved_at = Instant::now();
+            self.save_offsets();
+        }
+        if !self.unwatched.is_empty() && self.watch_retried_at.elapsed() >= WATCH_RETRY_INTERVAL {
+            self.watch_retried_at = Instant::now();
+            self.retry_unwatched();
+        }
+        if !self.pending.is_empty() && self.pending_checked_at.elapsed() >= WAIT_RETRY_INTERVAL {
+            self.pending_checked_at = Instant::now();
+            for (path, options) in std::mem::take(&mut self.pending) {
+                // goes back on the list if it still can't be opened
+                let _ = self.add_file(&path, &options);
             }
-            self.last_read_file_pos = file_len;
         }
-        else {
-            let result = get_lines_for_interval(&mut self.file_handle, pos, file_len);
-            if let Some(lines) = result {
-                let msg = LogsMessage {
-                    file_id: self.id.clone(),
-                    lines: lines,
-                };
-                match self.tx.send(msg) {
-                    Ok(_) => { self.last_read_file_pos = file_len },
-                    Err(_) => log::error!("File event handler {} failed to send", &self.id)
+    }
+
+    fn next_timeout(&self) -> Duration {
+        let mut timeout = self.files.values()
+            .map(FileState::next_timeout)
+            .fold(SHUTDOWN_CHECK_INTERVAL, Duration::min);
+        if !self.pending.is_empty() {
+            timeout = timeout.min(WAIT_RETRY_INTERVAL.saturating_sub(self.pending_checked_at.elapsed()));
+        }
+        if !self.unwatched.is_empty() {
+            timeout = timeout.min(WATCH_RETRY_INTERVAL.saturating_sub(self.watch_retried_at.elapsed()));
+        }
+        timeout
+    }
+
+    /// Save how far each file with `WatchOptions::resume` has been read
+    fn save_offsets(&mut self) {
+        let Some(offsets) = &mut self.offsets else {
+            return;
+        };
+        for (key, file) in &self.files {
+            if let Some(identity) = file.identity.filter(|_| file.options.resume.is_some()) {
+                offsets.set(key, identity, file.last_read_file_pos);
+            }
+        }
+        if let Err(e) = offsets.save() {
+            log::error!("Failed to save read offsets: {}", e);
+        }
+    }
+
+    /// Try watching the directories in `unwatched` again, and read the files
+    /// in them, as nothing else will until they're watched
+    fn retry_unwatched(&mut self) {
+        for (backend, dir, recursive) in std::mem::take(&mut self.unwatched) {
+            let result = self.try_watch(backend, &dir, recursive);
+            let in_dir = |key: &PathBuf| if recursive { key.starts_with(&dir) } else { parent_dir(key) == dir };
+            for (_, file) in self.files.iter_mut().filter(|(key, _)| in_dir(key)) {
+                if result.is_ok() {
+                    file.send_event(SourceEvent::Notice(String::from("watching for changes again")));
+                }
+                file.read_new_lines(false);
+            }
+            match result {
+                Ok(()) => log::info!("Watching {} again", dir.display()),
+                Err(e) => {
+                    log::debug!("Still cannot watch {}: {}", dir.display(), e);
+                    self.unwatched.push((backend, dir, recursive));
                 }
-            }        
+            }
+        }
+    }
+}
+
+impl WatchedDir {
+    /// Path and options for a file found in this directory
+    fn file_source(&self, path: &Path) -> (String, WatchOptions) {
+        let mut options = self.options.clone();
+        options.label = options.label.map(|label| {
+            let relative = path.strip_prefix(&self.path).unwrap_or(path);
+            format!("{}/{}", label, relative.display())
+        });
+        (path.to_string_lossy().into_owned(), options)
+    }
+
+    /// Path and options for a file created at `created`, as reported by a
+    /// watcher, if it's in this directory
+    fn created_file_source(&self, created: &Path) -> Option<(String, WatchOptions)> {
+        let relative = created.strip_prefix(&self.absolute).ok()
+            .or_else(|| created.strip_prefix(self.canonical.as_ref()?).ok())?;
+        if !self.options.recursive && relative.components().count() != 1 {
+            return None;
         }
+        Some(self.file_source(&self.path.join(relative)))
     }
 }
 
-fn should_handle_event(event_res: &notify::Result<notify::Event>) -> bool {
-    match event_res {
-        Ok(event) => {
-            use notify::{event::*};
-            match event.kind {
-                EventKind::Modify(kind) => {
-                    kind != ModifyKind::Metadata(MetadataKind::Any) &&
-                    kind != ModifyKind::Name(RenameMode::Any)
-                    },
-                _ => false
+/// What went wrong, with a hint for the limits on inotify watches and instances
+fn watch_error_text(error: &notify::Error) -> String {
+    match &error.kind {
+        notify::ErrorKind::MaxFilesWatch => String::from(
+            "inotify watch limit reached (ENOSPC), raise fs.inotify.max_user_watches or use --poll"),
+        notify::ErrorKind::Io(e) if is_too_many_instances(e) => format!(
+            "{}, raise fs.inotify.max_user_instances or use --poll", e),
+        notify::ErrorKind::Io(e) => e.to_string(),
+        _ => error.to_string(),
+    }
+}
+
+/// Creating an inotify instance fails with EMFILE over `max_user_instances`
+#[cfg(target_os = "linux")]
+fn is_too_many_instances(e: &io::Error) -> bool {
+    e.raw_os_error() == Some(libc::EMFILE)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_too_many_instances(_e: &io::Error) -> bool {
+    false
+}
+
+/// `path` made absolute without resolving symlinks, so that it looks like the
+/// paths the watchers report
+fn absolute_path(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_owned())
+}
+
+/// What's known about one watched file, see `FileEventHandler`
+struct FileState {
+    id: String,
+    path: PathBuf,
+    tx: SyncSender<LogsMessage>,
+    /// Kept at `last_read_file_pos` in between reads
+    reader: BufReader<File>,
+    last_read_file_pos: u64,
+    /// The file was deleted (or renamed, with `follow_descriptor`) and has
+    /// not been recreated yet
+    removed: bool,
+    /// Device and inode of the open file, to notice when the path is
+    /// replaced by another file without an event saying so
+    identity: Option<(u64, u64)>,
+    /// What the path points to, with symlinks resolved, if it's a symlink
+    link_target: Option<PathBuf>,
+    replace_checked_at: Instant,
+    /// The last bytes read, which are still there unless the file was
+    /// truncated and rewritten in between two reads
+    tail_bytes: Vec<u8>,
+    /// The last multi-line record, see `group_records`
+    record: Option<String>,
+    /// When a line was last added to `record`
+    record_since: Option<Instant>,
+    /// The file turned out to be binary, so nothing more is read from it
+    binary: bool,
+    stats: ReadStats,
+    /// The stats as last sent, and when, see `send_stats`
+    stats_sent: ReadStats,
+    stats_sent_at: Instant,
+    /// When the bytes after `last_read_file_pos` were first seen without a
+    /// newline, if there are any
+    partial_since: Option<Instant>,
+    /// When the file was last read, for debouncing modify events
+    last_read_at: Option<Instant>,
+    /// A modify event arrived during the debounce window and still needs a read
+    read_pending: bool,
+    /// Encoding the file is read with, after looking at its byte order mark
+    encoding: &'static Encoding,
+    /// With `WatchOptions::max_lines_per_sec`, once the initial read is done
+    rate_limit: Option<RateLimit>,
+    /// The last read stopped at `READ_CHUNK_LINES`, the rest is read on the
+    /// next timeout
+    more_to_read: bool,
+    /// Progress through what was in the file when it was opened, until
+    /// that's all been read
+    loading: Option<Loading>,
+    options: WatchOptions,
+}
+
+/// See `FileState::loading`
+struct Loading {
+    start: u64,
+    end: u64,
+    /// `SourceEvent::Loading` has been sent, so it has to be sent once done too
+    reported: bool,
+}
+
+/// What an event in the watched file's parent directory means for the file itself
+#[derive(Debug, PartialEq)]
+enum FileEvent {
+    /// The file was written to
+    Modified,
+    /// The file was renamed away, e.g. by logrotate
+    MovedAway,
+    /// The file was deleted
+    Removed,
+    /// A (new) file appeared at the watched path
+    Created,
+}
 
+impl FileState {
+    fn send_lines(&mut self, lines: Vec<String>) -> bool {
+        let mut lines = self.group_records(lines);
+        lines.retain(|line| self.options.keeps(line));
+        if let Some(rate_limit) = &mut self.rate_limit {
+            lines.truncate(rate_limit.take(lines.len()));
+        }
+        if lines.is_empty() {
+            return true;
+        }
+        let msg = LogsMessage::new(self.id.clone(), SourceEvent::lines(lines, &self.options));
+        match self.tx.send(msg) {
+            Ok(_) => true,
+            Err(_) => { log::error!("File event handler {} failed to send", &self.id); false }
+        }
+    }
+
+    /// With `multiline_start`, lines that don't start a new record are
+    /// appended to the one before, e.g. the lines of a stack trace. The last
+    /// record is held back until the next one starts, as more of it may follow.
+    fn group_records(&mut self, lines: Vec<String>) -> Vec<String> {
+        let Some(start) = &self.options.multiline_start else {
+            return lines;
+        };
+        if lines.is_empty() {
+            return lines;
+        }
+        let mut records = vec![];
+        for line in lines {
+            match self.record.take() {
+                Some(mut record) if !start.is_match(&line) => {
+                    record.push('\n');
+                    record.push_str(&line);
+                    self.record = Some(record);
+                }
+                previous => {
+                    records.extend(previous);
+                    self.record = Some(line);
+                }
+            }
+        }
+        self.record_since = Some(Instant::now());
+        records
+    }
+
+    /// Send the record held back by `group_records`
+    fn flush_record(&mut self) {
+        if let Some(record) = self.record.take() {
+            self.record_since = None;
+            if !self.options.keeps(&record) || self.rate_limit.as_mut().is_some_and(|limit| limit.take(1) == 0) {
+                return;
+            }
+            let lines = vec![record];
+            let msg = LogsMessage::new(self.id.clone(), SourceEvent::lines(lines, &self.options));
+            if self.tx.send(msg).is_err() {
+                log::error!("File event handler {} failed to send", &self.id);
+            }
+        }
+    }
+
+    /// Send the stats if they changed, at most every `STATS_INTERVAL`. They
+    /// don't go through `send_event`, as that would cut a record short.
+    fn send_stats(&mut self) {
+        if self.stats == self.stats_sent || self.stats_sent_at.elapsed() < STATS_INTERVAL {
+            return;
+        }
+        self.stats_sent = self.stats;
+        self.stats_sent_at = Instant::now();
+        send_event(&self.tx, &self.id, SourceEvent::Stats(self.stats));
+    }
+
+    /// Report something that happened to the file, after any record that's
+    /// held back so the two stay in order
+    fn send_event(&mut self, event: SourceEvent) {
+        self.flush_record();
+        send_event(&self.tx, &self.id, event);
+    }
+
+    /// Read the complete lines between the cursor and the current end of the
+    /// file, up to `READ_CHUNK_LINES` of them, see `more_to_read`. With
+    /// `flush_partial`, a trailing line without a newline is read too.
+    ///
+    /// The reader stays at the cursor in between, so a read is usually just
+    /// that. The file's length is only looked at when there's nothing new to
+    /// read, to see whether it was truncated.
+    fn read_new_lines(&mut self, flush_partial: bool) {
+        self.last_read_at = Some(Instant::now());
+        self.read_pending = false;
+        self.more_to_read = false;
+        if self.last_read_file_pos == 0 {
+            if let Err(e) = self.skip_bom() {
+                self.send_event(SourceEvent::Error(format!("error reading {}: {}", &self.id, e)));
+                return;
+            }
+        }
+        let has_new_bytes = match self.reader.fill_buf() {
+            Ok(buffered) => !buffered.is_empty(),
+            Err(e) => {
+                self.send_event(SourceEvent::Error(format!("error reading {}: {}", &self.id, e)));
+                return;
+            }
+        };
+        if !has_new_bytes {
+            match self.reader.get_ref().metadata() {
+                Ok(metadata) if metadata.len() < self.last_read_file_pos => {
+                    // copytruncate style rotation, start again from the top
+                    self.send_event(SourceEvent::Truncated(metadata.len()));
+                    self.reopen();
+                }
+                Ok(_) => log::debug!("Ignoring event as there is nothing new to read"),
+                Err(e) => self.send_event(SourceEvent::Error(format!("cannot read metadata for {}: {}", &self.id, e))),
+            }
+        }
+        else if !self.tail_bytes_unchanged() {
+            // truncated and written past the old length before we got to it
+            self.send_event(SourceEvent::Rewritten);
+            self.reopen();
+        }
+        else if self.binary || self.new_bytes_look_binary() {
+            if !self.binary {
+                self.binary = true;
+                self.send_event(SourceEvent::Notice(format!("{} appears to be binary, skipping", self.path.display())));
+            }
+            if let Err(e) = self.reader.get_ref().metadata().and_then(|m| self.seek_to(m.len())) {
+                self.send_event(SourceEvent::Error(format!("error reading {}: {}", &self.id, e)));
+            }
+            self.partial_since = None;
+        }
+        else {
+            match read_lines(&mut self.reader, flush_partial, self.encoding, &self.options, &mut self.tail_bytes) {
+                Ok(read) => {
+                    self.stats.bytes += read.consumed;
+                    if !read.lines.is_empty() {
+                        self.stats.lines += read.lines.len() as u64;
+                        self.stats.last_line_at = Some(SystemTime::now());
+                    }
+                    self.last_read_file_pos += read.consumed;
+                    self.more_to_read = read.more;
+                    self.send_lines(read.lines);
+                    if read.invalid > 0 {
+                        self.send_event(SourceEvent::InvalidLines { count: read.invalid, encoding: self.encoding.name() });
+                    }
+                    self.partial_since = if read.partial {
+                        self.partial_since.or(Some(Instant::now()))
+                    } else {
+                        None
+                    };
+                }
+                Err(e) => self.send_event(SourceEvent::Error(format!("error reading {}: {}", &self.id, e)))
+            }
+        }
+        if !self.more_to_read {
+            self.finish_loading();
+        }
+        else {
+            self.report_loading();
+        }
+        if flush_partial && !self.more_to_read {
+            // nothing more is expected, so neither is the rest of the record
+            self.flush_record();
+        }
+    }
+
+    /// Read up to the current end of the file, however many chunks that
+    /// takes, before the file is let go of
+    fn read_remaining_lines(&mut self, flush_partial: bool) {
+        self.read_new_lines(flush_partial);
+        while self.more_to_read {
+            self.read_new_lines(flush_partial);
+        }
+    }
+
+    /// Keep track of reading what's in the file up to its current length,
+    /// from the cursor, see `SourceEvent::Loading`
+    fn start_loading(&mut self) {
+        self.loading = self.reader.get_ref().metadata().ok()
+            .filter(|metadata| metadata.len() > self.last_read_file_pos)
+            .map(|metadata| Loading { start: self.last_read_file_pos, end: metadata.len(), reported: false });
+    }
+
+    fn report_loading(&mut self) {
+        if let Some(loading) = &mut self.loading {
+            loading.reported = true;
+            let done = self.last_read_file_pos.min(loading.end) - loading.start;
+            let event = SourceEvent::Loading { done, total: loading.end - loading.start };
+            // not through `send_event`, as that would cut a record short
+            send_event(&self.tx, &self.id, event);
+        }
+    }
+
+    /// Everything that was in the file when it was opened has been read.
+    /// That's when rate limiting starts, so history isn't dropped.
+    fn finish_loading(&mut self) {
+        if let Some(loading) = self.loading.take() {
+            if loading.reported {
+                let total = loading.end - loading.start;
+                send_event(&self.tx, &self.id, SourceEvent::Loading { done: total, total });
             }
         }
-        Err(error) => {
-            log::error!("Event error: {:?}", error);
+        if self.rate_limit.is_none() {
+            self.rate_limit = self.options.max_lines_per_sec.map(RateLimit::new);
+        }
+    }
+
+    /// Whether the start of what's been written since the cursor looks like
+    /// binary data rather than text, see `looks_binary`. Only looks at what
+    /// the reader has buffered.
+    fn new_bytes_look_binary(&mut self) -> bool {
+        if self.options.force_binary {
             return false;
         }
+        // if it can't be read, reading the lines will report why
+        self.reader.fill_buf().is_ok_and(|buffered| {
+            let sample = &buffered[..buffered.len().min(BINARY_SNIFF_LEN as usize)];
+            looks_binary(sample, self.encoding)
+        })
+    }
+
+    /// Move the cursor, and the reader with it, to `pos`. The bytes before it
+    /// are remembered for `tail_bytes_unchanged`.
+    fn seek_to(&mut self, pos: u64) -> io::Result<()> {
+        self.reader.seek(io::SeekFrom::Start(pos))?;
+        self.last_read_file_pos = pos;
+        self.remember_tail_bytes();
+        Ok(())
+    }
+
+    /// Reopen the watched path from the start and read whatever is already in it
+    fn reopen(&mut self) {
+        match fs::File::open(&self.path) {
+            Ok(file_handle) => {
+                self.identity = file_handle.metadata().ok().and_then(|m| file_identity(&m));
+                self.link_target = link_target(&self.path);
+                self.reader = BufReader::new(file_handle);
+                self.tail_bytes.clear();
+                self.binary = false;
+                self.last_read_file_pos = 0;
+                self.partial_since = None;
+                self.encoding = self.options.encoding.initial();
+                self.more_to_read = false;
+                self.start_loading();
+                self.read_new_lines(false);
+            }
+            Err(e) => self.send_event(SourceEvent::Error(format!("cannot reopen {}: {}", self.path.display(), e)))
+        }
+    }
+
+    /// Reopen the path if it's now a different file than the one being read,
+    /// e.g. an editor wrote a new file and renamed it over the old one.
+    /// Events normally say so, this catches the cases where they don't, like
+    /// with the poll backend.
+    fn check_replaced(&mut self) {
+        if self.options.follow_descriptor || self.identity.is_none()
+            || self.replace_checked_at.elapsed() < SHUTDOWN_CHECK_INTERVAL {
+            return;
+        }
+        self.replace_checked_at = Instant::now();
+        let Ok(metadata) = fs::metadata(&self.path) else {
+            // gone, a create event will follow if it comes back
+            return;
+        };
+        let identity = file_identity(&metadata);
+        if identity.is_some() && identity != self.identity {
+            log::debug!("{} was replaced ({:?} -> {:?})", &self.id, self.identity, identity);
+            if self.is_retargeted() {
+                self.retarget();
+                return;
+            }
+            // whatever was written to the old file before it was replaced
+            self.read_remaining_lines(true);
+            self.removed = false;
+            self.send_event(SourceEvent::Replaced);
+            self.reopen();
+        }
+    }
+
+    /// The path is a symlink that doesn't point to the open file anymore
+    fn is_retargeted(&self) -> bool {
+        self.link_target.is_some() && link_target(&self.path).is_some_and(|target| Some(target) != self.link_target)
+    }
+
+    /// Finish reading the file the symlink pointed to, then read the one it
+    /// points to now from the start
+    fn retarget(&mut self) {
+        log::debug!("{} now points to {:?}", &self.id, link_target(&self.path));
+        self.read_remaining_lines(true);
+        self.removed = false;
+        self.send_event(SourceEvent::Retargeted);
+        self.reopen();
+    }
+
+    fn is_open_file_at_path(&self) -> bool {
+        let identity = fs::metadata(&self.path).ok().and_then(|m| file_identity(&m));
+        identity.is_some() && identity == self.identity
+    }
+
+    /// Keep the bytes just before the cursor, see `tail_bytes_unchanged`.
+    /// Reading lines keeps them up to date, this is for when the cursor is
+    /// moved without reading.
+    fn remember_tail_bytes(&mut self) {
+        let len = self.last_read_file_pos.min(TAIL_CHECK_LEN);
+        let mut bytes = vec![0; len as usize];
+        self.tail_bytes = match read_exact_at(self.reader.get_mut(), &mut bytes, self.last_read_file_pos - len) {
+            Ok(()) => bytes,
+            Err(_) => vec![],
+        };
+    }
+
+    /// Whether the bytes before the cursor are still the ones that were read.
+    /// If not, the file was truncated and then written past the cursor before
+    /// the truncation was noticed.
+    fn tail_bytes_unchanged(&mut self) -> bool {
+        if self.tail_bytes.is_empty() {
+            return true;
+        }
+        let start = self.last_read_file_pos - self.tail_bytes.len() as u64;
+        let mut bytes = vec![0; self.tail_bytes.len()];
+        let read = read_exact_at(self.reader.get_mut(), &mut bytes, start);
+        // can't tell, the reading that follows will report any error
+        read.is_err() || bytes == self.tail_bytes
+    }
+
+    /// Move the cursor forward so only the `tail` of the file is read
+    fn skip_history(&mut self, tail: Tail) {
+        let mut skip = || -> Result<(u64, u64), io::Error> {
+            let bom_len = self.skip_bom()?;
+            let file_handle = self.reader.get_mut();
+            let file_len = file_handle.metadata()?.len();
+            let newline = newline_bytes(self.encoding);
+            let start = match tail {
+                Tail::Nothing => file_len,
+                Tail::Lines(lines) => find_tail_lines_start(file_handle, bom_len, file_len, lines, newline)?,
+                Tail::Bytes(bytes) => find_tail_bytes_start(file_handle, bom_len, file_len, bytes, newline)?,
+            };
+            self.seek_to(start)?;
+            Ok((bom_len, start))
+        };
+        match skip() {
+            Ok((bom_len, start)) => {
+                if let Tail::Nothing = tail {
+                    // always say so, otherwise an empty view looks like a bug
+                    self.send_event(SourceEvent::Notice(format!("history skipped ({} bytes), showing new lines only", start - bom_len)));
+                }
+                else if start > bom_len {
+                    self.send_event(SourceEvent::Notice(format!("skipped {} bytes of history", start - bom_len)));
+                }
+            }
+            Err(e) => self.send_event(SourceEvent::Error(format!("error reading {}: {}", &self.id, e)))
+        }
+    }
+
+    /// Skip a byte order mark at the start of the file, using it to pick the
+    /// encoding when that's left to `TextEncoding::Auto`. Moves the cursor
+    /// past it, and returns its length.
+    fn skip_bom(&mut self) -> Result<u64, io::Error> {
+        let mut start = Vec::with_capacity(3);
+        self.reader.seek(io::SeekFrom::Start(0))?;
+        (&mut self.reader).take(3).read_to_end(&mut start)?;
+        let bom_len = match Encoding::for_bom(&start) {
+            Some((encoding, bom_len)) => {
+                if let TextEncoding::Auto = self.options.encoding {
+                    log::debug!("{} has a {} byte order mark", &self.id, encoding.name());
+                    self.encoding = encoding;
+                }
+                if encoding == self.encoding { bom_len as u64 } else { 0 }
+            }
+            None => 0
+        };
+        self.reader.seek(io::SeekFrom::Start(bom_len))?;
+        self.last_read_file_pos = bom_len;
+        Ok(bom_len)
+    }
+
+    /// How long to wait for an event before checking for shutdown, doing a
+    /// debounced read, reading the next chunk or flushing a partial line
+    fn next_timeout(&self) -> Duration {
+        if self.more_to_read {
+            return Duration::ZERO;
+        }
+        let mut timeout = SHUTDOWN_CHECK_INTERVAL;
+        if let Some(since) = self.partial_since {
+            timeout = timeout.min(self.options.partial_flush.saturating_sub(since.elapsed()));
+        }
+        if let (true, Some(at)) = (self.read_pending, self.last_read_at) {
+            timeout = timeout.min(self.options.debounce.saturating_sub(at.elapsed()));
+        }
+        if let Some(since) = self.record_since {
+            timeout = timeout.min(self.options.partial_flush.saturating_sub(since.elapsed()));
+        }
+        timeout
+    }
+
+    /// Read the next chunk, do the catch-up read for modify events that
+    /// arrived during the debounce window, and send a partial line once it
+    /// has waited `partial_flush` for its newline
+    fn handle_timeout(&mut self) {
+        if self.more_to_read {
+            self.read_new_lines(false);
+        }
+        if self.read_pending && self.last_read_at.is_some_and(|at| at.elapsed() >= self.options.debounce) {
+            self.read_new_lines(false);
+        }
+        if self.partial_since.is_some_and(|since| since.elapsed() >= self.options.partial_flush) {
+            log::debug!("Flushing partial line for {}", &self.id);
+            self.read_new_lines(true);
+        }
+        if self.record_since.is_some_and(|since| since.elapsed() >= self.options.partial_flush) {
+            self.flush_record();
+        }
+        // not through `send_event`, as that would cut a record short
+        if let Some(text) = self.rate_limit.as_mut().and_then(|limit| limit.report(&self.id)) {
+            send_event(&self.tx, &self.id, SourceEvent::Notice(text));
+        }
+        // no more events arrive for a file that's been moved away from the
+        // watched path, so it has to be polled
+        if self.options.follow_descriptor && self.removed {
+            self.read_new_lines(false);
+        }
+    }
+
+    /// Read on a modify event, unless the file was read less than `debounce`
+    /// ago, in which case one read is done once the window is over. A busy
+    /// file then gets read in large batches instead of once per write.
+    fn handle_modified(&mut self) {
+        if self.last_read_at.is_some_and(|at| at.elapsed() < self.options.debounce) {
+            self.read_pending = true;
+        }
+        else {
+            self.read_new_lines(false);
+        }
     }
 }
 
-fn get_lines_for_interval(file_handle: &mut File, start_pos: u64, end_pos: u64) -> Option<Vec<String>> {
-    if start_pos > end_pos {
-        log::info!("will not read file, start pos ({start_pos}) > end pos ({end_pos})");
-        return Option::Some(vec![]);
+impl FileState {
+    /// `watched` says which of the event's paths are this file
+    fn handle_event(&mut self, event: &notify::Event, watched: &[bool]) {
+        let file_event = classify_event(event, watched);
+        if file_event.is_none() {
+            log::trace!("Skip Event: {:?}", event);
+            return;
+        }
+        log::debug!("Event ({:?}): {:?}", file_event, event);
+        match file_event {
+            Some(FileEvent::Modified) => self.handle_modified(),
+            Some(FileEvent::MovedAway | FileEvent::Removed) if self.options.follow_descriptor => {
+                // the open file can still be read, whatever happens to the path
+                self.read_new_lines(false);
+                if !self.removed {
+                    self.removed = true;
+                    self.send_event(SourceEvent::Notice(String::from("file moved or removed, still reading it")));
+                }
+            }
+            Some(FileEvent::Created) if self.options.follow_descriptor => {
+                log::debug!("Not following new file at {}", self.path.display());
+            }
+            Some(FileEvent::MovedAway) => {
+                // pick up anything written before the file was moved, nothing
+                // will complete a partial line now
+                self.read_remaining_lines(true);
+            }
+            Some(FileEvent::Removed) => {
+                self.read_remaining_lines(true);
+                self.removed = true;
+                self.send_event(SourceEvent::Removed);
+            }
+            Some(FileEvent::Created) if !self.removed && self.is_retargeted() => self.retarget(),
+            Some(FileEvent::Created) if !self.removed && self.is_open_file_at_path() => {
+                // a rename is reported both as a rename to the path and as a
+                // rename from one path to another, don't reopen twice
+                log::debug!("Already reading the file at {}", self.path.display());
+            }
+            Some(FileEvent::Created) => {
+                let event = if self.removed { SourceEvent::Recreated } else { SourceEvent::Rotated };
+                self.removed = false;
+                self.send_event(event);
+                self.reopen();
+            }
+            None => {}
+        }
+    }
+}
+
+/// What `event` means for the file that's at the paths marked in `watched`.
+/// Events come from the file's parent directory, so they can be about other
+/// files as well.
+fn classify_event(event: &notify::Event, watched: &[bool]) -> Option<FileEvent> {
+    let is_watched = |i: usize| watched.get(i).copied().unwrap_or(false);
+    use notify::{event::*};
+    match event.kind {
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            // paths are [from, to]
+            if is_watched(1) {
+                Some(FileEvent::Created)
+            }
+            else if is_watched(0) {
+                Some(FileEvent::MovedAway)
+            }
+            else {
+                None
+            }
+        }
+        _ if !watched.contains(&true) => None,
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => Some(FileEvent::Created),
+        EventKind::Modify(ModifyKind::Name(_)) => Some(FileEvent::MovedAway),
+        // the poll backend reports writes as a write time change
+        EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any)) => None,
+        EventKind::Modify(_) => Some(FileEvent::Modified),
+        EventKind::Create(_) => Some(FileEvent::Created),
+        EventKind::Remove(_) => Some(FileEvent::Removed),
+        _ => None
     }
+}
+
+/// Device and inode number, which tell two files apart even if they had the
+/// same path
+#[cfg(unix)]
+fn file_identity(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_identity(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    // the Windows equivalent (volume serial and file index) isn't stable yet
+    None
+}
+
+/// What `path` points to, with every symlink resolved, if it is a symlink
+fn link_target(path: &Path) -> Option<PathBuf> {
+    if !fs::symlink_metadata(path).ok()?.is_symlink() {
+        return None;
+    }
+    fs::canonicalize(path).ok()
+}
+
+/// Directory that has to be watched to see `path` being replaced
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new(".")
+    }
+}
 
-    log::debug!("Reading from position {} to {}", start_pos, end_pos);
+/// Lines read by `read_lines`
+struct ReadLines {
+    lines: Vec<String>,
+    /// Bytes the lines took up
+    consumed: u64,
+    /// There's a last line without a newline after them, left unread
+    partial: bool,
+    /// Reading stopped at `READ_CHUNK_LINES`, with more lines after them
+    more: bool,
+    /// Lines dropped for not being valid in the encoding, see `decode_line`
+    invalid: u64,
+}
 
-    // read from pos to end of file
+/// Reads the lines from the reader's position to the end of the file, or
+/// `READ_CHUNK_LINES` of them if there are more. A last line without a
+/// newline is left unread, unless `include_partial` is set. `tail_bytes` is
+/// updated with the last bytes that were read, see
+/// `FileState::tail_bytes_unchanged`.
+fn read_lines(reader: &mut BufReader<File>, include_partial: bool, encoding: &'static Encoding, options: &WatchOptions, tail_bytes: &mut Vec<u8>) -> Result<ReadLines, io::Error> {
     let mut lines = Vec::new();
-    file_handle.seek(io::SeekFrom::Start(start_pos)).unwrap();
-    let reader = BufReader::new(file_handle);
-    for line_res in reader.lines() {
-        let line = line_res.unwrap();
-        if line.len() == 0 {
-            continue;
-        }
-        // else parse line, add to db?
-        lines.push(line)
-    }
-    Option::Some(lines)
-}
\ No newline at end of file
+    let mut consumed = 0;
+    let mut partial = false;
+    let mut more = false;
+    let mut invalid = 0;
+    let newline = newline_bytes(encoding);
+    let mut buf = Vec::new();
+    loop {
+        if lines.len() >= READ_CHUNK_LINES {
+            more = !reader.fill_buf()?.is_empty();
+            break;
+        }
+        buf.clear();
+        let read = read_line_bytes(reader, newline, &mut buf)?;
+        if read == 0 {
+            break;
+        }
+        let complete = buf.ends_with(newline);
+        if !complete && !include_partial {
+            // the rest of the line is read with it once it's there
+            reader.seek_relative(-(read as i64))?;
+            partial = true;
+            break;
+        }
+        consumed += read as u64;
+        tail_bytes.extend_from_slice(&buf);
+        tail_bytes.drain(..tail_bytes.len().saturating_sub(TAIL_CHECK_LEN as usize));
+        if complete {
+            buf.truncate(buf.len() - newline.len());
+        }
+        match decode_line(&buf, encoding, options) {
+            Some(decoded) => lines.extend(decoded),
+            None => invalid += 1,
+        }
+    }
+    Ok(ReadLines { lines, consumed, partial, more, invalid })
+}
+
+/// The text of a line read as `raw` bytes, without its newline, as it's
+/// shown: which can be no lines if it's blank and those are skipped, or more
+/// than one, see `WatchOptions::clean_line`. None if it's dropped for not
+/// being valid in `encoding`, see `WatchOptions::strict_utf8`.
+fn decode_line(raw: &[u8], encoding: &'static Encoding, options: &WatchOptions) -> Option<Vec<String>> {
+    // invalid sequences become U+FFFD rather than losing the whole line
+    let (text, had_errors) = encoding.decode_without_bom_handling(raw);
+    if had_errors && options.strict_utf8 {
+        return None;
+    }
+    let mut lines = options.clean_line(text.into_owned());
+    if options.skip_blank {
+        lines.retain(|line| !line.is_empty());
+    }
+    for line in &mut lines {
+        if line.len() > options.max_line_bytes {
+            *line = truncate_line(std::mem::take(line), options.max_line_bytes);
+        }
+        if options.force_binary {
+            *line = line.chars()
+                .map(|c| if c.is_control() && c != '\t' { char::REPLACEMENT_CHARACTER } else { c })
+                .collect();
+        }
+    }
+    Some(lines)
+}
+
+/// Read exactly `buf.len()` bytes from `offset`, leaving the file's position
+/// where it was
+#[cfg(unix)]
+fn read_exact_at(file: &mut File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    use std::os::unix::fs::FileExt;
+    file.read_exact_at(buf, offset)
+}
+
+#[cfg(not(unix))]
+fn read_exact_at(file: &mut File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    let pos = file.stream_position()?;
+    file.seek(io::SeekFrom::Start(offset))?;
+    let read = file.read_exact(buf);
+    file.seek(io::SeekFrom::Start(pos))?;
+    read
+}
+
+/// Cut `line` to at most `max_bytes`, on a char boundary, and say how much was cut
+fn truncate_line(mut line: String, max_bytes: usize) -> String {
+    let mut end = max_bytes;
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    let cut = line.len() - end;
+    line.truncate(end);
+    line.push_str(&format!("…[truncated {} bytes]", cut));
+    line
+}
+
+/// Text has no NUL bytes (outside of UTF-16) and few control characters
+fn looks_binary(sample: &[u8], encoding: &'static Encoding) -> bool {
+    if sample.is_empty() || newline_bytes(encoding).len() > 1 {
+        return false;
+    }
+    if sample.contains(&0) {
+        return true;
+    }
+    let control = sample.iter()
+        .filter(|b| b.is_ascii_control() && !matches!(b, b'\t' | b'\n' | b'\r' | b'\x0c' | b'\x1b'))
+        .count();
+    control * 10 > sample.len()
+}
+
+/// The newline character as it's encoded in `encoding`
+fn newline_bytes(encoding: &'static Encoding) -> &'static [u8] {
+    if encoding == encoding_rs::UTF_16LE {
+        b"\n\0"
+    }
+    else if encoding == encoding_rs::UTF_16BE {
+        b"\0\n"
+    }
+    else {
+        b"\n"
+    }
+}
+
+/// Like `BufRead::read_until`, but for newlines that take up more than one
+/// byte. Those are only matched at code unit boundaries, so a UTF-16 line
+/// isn't split in the middle of a character that happens to contain 0x0A.
+fn read_line_bytes<R: BufRead>(reader: &mut R, newline: &[u8], buf: &mut Vec<u8>) -> Result<usize, io::Error> {
+    if let [byte] = newline {
+        return reader.read_until(*byte, buf);
+    }
+    let start_len = buf.len();
+    let mut unit = vec![0; newline.len()];
+    loop {
+        let mut got = 0;
+        while got < unit.len() {
+            let read = reader.read(&mut unit[got..])?;
+            if read == 0 {
+                break;
+            }
+            got += read;
+        }
+        buf.extend_from_slice(&unit[..got]);
+        if got < unit.len() || unit == newline {
+            return Ok(buf.len() - start_len);
+        }
+    }
+}
+
+/// Finds where the last `lines` lines of the file start, by reading backwards
+/// from the end in chunks, so a huge file doesn't have to be read in full.
+/// `start` is where the text starts, i.e. after any byte order mark.
+fn find_tail_lines_start(file_handle: &mut File, start: u64, end: u64, lines: u64, newline: &[u8]) -> Result<u64, io::Error> {
+    if lines == 0 {
+        return Ok(end);
+    }
+    let unit = newline.len() as u64;
+    let mut newlines_to_find = lines;
+    let mut chunk_end = end;
+    let mut chunk = Vec::new();
+    while chunk_end > start {
+        // chunks stay aligned to code units, so multi-byte newlines never
+        // straddle two chunks
+        let chunk_start = chunk_end.saturating_sub(TAIL_CHUNK_SIZE).max(start);
+        let chunk_start = chunk_start + (chunk_start - start) % unit;
+        chunk.clear();
+        file_handle.seek(io::SeekFrom::Start(chunk_start))?;
+        (&mut *file_handle).take(chunk_end - chunk_start).read_to_end(&mut chunk)?;
+        for (i, code_unit) in chunk.chunks_exact(newline.len()).enumerate().rev() {
+            if code_unit != newline {
+                continue;
+            }
+            let line_start = chunk_start + (i as u64 + 1) * unit;
+            // the newline ending the last line doesn't start a new line
+            if line_start == end {
+                continue;
+            }
+            newlines_to_find -= 1;
+            if newlines_to_find == 0 {
+                return Ok(line_start);
+            }
+        }
+        if chunk_start == start {
+            break;
+        }
+        chunk_end = chunk_start;
+    }
+    Ok(start)
+}
+
+/// Finds the start of the first line in the last `bytes` bytes of the file
+fn find_tail_bytes_start(file_handle: &mut File, start: u64, end: u64, bytes: u64, newline: &[u8]) -> Result<u64, io::Error> {
+    let unit = newline.len() as u64;
+    let mut pos = end.saturating_sub(bytes).max(start);
+    pos += (pos - start) % unit;
+    if pos == start {
+        return Ok(start);
+    }
+    // the byte before might be the end of a line, in which case pos is
+    // already at the start of one
+    pos -= unit;
+    file_handle.seek(io::SeekFrom::Start(pos))?;
+    let mut reader = BufReader::new((&mut *file_handle).take(end - pos));
+    let mut skipped = Vec::new();
+    read_line_bytes(&mut reader, newline, &mut skipped)?;
+    Ok(pos + skipped.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn utf16be(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_be_bytes).collect()
+    }
+
+    /// Each line in `bytes` as `read_line_bytes` splits them, decoded, and
+    /// whether it ended with a newline
+    fn read_all(bytes: &[u8], encoding: &'static Encoding) -> Vec<(Vec<String>, bool)> {
+        let newline = newline_bytes(encoding);
+        let mut reader = Cursor::new(bytes);
+        let mut lines = vec![];
+        let mut buf = vec![];
+        loop {
+            buf.clear();
+            if read_line_bytes(&mut reader, newline, &mut buf).unwrap() == 0 {
+                return lines;
+            }
+            let complete = buf.ends_with(newline);
+            if complete {
+                buf.truncate(buf.len() - newline.len());
+            }
+            lines.push((decode_line(&buf, encoding, &WatchOptions::default()).unwrap(), complete));
+        }
+    }
+
+    fn line(text: &str) -> (Vec<String>, bool) {
+        (vec![text.to_owned()], true)
+    }
+
+    #[test]
+    fn utf16_crlf_lines() {
+        for (bytes, encoding) in [
+            (utf16le("one\r\ntwo\r\n"), encoding_rs::UTF_16LE),
+            (utf16be("one\r\ntwo\r\n"), encoding_rs::UTF_16BE),
+        ] {
+            assert_eq!(read_all(&bytes, encoding), [line("one"), line("two")], "{}", encoding.name());
+        }
+    }
+
+    #[test]
+    fn utf16_newline_only_at_code_units() {
+        // U+0A41 U+0100 is `41 0A 00 01` in UTF-16LE, with `0A 00` across
+        // two code units, and U+0A0A is `0A 0A`
+        let text = "\u{0A41}\u{0100}\u{0A0A}\r\nnext\r\n";
+        assert_eq!(read_all(&utf16le(text), encoding_rs::UTF_16LE), [line("\u{0A41}\u{0100}\u{0A0A}"), line("next")]);
+        // and `00 0A` across two in UTF-16BE
+        let text = "\u{0100}\u{0A41}\r\nnext\r\n";
+        assert_eq!(read_all(&utf16be(text), encoding_rs::UTF_16BE), [line("\u{0100}\u{0A41}"), line("next")]);
+    }
+
+    #[test]
+    fn utf16_read_ending_halfway_through_a_code_unit() {
+        for (mut bytes, encoding) in [
+            (utf16le("one\r\ntwo\r\n"), encoding_rs::UTF_16LE),
+            (utf16be("one\r\ntwo\r\n"), encoding_rs::UTF_16BE),
+        ] {
+            bytes.pop();
+            let newline = newline_bytes(encoding);
+            let mut reader = Cursor::new(&bytes);
+            let mut buf = vec![];
+            read_line_bytes(&mut reader, newline, &mut buf).unwrap();
+            buf.clear();
+            let read = read_line_bytes(&mut reader, newline, &mut buf).unwrap();
+            // what's there of the line, for `read_lines` to leave unread
+            // until the rest of it is
+            assert_eq!(read, 9, "{}", encoding.name());
+            assert!(!buf.ends_with(newline), "{}", encoding.name());
+        }
+    }
+
+    #[test]
+    fn crlf_and_mixed_line_endings() {
+        let lines = read_all(b"one\r\ntwo\nthree\r\n\r\n\nlast\r", encoding_rs::UTF_8);
+        let expected = [line("one"), line("two"), line("three"), line(""), line(""), (vec![String::from("last")], false)];
+        assert_eq!(lines, expected);
+    }
+
+    #[test]
+    fn carriage_returns_inside_a_line() {
+        let cases = [
+            ("10%\r50%\r100%", CarriageReturns::Last, vec!["100%"]),
+            ("10%\r50%\r100%", CarriageReturns::Split, vec!["10%", "50%", "100%"]),
+            ("10%\r50%\r100%", CarriageReturns::Keep, vec!["10%\r50%\r100%"]),
+            // a line ending in `\r\n` only loses that one
+            ("a\rb\r", CarriageReturns::Keep, vec!["a\rb"]),
+            ("a\rb\r", CarriageReturns::Split, vec!["a", "b"]),
+            ("progress\r", CarriageReturns::Last, vec!["progress"]),
+            ("\rredrawn", CarriageReturns::Last, vec!["redrawn"]),
+        ];
+        for (raw, carriage_returns, expected) in cases {
+            let options = WatchOptions { carriage_returns, ..WatchOptions::default() };
+            let lines = decode_line(raw.as_bytes(), encoding_rs::UTF_8, &options).unwrap();
+            assert_eq!(lines, expected, "{:?} with {:?}", raw, carriage_returns);
+        }
+    }
+}
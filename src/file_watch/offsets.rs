@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Map, Value};
+
+/// How far each file was read, kept in a JSON file so that a restart with
+/// `WatchOptions::resume` carries on from there instead of reading the files
+/// again. Offsets are only used for the same file, by device and inode, so
+/// a file that was rotated in the meantime is read as usual.
+pub struct SavedOffsets {
+    path: PathBuf,
+    offsets: HashMap<PathBuf, SavedOffset>,
+    /// Offsets were set since they were last saved
+    changed: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct SavedOffset {
+    identity: (u64, u64),
+    pos: u64,
+}
+
+impl SavedOffsets {
+    /// The offsets in the file at `path`. A missing or unreadable file is
+    /// the same as an empty one, it's replaced when the offsets are saved.
+    pub fn load(path: &Path) -> Self {
+        let offsets = match fs::read_to_string(path) {
+            Ok(text) => parse(&text).unwrap_or_else(|| {
+                log::warn!("Ignoring {}, it's not a list of offsets", path.display());
+                HashMap::new()
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => {
+                log::warn!("Cannot read offsets from {}: {}", path.display(), e);
+                HashMap::new()
+            }
+        };
+        SavedOffsets { path: path.to_owned(), offsets, changed: false }
+    }
+
+    /// Where to resume reading `file` from, if it's the same file as when its
+    /// offset was saved
+    pub fn get(&self, file: &Path, identity: (u64, u64)) -> Option<u64> {
+        self.offsets.get(file)
+            .filter(|saved| saved.identity == identity)
+            .map(|saved| saved.pos)
+    }
+
+    pub fn set(&mut self, file: &Path, identity: (u64, u64), pos: u64) {
+        let offset = SavedOffset { identity, pos };
+        if self.offsets.get(file) != Some(&offset) {
+            self.offsets.insert(file.to_owned(), offset);
+            self.changed = true;
+        }
+    }
+
+    /// Write the offsets out if they changed, through a temporary file so
+    /// that a crash part way leaves the last ones in place
+    pub fn save(&mut self) -> io::Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+        let entries: Map<String, Value> = self.offsets.iter()
+            .map(|(file, saved)| {
+                let (dev, ino) = saved.identity;
+                (file.display().to_string(), json!({ "dev": dev, "ino": ino, "pos": saved.pos }))
+            })
+            .collect();
+        let temp = self.path.with_extension("tmp");
+        fs::write(&temp, Value::Object(entries).to_string())?;
+        fs::rename(&temp, &self.path)?;
+        self.changed = false;
+        Ok(())
+    }
+}
+
+fn parse(text: &str) -> Option<HashMap<PathBuf, SavedOffset>> {
+    let Value::Object(entries) = serde_json::from_str(text).ok()? else {
+        return None;
+    };
+    entries.into_iter()
+        .map(|(file, saved)| {
+            let field = |name| saved.get(name).and_then(Value::as_u64);
+            let offset = SavedOffset { identity: (field("dev")?, field("ino")?), pos: field("pos")? };
+            Some((PathBuf::from(file), offset))
+        })
+        .collect()
+}
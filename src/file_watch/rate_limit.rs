@@ -0,0 +1,66 @@
+use std::time::{Duration, Instant};
+
+/// How often dropped lines are reported, see `RateLimit::report`
+const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Token bucket for the lines of one source: on average `per_sec` lines a
+/// second get through, and a burst of up to a second's worth gets through
+/// all at once
+pub struct RateLimit {
+    per_sec: f64,
+    tokens: f64,
+    refilled_at: Instant,
+    /// Lines dropped since the last report
+    dropped: u64,
+    reported_at: Instant,
+}
+
+impl RateLimit {
+    pub fn new(per_sec: u32) -> Self {
+        RateLimit {
+            per_sec: per_sec.into(),
+            tokens: per_sec.into(),
+            refilled_at: Instant::now(),
+            dropped: 0,
+            reported_at: Instant::now(),
+        }
+    }
+
+    /// How many of `wanted` lines may be sent now. The rest are counted as
+    /// dropped.
+    pub fn take(&mut self, wanted: usize) -> usize {
+        let now = Instant::now();
+        let refill = now.duration_since(self.refilled_at).as_secs_f64() * self.per_sec;
+        self.tokens = (self.tokens + refill).min(self.per_sec);
+        self.refilled_at = now;
+        let allowed = wanted.min(self.tokens as usize);
+        self.tokens -= allowed as f64;
+        self.dropped += (wanted - allowed) as u64;
+        allowed
+    }
+
+    /// What to tell the user about lines dropped since the last report, at
+    /// most every `REPORT_INTERVAL`
+    pub fn report(&mut self, id: &str) -> Option<String> {
+        if self.dropped == 0 || self.reported_at.elapsed() < REPORT_INTERVAL {
+            return None;
+        }
+        let text = format!("dropped {} lines from {} (rate limit)", thousands(self.dropped), id);
+        self.dropped = 0;
+        self.reported_at = Instant::now();
+        Some(text)
+    }
+}
+
+/// `n` with commas between groups of three digits, e.g. 8,512
+pub fn thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut text = String::with_capacity(digits.len() * 4 / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            text.push(',');
+        }
+        text.push(digit);
+    }
+    text
+}
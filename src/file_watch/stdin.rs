@@ -0,0 +1,83 @@
+use std::io::{self, BufRead, BufReader, Read};
+use std::sync::mpsc::SyncSender;
+
+use super::{send_event, LogsMessage, SourceEvent, WatchError, WatchOptions};
+
+/// Lines read from stdin are tagged with this id, unless it has a label
+pub const STDIN_ID: &str = "stdin";
+
+/// Reads lines from stdin until it's closed, e.g. the output of a command
+/// piped into filewatch. The UI reads keys from the terminal, not stdin, so
+/// this doesn't get in the way of key handling.
+///
+/// Reading stdin can't be interrupted, so unlike `watch_file` this doesn't
+/// take a `Shutdown`, and the thread running it shouldn't be joined.
+pub fn watch_stdin(options: &WatchOptions, tx: SyncSender<LogsMessage>) -> Result<(), WatchError> {
+    watch_reader(io::stdin().lock(), options, tx)
+}
+
+/// Reads lines from `input` until it's closed, as `watch_stdin`
+fn watch_reader(input: impl Read, options: &WatchOptions, tx: SyncSender<LogsMessage>) -> Result<(), WatchError> {
+    let id = options.id_or(STDIN_ID);
+    let encoding = options.encoding.initial();
+    let mut reader = BufReader::new(input);
+    let mut buf = Vec::new();
+    let mut lines = Vec::new();
+    loop {
+        buf.clear();
+        let read = reader.read_until(b'\n', &mut buf)
+            .inspect_err(|e| send_event(&tx, &id, SourceEvent::Error(format!("error reading stdin: {}", e))))?;
+        if read == 0 {
+            break;
+        }
+        if buf.ends_with(b"\n") {
+            buf.pop();
+        }
+        let (line, _) = encoding.decode_without_bom_handling(&buf);
+        lines.extend(options.clean_line(line.into_owned())
+            .into_iter()
+            .filter(|line| options.keeps(line)));
+        // send everything that's already buffered in one go
+        if !reader.buffer().contains(&b'\n') && !lines.is_empty() {
+            let lines = std::mem::take(&mut lines);
+            let msg = LogsMessage::new(id.clone(), SourceEvent::lines(lines, options));
+            if tx.send(msg).is_err() {
+                log::error!("stdin reader failed to send");
+                return Ok(());
+            }
+        }
+    }
+    if !lines.is_empty() {
+        let msg = LogsMessage::new(id.clone(), SourceEvent::lines(lines, options));
+        if tx.send(msg).is_err() {
+            log::error!("stdin reader failed to send");
+        }
+    }
+    send_event(&tx, &id, SourceEvent::Notice(String::from("stdin closed")));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    #[test]
+    fn closed_marker_after_the_last_line() {
+        let (tx, rx) = mpsc::sync_channel(16);
+        watch_reader(&b"one\r\ntwo\nlast without newline"[..], &WatchOptions::default(), tx).unwrap();
+        let received: Vec<_> = rx.iter().map(|message| (message.file_id, message.event)).collect();
+        let lines: Vec<&String> = received.iter()
+            .flat_map(|(_, event)| match event {
+                SourceEvent::Lines { lines, .. } => lines.iter().collect(),
+                _ => vec![],
+            })
+            .collect();
+        assert_eq!(lines, ["one", "two", "last without newline"]);
+        match received.last() {
+            Some((id, SourceEvent::Notice(notice))) => assert_eq!((id.as_str(), notice.as_str()), (STDIN_ID, "stdin closed")),
+            last => panic!("expected the closed notice last, got {:?}", last),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::io;
+use std::net::UdpSocket;
+use std::sync::mpsc::SyncSender;
+
+use super::{send_event, LogsMessage, SourceEvent, Shutdown, WatchError, WatchOptions, SHUTDOWN_CHECK_INTERVAL};
+
+pub const SYSLOG_PREFIX: &str = "syslog://";
+
+/// Datagrams that can't be parsed are shown as is, under this id
+const UNPARSED_ID: &str = "syslog:unparsed";
+
+const SEVERITIES: [&str; 8] = ["EMERG", "ALERT", "CRIT", "ERR", "WARNING", "NOTICE", "INFO", "DEBUG"];
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// Receives syslog datagrams on `addr` (e.g. `0.0.0.0:5514`) until `shutdown`
+/// is triggered. Messages are tagged with `hostname/app-name`, prefixed with
+/// `<label>:` if there is one, and their lines start with the message's
+/// timestamp and severity.
+pub fn watch_syslog(addr: &str, options: &WatchOptions, shutdown: Shutdown, tx: SyncSender<LogsMessage>) -> Result<(), WatchError> {
+    let id = options.id_or(&format!("{}{}", SYSLOG_PREFIX, addr));
+    let unparsed_id = match &options.label {
+        Some(label) => format!("{}:unparsed", label),
+        None => UNPARSED_ID.to_owned(),
+    };
+    let socket = UdpSocket::bind(addr)
+        .inspect_err(|e| send_event(&tx, &id, SourceEvent::Error(format!("cannot listen on {}: {}", addr, e))))?;
+    socket.set_read_timeout(Some(SHUTDOWN_CHECK_INTERVAL))?;
+    send_event(&tx, &id, SourceEvent::Notice(format!("listening for syslog on {}", addr)));
+
+    // the largest datagram syslog over UDP allows
+    let mut datagram = vec![0; 65535];
+    while !shutdown.is_triggered() {
+        let len = match socket.recv(&mut datagram) {
+            Ok(len) => len,
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted) => continue,
+            Err(e) => {
+                send_event(&tx, &id, SourceEvent::Error(format!("error receiving on {}: {}", addr, e)));
+                return Err(e.into());
+            }
+        };
+        let text = String::from_utf8_lossy(&datagram[..len]);
+        let text = text.trim_end_matches(['\r', '\n', '\0']);
+        let msg = match parse_syslog(text) {
+            Some(message) => {
+                let id = match &options.label {
+                    Some(label) => format!("{}:{}/{}", label, message.hostname, message.app_name),
+                    None => format!("{}/{}", message.hostname, message.app_name),
+                };
+                let line = format!("{} {} {}", message.timestamp, SEVERITIES[message.severity as usize], message.message);
+                LogsMessage::new(id, SourceEvent::lines(vec![line], options))
+            }
+            None => LogsMessage::new(unparsed_id.clone(), SourceEvent::lines(vec![text.to_owned()], options)),
+        };
+        if tx.send(msg).is_err() {
+            log::error!("Syslog receiver {} failed to send", addr);
+        }
+    }
+    log::debug!("Stopped listening on {}", addr);
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+struct SyslogMessage<'a> {
+    severity: u8,
+    timestamp: &'a str,
+    hostname: &'a str,
+    app_name: &'a str,
+    message: &'a str,
+}
+
+/// Parses an RFC 5424 or RFC 3164 (BSD) syslog message
+fn parse_syslog(text: &str) -> Option<SyslogMessage<'_>> {
+    let rest = text.strip_prefix('<')?;
+    let (pri, rest) = rest.split_once('>')?;
+    if pri.is_empty() || pri.len() > 3 {
+        return None;
+    }
+    let pri: u8 = pri.parse().ok()?;
+    let severity = pri % 8;
+    match rest.strip_prefix("1 ") {
+        Some(rest) => parse_rfc5424(severity, rest),
+        None => parse_rfc3164(severity, rest),
+    }
+}
+
+/// `TIMESTAMP HOSTNAME APP-NAME PROCID MSGID STRUCTURED-DATA [MSG]`
+fn parse_rfc5424(severity: u8, rest: &str) -> Option<SyslogMessage<'_>> {
+    let mut fields = rest.splitn(6, ' ');
+    let timestamp = fields.next()?;
+    let hostname = fields.next()?;
+    let app_name = fields.next()?;
+    let _proc_id = fields.next()?;
+    let _msg_id = fields.next()?;
+    let rest = fields.next()?;
+    let message = skip_structured_data(rest)?;
+    let message = message.strip_prefix(' ').unwrap_or(message);
+    let message = message.strip_prefix('\u{feff}').unwrap_or(message);
+    Some(SyslogMessage { severity, timestamp, hostname, app_name, message })
+}
+
+/// Returns what follows the structured data, which is either `-` or one or
+/// more `[...]` elements that can contain escaped `]`s and spaces
+fn skip_structured_data(text: &str) -> Option<&str> {
+    if let Some(rest) = text.strip_prefix('-') {
+        return Some(rest);
+    }
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while bytes.get(i) == Some(&b'[') {
+        loop {
+            i += 1;
+            match bytes.get(i)? {
+                b'\\' => i += 1,
+                b']' => break,
+                _ => {}
+            }
+        }
+        i += 1;
+    }
+    if i == 0 {
+        return None;
+    }
+    text.get(i..)
+}
+
+/// `Mmm dd hh:mm:ss HOSTNAME TAG[PID]: MSG`
+fn parse_rfc3164(severity: u8, rest: &str) -> Option<SyslogMessage<'_>> {
+    let timestamp = rest.get(..15)?;
+    let month = timestamp.get(..3)?;
+    if !MONTHS.contains(&month) || timestamp.as_bytes()[9] != b':' {
+        return None;
+    }
+    let rest = rest.get(15..)?.strip_prefix(' ')?;
+    let (hostname, rest) = rest.split_once(' ')?;
+    let (tag, message) = match rest.split_once(':') {
+        Some((tag, message)) if !tag.contains(' ') => (tag, message.trim_start()),
+        _ => ("-", rest),
+    };
+    // the pid isn't part of the app name
+    let app_name = tag.split('[').next().unwrap_or(tag);
+    Some(SyslogMessage { severity, timestamp, hostname, app_name, message })
+}
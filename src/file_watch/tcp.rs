@@ -0,0 +1,101 @@
+use std::io::{self, BufRead, BufReader};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::SyncSender;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use super::{send_event, LogsMessage, SourceEvent, Shutdown, WatchError, WatchOptions, SHUTDOWN_CHECK_INTERVAL};
+
+pub const TCP_PREFIX: &str = "tcp://";
+
+/// Accepts connections on `addr` (e.g. `0.0.0.0:5555`) until `shutdown` is
+/// triggered. Each connection is read on its own thread, and its lines are
+/// tagged with `tcp:<peer address>`, or `<label>:<peer address>`.
+pub fn watch_tcp(addr: &str, options: &WatchOptions, shutdown: Shutdown, tx: SyncSender<LogsMessage>) -> Result<(), WatchError> {
+    let id = options.id_or(&format!("{}{}", TCP_PREFIX, addr));
+    let peer_prefix = options.label.as_deref().unwrap_or("tcp");
+    let listener = TcpListener::bind(addr)
+        .inspect_err(|e| send_event(&tx, &id, SourceEvent::Error(format!("cannot listen on {}: {}", addr, e))))?;
+    // accept without blocking, so shutdown can be checked in between
+    listener.set_nonblocking(true)?;
+    send_event(&tx, &id, SourceEvent::Notice(format!("listening on {}", addr)));
+
+    let mut connections: Vec<JoinHandle<()>> = vec![];
+    while !shutdown.is_triggered() {
+        match listener.accept() {
+            Ok((stream, peer)) => {
+                let peer_id = format!("{}:{}", peer_prefix, peer);
+                let options = options.clone();
+                let shutdown = shutdown.clone();
+                let tx = tx.clone();
+                connections.push(std::thread::spawn(move || {
+                    send_event(&tx, &peer_id, SourceEvent::Notice(String::from("connection opened")));
+                    if let Err(e) = read_connection(stream, &peer_id, &options, &shutdown, &tx) {
+                        send_event(&tx, &peer_id, SourceEvent::Error(format!("error reading connection: {}", e)));
+                    }
+                    send_event(&tx, &peer_id, SourceEvent::Notice(String::from("connection closed")));
+                }));
+                connections.retain(|c| !c.is_finished());
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                shutdown.wait_timeout(Duration::from_millis(100));
+            }
+            Err(e) => log::error!("Failed to accept connection on {}: {}", addr, e)
+        }
+    }
+    for connection in connections {
+        if connection.join().is_err() {
+            log::error!("Connection thread for {} panicked", addr);
+        }
+    }
+    log::debug!("Stopped listening on {}", addr);
+    Ok(())
+}
+
+fn read_connection(stream: TcpStream, id: &str, options: &WatchOptions, shutdown: &Shutdown, tx: &SyncSender<LogsMessage>) -> Result<(), io::Error> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(SHUTDOWN_CHECK_INTERVAL))?;
+    let encoding = options.encoding.initial();
+    let mut reader = BufReader::new(stream);
+    let mut buf = Vec::new();
+    while !shutdown.is_triggered() {
+        // a line can be split across packets. On a timeout, the part that did
+        // arrive stays in `buf` and the rest is appended to it next time round
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) => break,
+            Ok(_) => {
+                if !buf.ends_with(b"\n") {
+                    // closed mid-line
+                    break;
+                }
+                buf.pop();
+                let (line, _) = encoding.decode_without_bom_handling(&buf);
+                let lines: Vec<String> = options.clean_line(line.into_owned())
+                    .into_iter()
+                    .filter(|line| options.keeps(line))
+                    .collect();
+                if !lines.is_empty() {
+                    let msg = LogsMessage::new(id.to_owned(), SourceEvent::lines(lines, options));
+                    if tx.send(msg).is_err() {
+                        log::error!("Connection reader {} failed to send", id);
+                    }
+                }
+                buf.clear();
+            }
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    let (line, _) = encoding.decode_without_bom_handling(&buf);
+    let lines: Vec<String> = options.clean_line(line.into_owned())
+        .into_iter()
+        .filter(|line| options.keeps(line))
+        .collect();
+    if !buf.is_empty() && !lines.is_empty() {
+        let msg = LogsMessage::new(id.to_owned(), SourceEvent::lines(lines, options));
+        if tx.send(msg).is_err() {
+            log::error!("Connection reader {} failed to send", id);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use std::str::FromStr;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, NaiveDateTime, NaiveTime, TimeZone};
+use regex::Regex;
+
+/// How to find the time a line was logged: a chrono format like
+/// `%Y-%m-%d %H:%M:%S%.f`, parsed from the start of the line or from what a
+/// regex picks out of it. Times without a date are taken to be today, and
+/// times without an offset to be local.
+#[derive(Clone, Debug)]
+pub struct TimestampFormat {
+    format: String,
+    /// The timestamp is the `ts` group, or the first group, or the whole match
+    regex: Option<Regex>,
+}
+
+impl FromStr for TimestampFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+            return Err(format!("invalid timestamp format '{}'", format));
+        }
+        Ok(TimestampFormat { format: format.to_owned(), regex: None })
+    }
+}
+
+impl TimestampFormat {
+    pub fn with_regex(mut self, regex: Option<Regex>) -> Self {
+        self.regex = regex;
+        self
+    }
+
+    /// Milliseconds since the epoch, or None if the line doesn't have a
+    /// timestamp in this format
+    pub fn parse(&self, line: &str) -> Option<i64> {
+        let text = match &self.regex {
+            Some(regex) => {
+                let captures = regex.captures(line)?;
+                captures.name("ts").or_else(|| captures.get(1)).or_else(|| captures.get(0))?.as_str()
+            }
+            None => line,
+        };
+        let format = self.format.as_str();
+        if let Ok((time, _)) = DateTime::parse_and_remainder(text, format) {
+            return Some(time.timestamp_millis());
+        }
+        let naive = match NaiveDateTime::parse_and_remainder(text, format) {
+            Ok((naive, _)) => naive,
+            Err(_) => {
+                let (time, _) = NaiveTime::parse_and_remainder(text, format).ok()?;
+                Local::now().date_naive().and_time(time)
+            }
+        };
+        Some(Local.from_local_datetime(&naive).earliest()?.timestamp_millis())
+    }
+}
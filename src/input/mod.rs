@@ -0,0 +1,314 @@
+use std::io;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{DisableMouseCapture, EnableMouseCapture, KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+
+/// What a key does in the pager, when no prompt is open, see `KEYMAP`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Quit,
+    /// The first row, `gg` or Home
+    Top,
+    /// The last row, turning on follow mode, `G` or End
+    Bottom,
+    ToggleFollow,
+    /// Stop showing new rows, or show them again, see `App::toggle_pause`
+    TogglePause,
+    /// None, the time of day, how long ago, see `Timestamps` in main
+    CycleTimestamps,
+    CyclePrefixes,
+    ToggleLineNumbers,
+    /// Lines between rows far apart in time, see `App::toggle_gaps`
+    ToggleGaps,
+    ToggleStats,
+    ToggleWrap,
+    ToggleWordWrap,
+    ToggleLevelColors,
+    ReloadConfig,
+    /// Half a screen sideways, right if true
+    ScrollSideways(bool),
+    /// To the start of lines, or the end if true
+    ScrollToSide(bool),
+    Search,
+    Export,
+    /// A prompt for a line to go to, see `App::go_to_line`
+    GoToLine,
+    /// The next match, or the one before if -1
+    JumpToMatch(isize),
+    CycleMinLevel,
+    ToggleEvents,
+    ToggleRepeats,
+    /// A second pane below, or close the one keys don't go to, see
+    /// `App::toggle_split`
+    ToggleSplit,
+    /// Keys go to the other pane
+    SwitchPane,
+    /// The focused pane shows the file with this number, or all files for
+    /// 0, see `App::switch_tab`
+    SwitchTab(usize),
+    /// The focused pane goes to the tab this many to the right, or to the
+    /// left if negative, see `App::cycle_tab`
+    CycleTab(isize),
+    /// The most common lines, to only show one of them, see
+    /// `App::open_frequencies`
+    Frequencies,
+    /// The list of files, to hide or show them, see `App::toggle_sidebar`
+    ToggleSidebar,
+    /// Hide the rows of the file at this index, in the order the sidebar
+    /// lists them, or show them again, typed as its number in place of
+    /// `ANY_DIGIT`
+    ToggleFile(usize),
+    /// The cursor this many rows down, or up if negative, see
+    /// `App::move_cursor`
+    MoveCursor(isize),
+    /// Screen lines down
+    ScrollDown(usize),
+    /// Screen lines up
+    ScrollUp(usize),
+    PageDown,
+    PageUp,
+    /// A click at this column and row of the terminal, which selects the
+    /// row of logs there or scrolls along the scrollbar
+    Click(u16, u16),
+    /// The mouse dragged to this row of the terminal
+    Drag(u16),
+    /// All of the row under the cursor, in a popup
+    Details,
+    /// The row under the cursor to the clipboard
+    CopyLine,
+    /// The rows on screen to the clipboard, as they're shown but unwrapped
+    CopyScreen,
+    /// Mark the row at the top of the screen with the letter typed in place
+    /// of `ANY_LETTER`
+    SetMark(char),
+    /// Back to the row marked with the letter
+    JumpToMark(char),
+    /// Back to where the screen was before the last jump to a mark
+    JumpBack,
+    ListMarks,
+    /// The lines that matched `--alert`, to jump to one, see `App::alerts`
+    ListAlerts,
+    /// Pin the row under the cursor above the logs, see `App::toggle_pin`
+    TogglePin,
+    /// Keys go to the pinned rows, or back, see `App::toggle_pins_focus`
+    FocusPins,
+    /// A prompt for a regex to chart the numbers of, see `ui::Graphs`
+    Graph,
+    /// Only the search matches and the rows around them, or all rows, see
+    /// `App::toggle_context`
+    ToggleContext,
+    /// This many more rows around each match, or fewer if negative, see
+    /// `App::change_context`
+    ChangeContext(isize),
+    Help,
+}
+
+/// Screen lines a turn of the mouse wheel scrolls
+const WHEEL_LINES: usize = 3;
+
+/// How long after the first key of one that takes two the second can come,
+/// before the first is forgotten
+const PENDING_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// In a `Binding`'s keys, any letter, which its action takes
+pub const ANY_LETTER: KeyCode = KeyCode::Null;
+
+/// In a `Binding`'s keys, any digit from 1 to 9, which its action takes. No
+/// key is F0.
+pub const ANY_DIGIT: KeyCode = KeyCode::F(0);
+
+/// What part of the help a `Binding` is listed in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Category {
+    Navigation,
+    Search,
+    Filters,
+    Display,
+    Other,
+}
+
+impl Category {
+    pub const ALL: [Category; 5] = [Category::Navigation, Category::Search, Category::Filters, Category::Display, Category::Other];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Navigation => "navigation",
+            Category::Search => "search",
+            Category::Filters => "filters",
+            Category::Display => "display",
+            Category::Other => "other",
+        }
+    }
+}
+
+/// Keys that do an action, and what the help says about it
+pub struct Binding {
+    /// Each way of typing it, as keys one after the other
+    pub keys: &'static [&'static [KeyCode]],
+    pub action: Action,
+    pub category: Category,
+    pub help: &'static str,
+}
+
+impl Binding {
+    /// The keys as the help shows them, e.g. `j / ↓`
+    pub fn keys_label(&self) -> String {
+        let typed: Vec<String> = self.keys.iter().map(|keys| keys.iter().map(|key| key_name(*key)).collect()).collect();
+        typed.join(" / ")
+    }
+}
+
+/// Every key of the pager, in the order the help lists them
+pub const KEYMAP: &[Binding] = &[
+    Binding { keys: &[&[KeyCode::Char('j')]], action: Action::MoveCursor(1), category: Category::Navigation, help: "cursor down a line" },
+    Binding { keys: &[&[KeyCode::Char('k')]], action: Action::MoveCursor(-1), category: Category::Navigation, help: "cursor up a line" },
+    Binding { keys: &[&[KeyCode::Down]], action: Action::ScrollDown(1), category: Category::Navigation, help: "down a line" },
+    Binding { keys: &[&[KeyCode::Up]], action: Action::ScrollUp(1), category: Category::Navigation, help: "up a line" },
+    Binding { keys: &[&[KeyCode::PageDown]], action: Action::PageDown, category: Category::Navigation, help: "down a page" },
+    Binding { keys: &[&[KeyCode::PageUp]], action: Action::PageUp, category: Category::Navigation, help: "up a page" },
+    Binding { keys: &[&[KeyCode::Char('g'), KeyCode::Char('g')], &[KeyCode::Home]], action: Action::Top, category: Category::Navigation, help: "first line" },
+    Binding { keys: &[&[KeyCode::Char('G')], &[KeyCode::End]], action: Action::Bottom, category: Category::Navigation, help: "last line, in follow mode" },
+    Binding { keys: &[&[KeyCode::Char(':')]], action: Action::GoToLine, category: Category::Navigation, help: "go to a line number, a percentage like 50% or +N / -N" },
+    Binding { keys: &[&[KeyCode::Char('m'), ANY_LETTER]], action: Action::SetMark('a'), category: Category::Navigation, help: "mark the top line with a letter" },
+    Binding { keys: &[&[KeyCode::Char('\''), ANY_LETTER]], action: Action::JumpToMark('a'), category: Category::Navigation, help: "back to the line marked with a letter" },
+    Binding { keys: &[&[KeyCode::Char('\''), KeyCode::Char('\'')]], action: Action::JumpBack, category: Category::Navigation, help: "back to before the last jump to a mark" },
+    Binding { keys: &[&[KeyCode::Char('M')]], action: Action::ListMarks, category: Category::Navigation, help: "the marks there are" },
+    Binding { keys: &[&[KeyCode::Char('!')]], action: Action::ListAlerts, category: Category::Navigation, help: "lines that matched --alert, Enter on one jumps to it" },
+    Binding { keys: &[&[KeyCode::Char('P')]], action: Action::TogglePin, category: Category::Navigation, help: "pin the line under the cursor above the others, or unpin it" },
+    Binding { keys: &[&[KeyCode::Char('`')]], action: Action::FocusPins, category: Category::Navigation, help: "the pinned lines, j/k and Enter in them to jump to one" },
+    Binding { keys: &[&[KeyCode::Char('f')]], action: Action::ToggleFollow, category: Category::Navigation, help: "follow mode on or off" },
+    Binding { keys: &[&[KeyCode::Char('p')]], action: Action::TogglePause, category: Category::Navigation, help: "pause new lines, still storing them, or show them" },
+    Binding { keys: &[&[KeyCode::Left]], action: Action::ScrollSideways(false), category: Category::Navigation, help: "left half a screen, when not wrapping" },
+    Binding { keys: &[&[KeyCode::Right]], action: Action::ScrollSideways(true), category: Category::Navigation, help: "right half a screen, when not wrapping" },
+    Binding { keys: &[&[KeyCode::Char('0')]], action: Action::ScrollToSide(false), category: Category::Navigation, help: "start of lines" },
+    Binding { keys: &[&[KeyCode::Char('$')]], action: Action::ScrollToSide(true), category: Category::Navigation, help: "end of the longest line" },
+    Binding { keys: &[&[KeyCode::Char('/')]], action: Action::Search, category: Category::Search, help: "search, Ctrl-r in the prompt for regex or words" },
+    Binding { keys: &[&[KeyCode::Char('n')]], action: Action::JumpToMatch(1), category: Category::Search, help: "next match" },
+    Binding { keys: &[&[KeyCode::Char('N')]], action: Action::JumpToMatch(-1), category: Category::Search, help: "previous match" },
+    Binding { keys: &[&[KeyCode::Char('o')]], action: Action::ToggleContext, category: Category::Search, help: "only matches and the lines around them, or all lines" },
+    Binding { keys: &[&[KeyCode::Char('+')]], action: Action::ChangeContext(1), category: Category::Search, help: "a line more around matches" },
+    Binding { keys: &[&[KeyCode::Char('-')]], action: Action::ChangeContext(-1), category: Category::Search, help: "a line fewer around matches" },
+    Binding { keys: &[&[KeyCode::Char('l')]], action: Action::CycleMinLevel, category: Category::Filters, help: "lowest level shown" },
+    Binding { keys: &[&[KeyCode::Char('e')]], action: Action::ToggleEvents, category: Category::Filters, help: "events shown or hidden" },
+    Binding { keys: &[&[KeyCode::Tab]], action: Action::ToggleSidebar, category: Category::Filters, help: "files, j/k and Space in it to hide or show one" },
+    Binding { keys: &[&[ANY_DIGIT]], action: Action::ToggleFile(0), category: Category::Filters, help: "hide or show the file with that number in the files" },
+    Binding { keys: &[&[KeyCode::Char('F')]], action: Action::Frequencies, category: Category::Filters, help: "most common lines, Enter on one to only show lines like it" },
+    Binding { keys: &[&[KeyCode::Char('c')]], action: Action::ToggleRepeats, category: Category::Filters, help: "repeated lines collapsed or not" },
+    Binding { keys: &[&[KeyCode::Char('t')]], action: Action::CycleTimestamps, category: Category::Display, help: "timestamps: time of day, how long ago, none" },
+    Binding { keys: &[&[KeyCode::Char('i')]], action: Action::CyclePrefixes, category: Category::Display, help: "file prefixes: path, name, short, none" },
+    Binding { keys: &[&[KeyCode::Char('#')]], action: Action::ToggleLineNumbers, category: Category::Display, help: "line numbers" },
+    Binding { keys: &[&[KeyCode::Char('_')]], action: Action::ToggleGaps, category: Category::Display, help: "lines between lines far apart in time" },
+    Binding { keys: &[&[KeyCode::Char('v')]], action: Action::ToggleWrap, category: Category::Display, help: "wrap long lines or not" },
+    Binding { keys: &[&[KeyCode::Char('W')]], action: Action::ToggleWordWrap, category: Category::Display, help: "wrap at words or anywhere" },
+    Binding { keys: &[&[KeyCode::Char('C')]], action: Action::ToggleLevelColors, category: Category::Display, help: "colors by level on or off" },
+    Binding { keys: &[&[KeyCode::Char('|')]], action: Action::ToggleSplit, category: Category::Display, help: "split the screen in two panes, or back to one" },
+    Binding { keys: &[&[KeyCode::BackTab]], action: Action::SwitchPane, category: Category::Display, help: "keys go to the other pane" },
+    Binding { keys: &[&[KeyCode::Char('g'), KeyCode::Char('t')]], action: Action::CycleTab(1), category: Category::Display, help: "next tab, of the next file" },
+    Binding { keys: &[&[KeyCode::Char('g'), KeyCode::Char('T')]], action: Action::CycleTab(-1), category: Category::Display, help: "tab before" },
+    Binding { keys: &[&[KeyCode::Char('g'), KeyCode::Char('0')], &[KeyCode::Char('g'), ANY_DIGIT]], action: Action::SwitchTab(0), category: Category::Display, help: "tab of all files, or of the file with that number" },
+    Binding { keys: &[&[KeyCode::Char('a')]], action: Action::Graph, category: Category::Display, help: "chart the numbers a regex with a group picks out of lines, or stop" },
+    Binding { keys: &[&[KeyCode::Char('s')]], action: Action::ToggleStats, category: Category::Display, help: "stats of each file" },
+    Binding { keys: &[&[KeyCode::Enter]], action: Action::Details, category: Category::Other, help: "all of the line under the cursor" },
+    Binding { keys: &[&[KeyCode::Char('y')]], action: Action::CopyLine, category: Category::Other, help: "copy the line under the cursor" },
+    Binding { keys: &[&[KeyCode::Char('Y')]], action: Action::CopyScreen, category: Category::Other, help: "copy the lines on screen" },
+    Binding { keys: &[&[KeyCode::Char('w')]], action: Action::Export, category: Category::Other, help: "write the lines shown to a file" },
+    Binding { keys: &[&[KeyCode::Char('R')]], action: Action::ReloadConfig, category: Category::Other, help: "read the config file again" },
+    Binding { keys: &[&[KeyCode::Char('?')]], action: Action::Help, category: Category::Other, help: "this help" },
+    Binding { keys: &[&[KeyCode::Char('q')]], action: Action::Quit, category: Category::Other, help: "quit" },
+];
+
+/// How `key` is shown in the help
+fn key_name(key: KeyCode) -> String {
+    match key {
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Up => String::from("↑"),
+        KeyCode::Down => String::from("↓"),
+        KeyCode::Left => String::from("←"),
+        KeyCode::Right => String::from("→"),
+        KeyCode::PageUp => String::from("PgUp"),
+        KeyCode::PageDown => String::from("PgDn"),
+        KeyCode::BackTab => String::from("Shift-Tab"),
+        ANY_LETTER => String::from("<a-z>"),
+        ANY_DIGIT => String::from("1-9"),
+        key => key.to_string(),
+    }
+}
+
+/// Turns key presses into `Action`s by `KEYMAP`, keeping the first keys of
+/// the ones that take more until the rest come
+#[derive(Default)]
+pub struct Keys {
+    pending: Vec<KeyCode>,
+    pending_at: Option<Instant>,
+}
+
+impl Keys {
+    /// The action `key` finishes, if any. As in less and vim, `gg` goes to
+    /// the top: a `g` on its own does nothing, and is forgotten if the second
+    /// doesn't come within `PENDING_TIMEOUT`. Any other key after it does
+    /// what it does on its own.
+    pub fn action(&mut self, key: KeyEvent) -> Option<Action> {
+        if self.pending_at.is_some_and(|at| at.elapsed() >= PENDING_TIMEOUT) {
+            self.pending.clear();
+        }
+        let mut typed = std::mem::take(&mut self.pending);
+        typed.push(key.code);
+        if let Some(binding) = KEYMAP.iter().find(|binding| binding.keys.iter().any(|keys| typed_as(&typed, keys))) {
+            return Some(match (binding.action, key.code) {
+                (Action::SetMark(_), KeyCode::Char(letter)) => Action::SetMark(letter),
+                (Action::JumpToMark(_), KeyCode::Char(letter)) => Action::JumpToMark(letter),
+                (Action::ToggleFile(_), KeyCode::Char(digit)) => Action::ToggleFile(digit.to_digit(10).unwrap_or(1) as usize - 1),
+                (Action::SwitchTab(_), KeyCode::Char(digit)) => Action::SwitchTab(digit.to_digit(10).unwrap_or(0) as usize),
+                (action, _) => action,
+            });
+        }
+        let started = KEYMAP.iter().flat_map(|binding| binding.keys).any(|keys| keys.len() > typed.len() && typed_as(&typed, &keys[..typed.len()]));
+        if started {
+            self.pending = typed;
+            self.pending_at = Some(Instant::now());
+            return None;
+        }
+        if typed.len() > 1 {
+            return self.action(key);
+        }
+        None
+    }
+}
+
+/// `typed` is the same as `keys`, with any letter for `ANY_LETTER` and
+/// digit for `ANY_DIGIT`
+fn typed_as(typed: &[KeyCode], keys: &[KeyCode]) -> bool {
+    typed.len() == keys.len() && typed.iter().zip(keys).all(|(typed, key)| match (*key, typed) {
+        (ANY_LETTER, KeyCode::Char(c)) => c.is_ascii_alphabetic(),
+        (ANY_DIGIT, KeyCode::Char(c)) => ('1'..='9').contains(c),
+        (key, typed) => key == *typed,
+    })
+}
+
+/// What `mouse` does in the pager, if anything: the wheel scrolls, and a
+/// click selects a row or, like a drag, moves along the scrollbar
+pub fn mouse_action(mouse: MouseEvent) -> Option<Action> {
+    match mouse.kind {
+        MouseEventKind::ScrollDown => Some(Action::ScrollDown(WHEEL_LINES)),
+        MouseEventKind::ScrollUp => Some(Action::ScrollUp(WHEEL_LINES)),
+        MouseEventKind::Down(MouseButton::Left) => Some(Action::Click(mouse.column, mouse.row)),
+        MouseEventKind::Drag(MouseButton::Left) => Some(Action::Drag(mouse.row)),
+        _ => None,
+    }
+}
+
+/// Have the terminal send mouse events rather than select text, until
+/// `disable_mouse`. That's also done if the program panics, or the
+/// terminal would be left eating clicks.
+pub fn enable_mouse() -> io::Result<()> {
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = disable_mouse();
+        hook(info);
+    }));
+    crossterm::execute!(io::stdout(), EnableMouseCapture)
+}
+
+pub fn disable_mouse() -> io::Result<()> {
+    crossterm::execute!(io::stdout(), DisableMouseCapture)
+}
@@ -0,0 +1,29 @@
+//! The watching side of filewatch, without the UI: tails files, directories,
+//! pipes, sockets and commands, and sends what they produce down a channel.
+//!
+//! `FileWatcherBuilder` covers the common case of one file. For everything
+//! else, the `file_watch::watch_*` functions block the calling thread until
+//! their `Shutdown` is triggered.
+//!
+//! ```no_run
+//! use std::sync::mpsc;
+//! use std::thread;
+//! use filewatch_rs::file_watch::{self, Shutdown, WatchOptions};
+//!
+//! let (tx, rx) = mpsc::sync_channel(file_watch::DEFAULT_CHANNEL_CAPACITY);
+//! let shutdown = Shutdown::default();
+//! let watcher_shutdown = shutdown.clone();
+//! let watcher = thread::spawn(move || {
+//!     file_watch::watch_dir(&String::from("logs"), &WatchOptions::default(), watcher_shutdown, tx)
+//! });
+//! for message in rx.iter().take(100) {
+//!     println!("{}: {}", message.file_id, message.event.describe());
+//! }
+//! shutdown.trigger();
+//! watcher.join().unwrap()?;
+//! # Ok::<(), filewatch_rs::WatchError>(())
+//! ```
+
+pub mod file_watch;
+
+pub use file_watch::{FileWatcherBuilder, LogsMessage, SourceEvent, WatchError, WatchHandle};
@@ -1,31 +1,380 @@
-mod file_watch;
+mod alert;
+mod clipboard;
+mod config;
+mod file_spec;
+mod input;
+mod sessions;
+mod store;
 mod ui;
 
-use std::collections::HashMap;
-use std::{fs, sync, usize};
-use std::path::PathBuf;
-use log::{debug, error, info, LevelFilter};
+use std::collections::{HashMap, HashSet};
+use std::{fmt, fs, sync};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use signal_hook::consts::{SIGINT, SIGTERM};
+use log::{debug, error, info, warn, LevelFilter};
 use simplelog::{CombinedLogger, Config, TermLogger, WriteLogger, TerminalMode, ColorChoice};
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use file_spec::FileSpec;
+use filewatch_rs::file_watch;
+use store::LogStore;
 
 /// A file watcher and log aggregator
 #[derive(Parser, Debug)]
-#[clap(author, version, about)]
+#[clap(author, version, about, args_conflicts_with_subcommands = true)]
 struct Args {
-    /// Files to watch
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// Files to watch. Glob patterns (e.g. 'logs/*.log') are expanded at startup.
+    /// Directories are watched for new files. Per-file options can be appended
+    /// as `PATH:KEY=VALUE`, e.g. `app.log:encoding=utf16le`. `PATH=LABEL` shows
+    /// LABEL instead of the path (write '=' in a path as '\='). `-` reads stdin,
+    /// `tcp://ADDR:PORT` accepts lines over TCP, `syslog://ADDR:PORT` receives
+    /// syslog over UDP, `exec:COMMAND` runs a command and tails its output.
+    /// Gzip archives (`app.log.1.gz`) are read once, as history
     #[clap(required = true)]
-    files: Vec<String>,
+    files: Vec<FileSpec>,
+
+    /// Also watch files in subdirectories of watched directories
+    #[clap(short = 'r', long)]
+    recursive: bool,
+
+    /// Poll for changes instead of using native file notifications, e.g. for
+    /// files on NFS or SSHFS mounts (default interval: 1000ms)
+    #[clap(long, value_name = "INTERVAL_MS", num_args = 0..=1, default_missing_value = "1000")]
+    poll: Option<u64>,
+
+    /// Only poll files under these paths, other files use native notifications
+    #[clap(long, value_name = "PATH", num_args = 1..)]
+    poll_paths: Vec<PathBuf>,
+
+    /// Keep retrying files that don't exist (yet) every second, instead of giving up
+    #[clap(short = 'w', long)]
+    wait: bool,
+
+    /// How long to wait for the rest of a line written without a trailing
+    /// newline before showing it as is
+    #[clap(long, value_name = "MS", default_value_t = 1000)]
+    partial_flush_ms: u64,
+
+    /// Coalesce a file's modify events over this window and read them in one
+    /// go, so busy files are read in batches. 0 reads on every event
+    #[clap(long, value_name = "MS", default_value_t = 50)]
+    debounce_ms: u64,
+
+    /// Show JSON lines through `--json-format` (or as `key=value` pairs)
+    /// rather than raw. The raw line is still what's stored. Per file with
+    /// `PATH:json`
+    #[clap(long)]
+    json: bool,
+
+    /// Template for JSON lines, e.g. '{level} {msg} {error.kind}'
+    #[clap(long, value_name = "TEMPLATE")]
+    json_format: Option<file_watch::JsonFormat>,
+
+    /// Order lines from different files by the time they were logged rather
+    /// than the time they were read, parsing it with this chrono format, e.g.
+    /// '%Y-%m-%d %H:%M:%S%.f'. Lines without one stay after the line before
+    #[clap(long, value_name = "FORMAT")]
+    timestamp_format: Option<file_watch::TimestampFormat>,
+
+    /// Where the timestamp is in each line, if it's not at the start: the `ts`
+    /// group, the first group or the whole match
+    #[clap(long, value_name = "REGEX", requires = "timestamp_format")]
+    timestamp_regex: Option<regex::Regex>,
+
+    /// How to find each line's severity: the first group that matched, or
+    /// the whole match, is read as a level name (ERROR, warn, ...) or syslog
+    /// priority. By default level names and `<PRI>` prefixes are found.
+    /// Per file with `PATH:level=REGEX`
+    #[clap(long, value_name = "REGEX")]
+    level_regex: Option<regex::Regex>,
+
+    /// Start in follow mode, staying at the last line as lines come in
+    /// until scrolling up. `f` turns it on and off
+    #[clap(long)]
+    follow: bool,
+
+    /// Show a line like `── 4m 12s gap ──` between lines that came further
+    /// apart than this, e.g. `30s` or `5m`: logged, going by
+    /// `--timestamp-format`, otherwise read. `_` turns these lines on and
+    /// off
+    #[clap(long, value_name = "DURATION", default_value = "30s", value_parser = sessions::parse_age)]
+    gap_threshold: Duration,
+
+    /// Show each line's number left of it, as the status bar counts them.
+    /// `#` turns this on and off
+    #[clap(long)]
+    line_numbers: bool,
+
+    /// Leave the mouse to the terminal, so text can be selected as usual,
+    /// rather than scrolling with the wheel and selecting rows by clicking
+    #[clap(long)]
+    no_mouse: bool,
+
+    /// Where `y` and `Y` copy lines to: `system` for the system's clipboard,
+    /// `osc52` for the terminal's, through an escape sequence, which works
+    /// over SSH but has to be allowed by some terminals, or `auto` for OSC 52
+    /// in an SSH session and the system's otherwise
+    #[clap(long, value_name = "BACKEND", default_value = "auto")]
+    clipboard: clipboard::Backend,
+
+    /// Shown at the start of each screen line a long line is wrapped onto,
+    /// '' for nothing
+    #[clap(long, value_name = "STRING", default_value = "↪ ")]
+    wrap_indicator: String,
+
+    /// Indent the screen lines a line is wrapped onto as much as the line
+    /// itself is, so that e.g. the frames of a stack trace stay lined up
+    #[clap(long)]
+    wrap_indent: bool,
+
+    /// Tabs in lines go to the next multiple of this many columns. Other
+    /// control chars are shown as symbols, e.g. `␛` for an escape
+    #[clap(long, value_name = "N", default_value_t = 8, value_parser = clap::value_parser!(u16).range(1..))]
+    tab_width: u16,
+
+    /// Pick out text matching REGEX in the style after the last `:`, e.g.
+    /// `ERROR:red` or `user=\w+:cyan underlined`, see `--style`. Bold if
+    /// only colors are given, bold yellow if nothing is. Can be given more
+    /// than once, later ones win where they overlap, and over the config
+    /// file's
+    #[clap(long = "highlight", value_name = "REGEX[:STYLE]", value_parser = ui::Highlight::from_str)]
+    highlights: Vec<ui::Highlight>,
+
+    /// How lines of a level are shown, e.g. `error=red bold` or `debug=dim`:
+    /// colors, `on_` and a color for the background, and bold, dim, italic,
+    /// underlined, reversed or blink. Warnings are yellow and errors red
+    /// unless given. `C` turns this coloring on and off
+    #[clap(long = "style", value_name = "LEVEL=STYLE", value_parser = ui::LevelStyle::from_str)]
+    level_styles: Vec<ui::LevelStyle>,
+
+    /// Read highlights and level styles from this file rather than
+    /// ~/.config/filewatch/config, see `config::Config`. `R` reads it again
+    #[clap(long)]
+    config: Option<PathBuf>,
+
+    /// Only show lines matching REGEX. Can be given more than once, lines
+    /// matching any of them are shown. Per file with `PATH:filter=REGEX`
+    #[clap(long = "filter", value_name = "REGEX")]
+    filters: Vec<regex::Regex>,
+
+    /// Drop lines matching REGEX, e.g. health checks. Can be given more than
+    /// once. Per file with `PATH:exclude=REGEX`
+    #[clap(long = "exclude", value_name = "REGEX")]
+    excludes: Vec<regex::Regex>,
+
+    /// Ring the terminal's bell and flash the status bar when a line
+    /// matching REGEX comes in, e.g. 'FATAL|panic', until `!` lists the
+    /// lines that did. Can be given more than once
+    #[clap(long = "alert", value_name = "REGEX")]
+    alerts: Vec<regex::Regex>,
+
+    /// Run COMMAND through the shell for each `--alert`, with the line on
+    /// its stdin, e.g. 'notify-send filewatch "$(cat)"'. One runs at a time,
+    /// for 30s at the most, and alerts that come in the meantime wait their
+    /// turn, or are dropped when too many are waiting
+    #[clap(long, value_name = "COMMAND", requires = "alerts")]
+    alert_exec: Option<String>,
+
+    /// Chart the number in the first group of REGEX in the lines it
+    /// matches over the last 5 minutes, below the logs, e.g.
+    /// 'latency_ms=(\d+)'. Can be given more than once, for a line each. `a`
+    /// adds more
+    #[clap(long = "graph", value_name = "REGEX", value_parser = ui::parse_graph)]
+    graphs: Vec<regex::Regex>,
+
+    /// Drop empty lines instead of showing them
+    #[clap(long)]
+    skip_blank: bool,
+
+    /// Store a line that's the same as the one before it from the same file
+    /// as a count on that one instead, shown as `last message repeated N
+    /// times`. `c` turns this on and off
+    #[clap(long)]
+    collapse_repeats: bool,
+
+    /// Start counting again when a repeat comes more than this long after
+    /// the one before
+    #[clap(long, value_name = "SECS", default_value_t = 60)]
+    repeat_gap_secs: u64,
+
+    /// What to do with `\r` in the middle of a line, e.g. from progress bars:
+    /// `last` keeps what comes after the last one, like a terminal shows it,
+    /// `split` makes each one a line break, `keep` leaves them in
+    #[clap(long, value_name = "MODE", default_value = "last")]
+    carriage_returns: file_watch::CarriageReturns,
+
+    /// Drop lines from a file that writes more than N a second, reporting
+    /// how many were dropped. Short bursts get through
+    #[clap(long, value_name = "N")]
+    max_lines_per_sec: Option<u32>,
+
+    /// Cut lines longer than this, marking them as truncated
+    #[clap(long, value_name = "BYTES", default_value_t = 64 * 1024)]
+    max_line_bytes: usize,
+
+    /// Read files that look binary instead of skipping them, with control
+    /// characters replaced
+    #[clap(long)]
+    force_binary: bool,
+
+    /// What to do with colors and other terminal escape sequences in lines:
+    /// `strip` removes them before lines are stored, `render` stores them and
+    /// shows the colors, `never` stores them but shows lines without them
+    #[clap(long, value_name = "WHEN", default_value = "strip")]
+    color: ColorMode,
+
+    /// Show each file's name in front of its lines in a color of its own,
+    /// the same one every time: `always`, or `never` for a monochrome
+    /// terminal
+    #[clap(long, value_name = "WHEN", default_value = "always")]
+    color_files: ColorFiles,
+
+    /// Group lines into multi-line records (e.g. stack traces): lines that
+    /// don't match REGEX are joined to the line before
+    #[clap(long, value_name = "REGEX")]
+    multiline_start: Option<regex::Regex>,
+
+    /// Drop lines that aren't valid UTF-8 instead of showing them with
+    /// replacement characters
+    #[clap(long)]
+    strict_utf8: bool,
+
+    /// Encoding of the watched files, e.g. utf-8, utf-16le, latin1. `auto`
+    /// picks it from the file's byte order mark, falling back to UTF-8
+    #[clap(long, default_value = "utf-8")]
+    encoding: file_watch::TextEncoding,
+
+    /// Restart `exec:` commands when they exit
+    #[clap(long)]
+    exec_restart: bool,
+
+    /// Don't load existing content, only show lines written from now on
+    #[clap(short = 'n', long, conflicts_with_all = ["tail_lines", "tail_bytes"])]
+    no_history: bool,
+
+    /// Keep reading a file after it's renamed or deleted (like `tail -f`),
+    /// instead of switching to the new file at its path (like `tail -F`)
+    #[clap(long)]
+    follow_descriptor: bool,
+
+    /// Only load the last N lines of each file's existing content
+    #[clap(long, value_name = "N", conflicts_with = "tail_bytes")]
+    tail_lines: Option<u64>,
+
+    /// Only load (about) the last N bytes of each file's existing content,
+    /// starting from the first full line
+    #[clap(long, value_name = "N")]
+    tail_bytes: Option<u64>,
+
+    /// Carry on with an earlier session: the database at SESSION, or with
+    /// `latest` the last one created in the db directory. Its lines are
+    /// shown and new ones added to it, and each file is read from where that
+    /// session stopped, if it's still the same file, instead of loading its
+    /// existing content again
+    #[clap(long, value_name = "SESSION", num_args = 0..=1, require_equals = true, default_missing_value = "latest")]
+    resume: Option<String>,
+
+    /// How many batches of lines can wait to be stored before watchers stop
+    /// reading until there's room
+    #[clap(long, value_name = "N", default_value_t = file_watch::DEFAULT_CHANNEL_CAPACITY)]
+    channel_capacity: usize,
+
+    /// Keep lines in memory only, leaving nothing behind on disk. The oldest
+    /// lines are dropped beyond `--max-lines`
+    #[clap(long, conflicts_with_all = ["resume", "db_dir"])]
+    no_persist: bool,
+
+    /// How many lines `--no-persist` keeps
+    #[clap(long, value_name = "N", default_value_t = 100_000, requires = "no_persist")]
+    max_lines: u64,
+
+    /// Delete the oldest lines beyond N as new ones are stored, so a session
+    /// that runs for days doesn't grow without limit
+    #[clap(long, value_name = "N")]
+    retain_lines: Option<u64>,
+
+    /// Delete lines stored more than H hours ago, checked every minute
+    #[clap(long, value_name = "H")]
+    retain_hours: Option<u64>,
+
+    /// Where each session's database is created, along with how far it read
+    /// each file, for `--resume`
+    /// (default: $XDG_DATA_HOME/filewatch, or ~/.local/share/filewatch)
+    #[clap(long, value_name = "DIR", global = true)]
+    db_dir: Option<PathBuf>,
+
+    /// Files without a new line for this long are marked stale in the stats
+    /// popup (`s`) and the status bar
+    #[clap(long, value_name = "SECS", default_value_t = 60)]
+    stale_secs: u64,
     
     /// Enable debug logging to a file (default: filewatch.log)
     #[clap(short = 'o', long)]
     debug_output: Option<PathBuf>,
 }
 
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Write a session's rows to a file, in the order they're shown
+    Export {
+        /// `latest`, a session's name from `sessions`, or the path of its
+        /// database
+        session: String,
+
+        #[clap(long, default_value = "text", value_parser = store::ExportFormat::from_str)]
+        format: store::ExportFormat,
+
+        /// Where to write them, stdout if not given
+        #[clap(short = 'o', long, value_name = "PATH", default_value = "-")]
+        output: PathBuf,
+
+        /// Leave out events, like files being rotated, and only write lines
+        #[clap(long)]
+        no_events: bool,
+    },
+    /// List the sessions in the db directory, or prune or show them
+    Sessions {
+        #[clap(subcommand)]
+        command: Option<SessionsCommand>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SessionsCommand {
+    /// Delete old sessions, all but the last N and/or those not written to
+    /// for a while
+    #[clap(group = clap::ArgGroup::new("which").required(true).multiple(true))]
+    Prune {
+        /// Keep the N most recent sessions
+        #[clap(long, value_name = "N", group = "which")]
+        keep_last: Option<usize>,
+
+        /// Only delete sessions not written to for this long, e.g. 12h, 7d
+        #[clap(long, value_name = "AGE", group = "which", value_parser = sessions::parse_age)]
+        older_than: Option<Duration>,
+    },
+    /// Show a session in the pager, without watching anything or adding to
+    /// it
+    Show {
+        /// `latest`, a session's name from the list, or the path of its
+        /// database
+        session: String,
+    },
+}
+
 use std::time::{Duration, Instant, SystemTime};
 
-use crossterm::event::{self, KeyCode};
+/// How long a write waits for the database to be free before failing, and
+/// being tried again
+const DB_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+use crossterm::event::{self, KeyCode, KeyModifiers};
 
-fn main() -> () {
+fn main() {
     // Parse command line arguments
     let args = Args::parse();
     
@@ -34,7 +383,6 @@ fn main() -> () {
         // Open existing file in append mode or create if it doesn't exist
         let log_file = fs::OpenOptions::new()
             .create(true)
-            .write(true)
             .append(true)
             .open(log_path)
             .expect("Failed to open log file");
@@ -54,124 +402,1278 @@ fn main() -> () {
         ]).unwrap();
     }
     
-    // Use the files from parsed arguments
-    let file_paths = args.files;
-    let file_tags = get_file_tags(&file_paths);
+    let db_dir = args.db_dir.clone().unwrap_or_else(default_db_dir);
+    // a session to show rather than files to watch, see `SessionsCommand::Show`
+    let mut shown_session = None;
+    if let Some(Command::Export { session, format, output, no_events }) = &args.command {
+        match export(&db_dir, session, output, *format, !no_events) {
+            Ok(rows) => eprintln!("filewatch: {} rows written", rows),
+            Err(msg) => {
+                eprintln!("filewatch: {}", msg);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if let Some(Command::Sessions { command }) = &args.command {
+        let result = match command {
+            None => sessions::list(&db_dir),
+            Some(SessionsCommand::Prune { keep_last, older_than }) => sessions::prune(&db_dir, *keep_last, *older_than),
+            Some(SessionsCommand::Show { session }) => {
+                shown_session = Some(session.clone());
+                Ok(())
+            }
+        };
+        if let Err(msg) = result {
+            eprintln!("filewatch: {}", msg);
+            std::process::exit(1);
+        }
+        if shown_session.is_none() {
+            return;
+        }
+    }
+
+    // Expand any glob patterns in the file arguments
+    let file_paths = match expand_file_patterns(&args.files, args.wait) {
+        Ok(paths) => paths,
+        Err(msg) => {
+            eprintln!("filewatch: {}", msg);
+            std::process::exit(1);
+        }
+    };
+    let file_ids: Vec<String> = file_paths.iter()
+        .map(|(path, spec)| match &spec.label {
+            Some(label) => label.clone(),
+            None if path == "-" => file_watch::STDIN_ID.to_owned(),
+            None => path.clone(),
+        })
+        .collect();
+    let mut file_prefixes = ui::FilePrefixes::new(get_file_tags(&file_ids));
     info!("Watching files: {:?}", file_paths);
+    let config_path = args.config.clone().or_else(config::default_path);
+    let config = match config_path.as_deref().map(|path| config::read(path, args.config.is_some())) {
+        Some(Ok(config)) => config,
+        Some(Err(msg)) => {
+            eprintln!("filewatch: {}", msg);
+            std::process::exit(1);
+        }
+        None => config::Config::default(),
+    };
     
-    // let watchers = vec![];
-    let (tx, rx) = sync::mpsc::channel();
+    // before any watchers are started, so there's nothing to clean up if it fails
+    let db = if let Some(session) = &shown_session {
+        open_session_read_only(&db_dir, session).map(|(path, conn)| (Some(path), conn))
+    } else if args.no_persist {
+        open_memory_db().map(|conn| (None, conn))
+    } else if let Some(session) = &args.resume {
+        open_session(&db_dir, session).map(|(path, conn)| (Some(path), conn))
+    } else {
+        open_db(&db_dir).map(|(path, conn)| (Some(path), conn))
+    };
+    let order_by_line_ts = args.timestamp_format.is_some();
+    let db = db.and_then(|(path, conn)| {
+        if shown_session.is_none() {
+            migrate(&conn).map_err(|e| match &path {
+                Some(path) => format!("{}: {}", path.display(), e),
+                None => e,
+            })?;
+            let files: Vec<sessions::SessionFile> = file_paths.iter()
+                .map(|(path, spec)| sessions::SessionFile { path, label: spec.label.as_deref() })
+                .collect();
+            sessions::record(&conn, unix_millis(), &files)
+                .map_err(|e| format!("cannot record session: {}", e))?;
+        }
+        LogStore::new(conn, order_by_line_ts)
+            .map(|store| (path, store))
+            .map_err(|e| format!("cannot set up database: {}", e))
+    });
+    let (db_path, store) = match db {
+        Ok(db) => db,
+        Err(msg) => {
+            eprintln!("filewatch: {}", msg);
+            std::process::exit(1);
+        }
+    };
+
+    let (tx, rx) = sync::mpsc::sync_channel(args.channel_capacity);
+    let shutdown = file_watch::Shutdown::default();
+    let mut watch_handles = vec![];
+    // files and directories all share one thread, see `watch_files`
+    let mut file_sources = vec![];
 
-    for path in file_paths {
+    for (path, spec) in file_paths {
         let tx_clone = tx.clone();        
-        std::thread::spawn(move || {
-            if let Err(e) = file_watch::watch_file(&path, tx_clone) {
+        let options = file_watch::WatchOptions {
+            backend: get_backend(&path, args.poll, &args.poll_paths),
+            recursive: args.recursive,
+            wait: args.wait,
+            partial_flush: Duration::from_millis(args.partial_flush_ms),
+            debounce: Duration::from_millis(args.debounce_ms),
+            strict_utf8: args.strict_utf8,
+            encoding: spec.encoding.unwrap_or(args.encoding),
+            exec_restart: args.exec_restart,
+            tail: get_tail(&args),
+            label: spec.label.clone(),
+            json: (args.json || spec.json).then(|| args.json_format.clone().unwrap_or_default()),
+            timestamp: args.timestamp_format.clone().map(|format| format.with_regex(args.timestamp_regex.clone())),
+            level_regex: spec.level_regex.clone().or_else(|| args.level_regex.clone()),
+            filters: args.filters.iter().chain(&spec.filters).cloned().collect(),
+            excludes: args.excludes.iter().chain(&spec.excludes).cloned().collect(),
+            skip_blank: args.skip_blank,
+            carriage_returns: args.carriage_returns,
+            max_line_bytes: args.max_line_bytes,
+            force_binary: args.force_binary,
+            keep_ansi: args.color != ColorMode::Strip,
+            multiline_start: args.multiline_start.clone(),
+            follow_descriptor: args.follow_descriptor,
+            // kept for every session, so that any of them can be resumed
+            resume: db_path.as_ref().map(|path| path.with_extension("offsets.json")),
+            max_lines_per_sec: args.max_lines_per_sec,
+        };
+        if path == "-" {
+            // blocks on stdin until it's closed, so this thread isn't joined
+            std::thread::spawn(move || {
+                if let Err(e) = file_watch::watch_stdin(&options, tx_clone) {
+                    error!("Error reading stdin: {}", e);
+                }
+            });
+            continue;
+        }
+        let is_stream = [file_watch::TCP_PREFIX, file_watch::EXEC_PREFIX, file_watch::SYSLOG_PREFIX]
+            .iter()
+            .any(|prefix| path.starts_with(prefix));
+        if !is_stream && !file_watch::is_fifo(&path) && !file_watch::is_gzip(&path) {
+            file_sources.push((path, options));
+            continue;
+        }
+        let shutdown = shutdown.clone();
+        let handle = std::thread::spawn(move || {
+            let result = if let Some(addr) = path.strip_prefix(file_watch::TCP_PREFIX) {
+                file_watch::watch_tcp(addr, &options, shutdown, tx_clone)
+            } else if let Some(command) = path.strip_prefix(file_watch::EXEC_PREFIX) {
+                file_watch::watch_exec(command, &options, shutdown, tx_clone)
+            } else if let Some(addr) = path.strip_prefix(file_watch::SYSLOG_PREFIX) {
+                file_watch::watch_syslog(addr, &options, shutdown, tx_clone)
+            } else if file_watch::is_gzip(&path) {
+                file_watch::read_gzip(&path, &options, shutdown, tx_clone)
+            } else {
+                file_watch::watch_fifo(&path, &options, shutdown, tx_clone)
+            };
+            if let Err(e) = result {
                 error!("Error tailing file {}: {}", &path, e);
             }
         });
+        watch_handles.push(handle);
+    }
+    if !file_sources.is_empty() {
+        let shutdown = shutdown.clone();
+        let tx = tx.clone();
+        watch_handles.push(std::thread::spawn(move || {
+            if let Err(e) = file_watch::watch_files(&file_sources, shutdown, tx) {
+                error!("Error tailing files: {}", e);
+            }
+        }));
     }
 
-    let ts = SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .expect("Time went backwards")
-        .as_millis();
-    
-    let db_path = format!("./db/{}.db3", ts);
-    debug!("Creating database at {}", db_path);
-    
-    let conn = rusqlite::Connection::open(&db_path)
-        .expect("failed to open db");
-    
-    debug!("Database opened successfully");
-
-    conn.execute(
-        "CREATE TABLE log ( id INTEGER PRIMARY KEY, file_id TEXT NOT NULL, message TEXT NOT NULL )",
-        (),
-    )
-        .unwrap();
+    // so the store can tell when the watchers are all done
+    drop(tx);
 
-    let mut query = conn.prepare("select file_id, message from log")
-        .unwrap();
+    // SIGINT/SIGTERM from outside the terminal get the same cleanup as `q`
+    let quit_signal = sync::Arc::new(AtomicBool::new(false));
+    for signal in [SIGINT, SIGTERM] {
+        if let Err(e) = signal_hook::flag::register(signal, quit_signal.clone()) {
+            error!("Failed to register handler for signal {}: {}", signal, e);
+        }
+    }
 
-    let mut insert = conn.prepare("INSERT INTO log (file_id, message) VALUES (?, ?)")
-        .unwrap();
+    let (events_tx, events) = sync::mpsc::channel();
+    let store_options = store::StoreOptions {
+        collapse_repeats: args.collapse_repeats,
+        repeat_gap: Duration::from_secs(args.repeat_gap_secs),
+        // nothing is deleted from a session that's only shown
+        max_rows: args.no_persist.then_some(args.max_lines).into_iter().chain(args.retain_lines).min().filter(|_| shown_session.is_none()),
+        max_age: args.retain_hours.map(|hours| Duration::from_secs(hours * 60 * 60)).filter(|_| shown_session.is_none()),
+        order_by_line_ts,
+        alerts: args.alerts.clone(),
+        graphs: args.graphs.clone(),
+    };
+    // as it was left in a resumed session
+    let word_wrap = store.setting(WORD_WRAP_SETTING)
+        .inspect_err(|err| error!("Failed to read settings ({:?}): {:?}", err.sqlite_error_code(), err.sqlite_error()))
+        .ok()
+        .flatten()
+        .is_some_and(|value| value == "true");
+    let (requests, store_handle) = store::spawn(store, rx, store_options, events_tx.clone());
 
     let mut terminal = ratatui::init();
+    if !args.no_mouse {
+        if let Err(e) = input::enable_mouse() {
+            warn!("Failed to turn on mouse capture: {}", e);
+        }
+    }
+    // key presses and the like, which are waited on along with the store
+    std::thread::spawn(move || {
+        while let Ok(event) = event::read() {
+            if events_tx.send(UiEvent::Input(event)).is_err() {
+                break;
+            }
+        }
+    });
     let mut app = ui::App::default();
+    // what the lines dropped are dropped for, see `App::top_note`
+    let retention = [
+        args.no_persist.then(|| format!("--max-lines {}", args.max_lines)),
+        args.retain_lines.map(|lines| format!("--retain-lines {}", lines)),
+        args.retain_hours.map(|hours| format!("--retain-hours {}", hours)),
+    ];
+    let retention = retention.into_iter().flatten().collect::<Vec<_>>().join(", ");
+    app.stale_after = Duration::from_secs(args.stale_secs);
+    app.db = db_path.clone();
+    app.collapse_repeats = args.collapse_repeats;
+    app.show_events = true;
+    app.color_files = args.color_files == ColorFiles::Always;
+    apply_config(&mut app, config, &args);
+    app.level_colors = true;
+    app.line_numbers = args.line_numbers;
+    app.show_gaps = true;
+    app.gap_after = args.gap_threshold;
+    app.graphs = ui::Graphs::new(args.graphs.clone());
+    app.wrap = ui::Wrap { words: word_wrap, indicator: args.wrap_indicator.clone(), indent: args.wrap_indent, tab_width: args.tab_width.into() };
+    if args.follow {
+        app.toggle_follow_mode();
+    }
+    let mut timestamps = Timestamps::default();
+    // when relative timestamps were last queried again, see `RELATIVE_REFRESH`
+    let mut timestamps_refreshed = Instant::now();
+    let mut keys = input::Keys::default();
+    let mut clipboard = clipboard::Clipboard::new(args.clipboard);
+    let alert_exec = args.alert_exec.clone().map(alert::Exec::spawn);
+    // nothing happening still redraws now and then, e.g. for stale files
     let tick_rate = Duration::from_millis(250);
-    let mut last_tick = Instant::now();
-    loop {
+    // rows were added, or rows before the screen may have come or gone,
+    // since each pane's window was last asked for, see `App::wanted_window`
+    let mut added = [false; store::PANES];
+    let mut moved = [false; store::PANES];
+    // the same, while paused, which waits until it's unpaused
+    let mut paused_added = false;
+    let mut paused_moved = false;
+    // a window was asked for each pane and hasn't come back yet
+    let mut window_requested = [false; store::PANES];
+    // the window asked for is of the tab the pane was on before, so it's
+    // asked for again rather than shown
+    let mut window_stale = [false; store::PANES];
+    'ui: while !quit_signal.load(Ordering::Relaxed) {
         terminal.draw(|frame| app.render(frame)).expect("draw should work");
-        let page_size = app.logs_widget_state.height;
-        let elapsed_time = last_tick.elapsed();
-        let timeout = tick_rate.saturating_sub(elapsed_time);
-        if event::poll(timeout).expect("bad poll") {
-            log::debug!("event recived");
-            if let Some(key) = event::read().unwrap().as_key_press_event() {
+        let page_size = app.page_size();
+        let first = events.recv_timeout(tick_rate).ok();
+        // and whatever else came in the meantime, before drawing again
+        for event in first.into_iter().chain(events.try_iter()) {
+            let key = match event {
+                UiEvent::Input(event::Event::Mouse(mouse)) => {
+                    match input::mouse_action(mouse) {
+                        Some(input::Action::ScrollDown(lines)) => app.scroll_down(lines),
+                        Some(input::Action::ScrollUp(lines)) => app.scroll_up(lines),
+                        Some(input::Action::Click(column, row)) => app.click(column, row),
+                        Some(input::Action::Drag(row)) => app.drag(row),
+                        _ => {}
+                    }
+                    continue;
+                }
+                UiEvent::Input(event) => match event.as_key_press_event() {
+                    Some(key) => key,
+                    None => continue,
+                },
+                UiEvent::Store(store::Update::Stored { added: rows_added, moved: rows_moved, repeats, unparsed_timestamps, dropped }) => {
+                    app.activity.add(rows_added);
+                    if app.is_paused() {
+                        app.add_paused(rows_added);
+                        paused_added |= rows_added > 0;
+                        paused_moved |= rows_moved;
+                    } else {
+                        for pane in 0..store::PANES {
+                            added[pane] |= rows_added > 0;
+                            moved[pane] |= rows_moved;
+                        }
+                    }
+                    for (id, repeats) in repeats {
+                        app.set_repeats(id, repeats);
+                    }
+                    app.unparsed_timestamps = unparsed_timestamps;
+                    if dropped > 0 {
+                        let text = format!("filewatch: {} older lines dropped ({})", dropped, retention);
+                        app.set_top_note(ui::LogLine { text, is_event: true, ..Default::default() });
+                    }
+                    continue;
+                }
+                UiEvent::Store(store::Update::Stats { file_id, stats }) => {
+                    app.stats.update(&file_id, stats);
+                    continue;
+                }
+                UiEvent::Store(store::Update::Loading { file_id, done, total }) => {
+                    app.set_loading(&file_id, done, total);
+                    continue;
+                }
+                UiEvent::Store(store::Update::Window { pane, start, rows, total }) => {
+                    window_requested[pane] = false;
+                    if std::mem::take(&mut window_stale[pane]) {
+                        continue;
+                    }
+                    let logs = rows.into_iter()
+                        .map(|row| {
+                            let prefix = file_prefixes.get(&row.file_id);
+                            log_line(row, prefix, args.color, timestamps, order_by_line_ts)
+                        })
+                        .collect();
+                    app.set_window(pane, start, logs, total);
+                    continue;
+                }
+                UiEvent::Store(store::Update::Indexing { done, total }) => {
+                    app.set_indexing(done, total);
+                    continue;
+                }
+                UiEvent::Store(store::Update::Matches { query, mode, matches }) => {
+                    app.set_matches(query, mode, matches);
+                    continue;
+                }
+                UiEvent::Store(store::Update::Values(values)) => {
+                    for (graph, ts, value) in values {
+                        app.graphs.add(graph, ts, value);
+                    }
+                    continue;
+                }
+                UiEvent::Store(store::Update::Alerts(rows)) => {
+                    if let Err(msg) = alert::bell() {
+                        warn!("Failed to ring the bell: {}", msg);
+                    }
+                    for row in rows {
+                        let prefix = file_prefixes.get(&row.file_id);
+                        let log = log_line(row, prefix, args.color, Timestamps::Hidden, order_by_line_ts);
+                        if alert_exec.as_ref().is_some_and(|exec| !exec.send(log.line())) {
+                            warn!("Too many alerts waiting for --alert-exec, not running it for: {}", log.line());
+                        }
+                        app.alerts.add(&log);
+                    }
+                    continue;
+                }
+                UiEvent::Store(store::Update::Frequencies(counted)) => {
+                    app.set_frequencies(counted);
+                    continue;
+                }
+                UiEvent::Store(store::Update::Exported { path, rows }) => {
+                    app.set_message(match rows {
+                        Ok(rows) => format!("{} rows written to {}", rows, path.display()),
+                        Err(msg) => msg,
+                    });
+                    continue;
+                }
+            };
+            // raw mode swallows SIGINT, so Ctrl-C arrives as a key press
+            if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                break 'ui;
+            }
+            app.clear_message();
+            if app.show_help {
+                app.show_help = false;
+                continue;
+            }
+            if app.show_marks {
+                app.show_marks = false;
+                continue;
+            }
+            if let Some(details) = &mut app.details {
                 match key.code {
-                    KeyCode::Char('q') => break,
-                    KeyCode::Char('g') => app.set_scroll(usize::MAX),
-                    KeyCode::Char('j') | KeyCode::Down => app.scroll_down(1),
-                    KeyCode::Char('k') | KeyCode::Up => app.scroll_up(1),
-                    KeyCode::PageUp => app.scroll_up(page_size.into()),
-                    KeyCode::PageDown => app.scroll_down(page_size.into()),
+                    KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => app.details = None,
+                    KeyCode::Char('y') => {
+                        let copied = clipboard.copy(details.line());
+                        app.set_message(copied_message(copied, 1));
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => details.scroll(1, 0),
+                    KeyCode::Up | KeyCode::Char('k') => details.scroll(-1, 0),
+                    KeyCode::Right | KeyCode::Char('l') => details.scroll(0, DETAILS_SIDEWAYS),
+                    KeyCode::Left | KeyCode::Char('h') => details.scroll(0, -DETAILS_SIDEWAYS),
                     _ => {}
                 }
+                continue;
             }
-        }
-
-        //hmm
-        let iter = rx.try_iter();
-        for msg in iter {
-            // Insert new rows
-            for line in msg.lines.into_iter() {
-                let insert_result = insert.execute((&msg.file_id, line));
-                if let Err(err) = insert_result {
-                   log::error!("Failed to insert to database ({:?}): {:?}", err.sqlite_error_code(), err.sqlite_error());
+            if app.alerts.is_open() {
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('!') => app.alerts.close(),
+                    KeyCode::Down | KeyCode::Char('j') => app.alerts.move_cursor(1),
+                    KeyCode::Up | KeyCode::Char('k') => app.alerts.move_cursor(-1),
+                    KeyCode::Enter => app.jump_to_alert(),
+                    _ => {}
+                }
+                continue;
+            }
+            let pane = app.focused_pane();
+            if let Some(frequencies) = &mut app.frequencies {
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') => app.frequencies = None,
+                    KeyCode::Down | KeyCode::Char('j') => frequencies.move_cursor(1),
+                    KeyCode::Up | KeyCode::Char('k') => frequencies.move_cursor(-1),
+                    KeyCode::Char('s') => frequencies.toggle_sort(),
+                    KeyCode::Char('n') => {
+                        frequencies.toggle_normalize();
+                        count_lines(frequencies, pane, &requests);
+                    }
+                    KeyCode::Char('t') => {
+                        frequencies.cycle_since();
+                        count_lines(frequencies, pane, &requests);
+                    }
+                    KeyCode::Enter | KeyCode::Char('x') => {
+                        let template = frequencies.selected().cloned().filter(|_| key.code == KeyCode::Enter);
+                        app.frequencies = None;
+                        app.set_template(template);
+                        filter_template(&app, &requests);
+                        moved[pane] = true;
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            if let Some(prompt) = &mut app.prompt {
+                match key.code {
+                    KeyCode::Esc => app.prompt = None,
+                    KeyCode::Enter => {
+                        let ui::Prompt { kind, input } = app.prompt.take().expect("prompt is open");
+                        if input.trim().is_empty() {
+                            continue;
+                        }
+                        match kind {
+                            ui::PromptKind::Search(mode) => {
+                                if app.search_context(&input, mode) {
+                                    filter_context(&app, &requests);
+                                    moved[app.focused_pane()] = true;
+                                }
+                                request(&requests, store::Request::Search { pane: app.focused_pane(), query: input, mode });
+                            }
+                            ui::PromptKind::Export => {
+                                let path = PathBuf::from(input.trim());
+                                let format = store::ExportFormat::for_path(&path);
+                                app.set_message(format!("writing {}", path.display()));
+                                request(&requests, store::Request::Export { pane: app.focused_pane(), path, format });
+                            }
+                            ui::PromptKind::Line => app.go_to_line(&input),
+                            ui::PromptKind::Graph => match ui::parse_graph(input.trim()) {
+                                Ok(graph) => {
+                                    let shown = app.graphs.toggle(graph);
+                                    request(&requests, store::Request::Graphs(app.graphs.regexes()));
+                                    app.set_message(format!("{} {}", if shown { "charting" } else { "not charting" }, input.trim()));
+                                }
+                                Err(msg) => app.set_message(msg.split_whitespace().collect::<Vec<_>>().join(" ")),
+                            },
+                        }
+                    }
+                    KeyCode::Backspace => {
+                        prompt.input.pop();
+                    }
+                    KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => prompt.switch_mode(),
+                    KeyCode::Char(c) => prompt.input.push(c),
+                    _ => {}
+                }
+                continue;
+            }
+            // the pinned rows' keys, while they have them, the rest do what
+            // they do without them
+            if app.pins_focused() {
+                let handled = match key.code {
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        app.move_pins_cursor(1);
+                        true
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        app.move_pins_cursor(-1);
+                        true
+                    }
+                    KeyCode::Enter => {
+                        app.jump_to_pin();
+                        true
+                    }
+                    KeyCode::Char('P') => {
+                        app.unpin_selected();
+                        true
+                    }
+                    KeyCode::Esc => {
+                        app.toggle_pins_focus();
+                        true
+                    }
+                    _ => false,
+                };
+                if handled {
+                    continue;
+                }
+            }
+            // the sidebar's keys, the rest do what they do without it
+            if app.sidebar_open() {
+                let handled = match key.code {
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        app.move_sidebar_cursor(1);
+                        true
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        app.move_sidebar_cursor(-1);
+                        true
+                    }
+                    KeyCode::Char(' ') => {
+                        app.toggle_sidebar_file();
+                        filter_files(&app, &requests);
+                        moved[app.focused_pane()] = true;
+                        true
+                    }
+                    KeyCode::Esc => {
+                        app.toggle_sidebar();
+                        true
+                    }
+                    _ => false,
+                };
+                if handled {
+                    continue;
                 }
             }
+            let Some(action) = keys.action(key) else {
+                continue;
+            };
+            match action {
+                input::Action::Quit => break 'ui,
+                input::Action::Top => app.go_to_top(),
+                input::Action::Bottom => app.go_to_bottom(),
+                input::Action::ToggleFollow => app.toggle_follow_mode(),
+                input::Action::TogglePause => {
+                    app.toggle_pause();
+                    if !app.is_paused() {
+                        let (paused_added, paused_moved) = (std::mem::take(&mut paused_added), std::mem::take(&mut paused_moved));
+                        for pane in 0..store::PANES {
+                            added[pane] |= paused_added;
+                            moved[pane] |= paused_moved;
+                        }
+                    }
+                }
+                input::Action::CycleTimestamps => {
+                    timestamps = timestamps.next();
+                    app.set_message(format!("timestamps: {}", timestamps));
+                    // every line's text changes, so the windows are queried again
+                    moved = [true; store::PANES];
+                }
+                input::Action::CyclePrefixes => {
+                    file_prefixes.cycle();
+                    app.set_message(format!("file prefixes: {}", file_prefixes.mode()));
+                    // as with timestamps, every line's text changes
+                    moved = [true; store::PANES];
+                }
+                input::Action::ToggleLineNumbers => app.line_numbers = !app.line_numbers,
+                input::Action::ToggleGaps => app.toggle_gaps(),
+                input::Action::ToggleStats => app.toggle_stats(),
+                input::Action::ToggleWrap => app.toggle_wrap(),
+                input::Action::ToggleWordWrap => {
+                    app.toggle_word_wrap();
+                    if shown_session.is_none() {
+                        request(&requests, store::Request::Setting { name: WORD_WRAP_SETTING, value: app.wrap.words.to_string() });
+                    }
+                }
+                input::Action::ToggleLevelColors => app.level_colors = !app.level_colors,
+                input::Action::ReloadConfig => match config_path.as_deref().map(|path| (path, config::read(path, args.config.is_some()))) {
+                    Some((path, Ok(config))) => {
+                        apply_config(&mut app, config, &args);
+                        app.set_message(format!("read {}", path.display()));
+                    }
+                    // regex errors take a few lines to point at what's wrong
+                    Some((_, Err(msg))) => app.set_message(msg.split_whitespace().collect::<Vec<_>>().join(" ")),
+                    None => app.set_message(String::from("no config file, there's no home directory")),
+                },
+                input::Action::ScrollSideways(right) => app.scroll_sideways(right),
+                input::Action::ScrollToSide(end) => app.scroll_to_side(end),
+                input::Action::Search => app.prompt = Some(ui::Prompt::new(ui::PromptKind::Search(store::SearchMode::default()))),
+                input::Action::Export => app.prompt = Some(ui::Prompt::new(ui::PromptKind::Export)),
+                input::Action::GoToLine => app.prompt = Some(ui::Prompt::new(ui::PromptKind::Line)),
+                input::Action::Graph => app.prompt = Some(ui::Prompt::new(ui::PromptKind::Graph)),
+                input::Action::JumpToMatch(step) => app.jump_to_match(step),
+                input::Action::ToggleContext => {
+                    if app.toggle_context() {
+                        filter_context(&app, &requests);
+                        moved[app.focused_pane()] = true;
+                    }
+                }
+                input::Action::ChangeContext(step) => {
+                    if app.change_context(step) {
+                        filter_context(&app, &requests);
+                        moved[app.focused_pane()] = true;
+                    }
+                }
+                input::Action::CycleMinLevel => {
+                    app.cycle_min_level();
+                    request(&requests, store::Request::MinLevel { pane: app.focused_pane(), min_level: app.min_level() });
+                    // hidden rows aren't matches anymore, or shown ones are now
+                    if let Some((query, mode)) = app.last_search() {
+                        request(&requests, store::Request::Search { pane: app.focused_pane(), query: query.to_owned(), mode });
+                    }
+                    moved[app.focused_pane()] = true;
+                }
+                input::Action::ToggleSidebar => app.toggle_sidebar(),
+                input::Action::Frequencies => {
+                    app.open_frequencies();
+                    if let Some(frequencies) = &app.frequencies {
+                        count_lines(frequencies, app.focused_pane(), &requests);
+                    }
+                }
+                input::Action::ToggleSplit => {
+                    app.toggle_split();
+                    // the new pane is filtered as the focused one is, before
+                    // it asks for its window
+                    if let Some(&pane) = app.pane_ids().get(1) {
+                        request(&requests, store::Request::MinLevel { pane, min_level: app.min_level() });
+                        request(&requests, store::Request::HiddenFiles { pane, hidden_files: app.hidden_files().clone() });
+                        request(&requests, store::Request::Template { pane, template: app.template().cloned() });
+                        request(&requests, store::Request::File { pane, file: app.tab_file().map(str::to_owned) });
+                        // it shows all rows around matches or not, whatever it did before
+                        request(&requests, store::Request::Context { pane, context: None });
+                    }
+                }
+                input::Action::SwitchPane => app.switch_pane(),
+                input::Action::SwitchTab(index) => {
+                    if app.switch_tab(index) {
+                        filter_tab(&app, &requests);
+                        let pane = app.focused_pane();
+                        window_stale[pane] = window_requested[pane];
+                        moved[pane] = true;
+                    }
+                }
+                input::Action::CycleTab(step) => {
+                    if app.cycle_tab(step) {
+                        filter_tab(&app, &requests);
+                        let pane = app.focused_pane();
+                        window_stale[pane] = window_requested[pane];
+                        moved[pane] = true;
+                    }
+                }
+                input::Action::ToggleFile(index) => {
+                    app.toggle_file(index);
+                    filter_files(&app, &requests);
+                    moved[app.focused_pane()] = true;
+                }
+                input::Action::ToggleEvents => {
+                    app.show_events = !app.show_events;
+                    request(&requests, store::Request::ShowEvents(app.show_events));
+                    moved = [true; store::PANES];
+                }
+                input::Action::ToggleRepeats => {
+                    app.collapse_repeats = !app.collapse_repeats;
+                    request(&requests, store::Request::CollapseRepeats(app.collapse_repeats));
+                }
+                input::Action::MoveCursor(step) => app.move_cursor(step),
+                input::Action::ScrollDown(lines) => app.scroll_down(lines),
+                input::Action::ScrollUp(lines) => app.scroll_up(lines),
+                input::Action::PageDown => app.scroll_down(page_size.into()),
+                input::Action::PageUp => app.scroll_up(page_size.into()),
+                input::Action::Click(column, row) => app.click(column, row),
+                input::Action::Drag(row) => app.drag(row),
+                input::Action::Details => app.open_details(),
+                input::Action::CopyLine => {
+                    let message = match app.cursor_line() {
+                        Some(line) => copied_message(clipboard.copy(line), 1),
+                        None => String::from("no line under the cursor, j and k move it"),
+                    };
+                    app.set_message(message);
+                }
+                input::Action::CopyScreen => {
+                    let lines = app.screen_lines();
+                    let message = copied_message(clipboard.copy(&lines.join("\n")), lines.len());
+                    app.set_message(message);
+                }
+                input::Action::SetMark(letter) => app.set_mark(letter),
+                input::Action::JumpToMark(letter) => app.jump_to_mark(letter),
+                input::Action::JumpBack => app.jump_back(),
+                input::Action::ListMarks => app.show_marks = true,
+                input::Action::ListAlerts => app.alerts.open(),
+                input::Action::TogglePin => app.toggle_pin(),
+                input::Action::FocusPins => app.toggle_pins_focus(),
+                input::Action::Help => app.show_help = true,
+            }
         }
 
-        // Query all logs from database
-        let logs = query
-            .query_map([], |row| {
-                let file_id: String = row.get("file_id").unwrap();
-                let message: String = row.get("message").unwrap();
-                let default_log_prefix = &String::from(" >");
-                let log_prefix = file_tags.get(&file_id).unwrap_or(default_log_prefix);
-                let line = format!("{} {}", log_prefix, message);
-                Ok(line)
-            })
-            .unwrap();
-        
-        // Collect all log lines into a single string
-        let mut log_content = vec![];
-        for log_result in logs {
-            if let Ok(line) = log_result {
-                log_content.push(line)
+        // "5s" is "6s" a second later, with or without new rows. Not while
+        // paused, as the rows would come with them.
+        if timestamps == Timestamps::Relative && !app.is_paused() && timestamps_refreshed.elapsed() >= RELATIVE_REFRESH {
+            moved = [true; store::PANES];
+            timestamps_refreshed = Instant::now();
+        }
+
+        // Ask for the rows around each pane, if they're not the ones there
+        // already. One request at a time for each, asking again for what
+        // changed since.
+        for pane in app.pane_ids() {
+            if window_requested[pane] {
+                continue;
             }
-            else {
-                log::error!("bad log")
+            if let Some(window) = app.wanted_window(pane, added[pane], moved[pane]) {
+                request(&requests, store::Request::Window { pane, window });
+                window_requested[pane] = true;
+                added[pane] = false;
+                moved[pane] = false;
             }
         }
+    }
 
-        app.set_log_lines(log_content);
-        last_tick = Instant::now();
-
+    info!("Shutting down");
+    shutdown.trigger();
+    // the store stops taking lines and closes, after which watchers blocked
+    // on a full channel get an error rather than waiting forever
+    drop(requests);
+    if store_handle.join().is_err() {
+        error!("Store thread panicked");
+    }
+    for handle in watch_handles {
+        if handle.join().is_err() {
+            error!("Watcher thread panicked");
+        }
+    }
+    if !args.no_mouse {
+        let _ = input::disable_mouse();
     }
     ratatui::restore();
+    if let Some(db_path) = db_path.filter(|_| shown_session.is_none()) {
+        eprintln!("filewatch: session stored in {}", db_path.display());
+    }
+}
+
+/// Show lines with the highlights and level styles of `config`, and those
+/// of `args` over them
+fn apply_config(app: &mut ui::App, config: config::Config, args: &Args) {
+    app.highlights = config.highlights.into_iter().chain(args.highlights.iter().cloned()).collect();
+    app.level_styles = ui::LevelStyles::default();
+    for style in config.level_styles.into_iter().chain(args.level_styles.iter().cloned()) {
+        app.level_styles.set(style);
+    }
+}
+
+/// What the UI loop waits for
+enum UiEvent {
+    Input(event::Event),
+    Store(store::Update),
+}
+
+impl From<store::Update> for UiEvent {
+    fn from(update: store::Update) -> Self {
+        UiEvent::Store(update)
+    }
+}
+
+/// Have the store only show the rows of the template `app` shows, if any,
+/// and search again, as with `filter_files`
+fn filter_template(app: &ui::App, requests: &sync::mpsc::Sender<store::Request>) {
+    let pane = app.focused_pane();
+    request(requests, store::Request::Template { pane, template: app.template().cloned() });
+    if let Some((query, mode)) = app.last_search() {
+        request(requests, store::Request::Search { pane, query: query.to_owned(), mode });
+    }
+}
+
+/// Have the store filter the focused pane as it is on the tab it's on now,
+/// each tab's being kept by `app`, and search again, as with `filter_files`
+fn filter_tab(app: &ui::App, requests: &sync::mpsc::Sender<store::Request>) {
+    let pane = app.focused_pane();
+    request(requests, store::Request::File { pane, file: app.tab_file().map(str::to_owned) });
+    request(requests, store::Request::MinLevel { pane, min_level: app.min_level() });
+    request(requests, store::Request::HiddenFiles { pane, hidden_files: app.hidden_files().clone() });
+    request(requests, store::Request::Template { pane, template: app.template().cloned() });
+    request(requests, store::Request::Context { pane, context: app.context() });
+    if let Some((query, mode)) = app.last_search() {
+        request(requests, store::Request::Search { pane, query: query.to_owned(), mode });
+    }
+}
+
+/// Have the store only show the rows around the matches `app` shows them
+/// around in the focused pane, if any, see `ui::App::toggle_context`
+fn filter_context(app: &ui::App, requests: &sync::mpsc::Sender<store::Request>) {
+    request(requests, store::Request::Context { pane: app.focused_pane(), context: app.context() });
+}
+
+/// Have the store count the lines `pane` shows for `frequencies`
+fn count_lines(frequencies: &ui::Frequencies, pane: usize, requests: &sync::mpsc::Sender<store::Request>) {
+    let since = frequencies.since.start(unix_millis());
+    request(requests, store::Request::Frequencies { pane, since, normalize: frequencies.normalize });
+}
+
+/// Have the store hide the rows of the files `app` hides, and search again,
+/// as hidden rows aren't matches anymore or shown ones are now
+fn filter_files(app: &ui::App, requests: &sync::mpsc::Sender<store::Request>) {
+    let pane = app.focused_pane();
+    request(requests, store::Request::HiddenFiles { pane, hidden_files: app.hidden_files().clone() });
+    if let Some((query, mode)) = app.last_search() {
+        request(requests, store::Request::Search { pane, query: query.to_owned(), mode });
+    }
+}
+
+/// Send `request` to the store, which is only gone if it panicked
+fn request(requests: &sync::mpsc::Sender<store::Request>, request: store::Request) {
+    if requests.send(request).is_err() {
+        error!("Store thread is gone");
+    }
+}
+
+/// `$XDG_DATA_HOME/filewatch`, falling back to `~/.local/share/filewatch`
+/// as the XDG spec says, or `./db` without a home directory
+fn default_db_dir() -> PathBuf {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        // the spec says relative paths are to be ignored
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")));
+    match data_home {
+        Some(data_home) => data_home.join("filewatch"),
+        None => PathBuf::from("db"),
+    }
+}
+
+/// Create a database for this session in `db_dir`, creating the directory
+/// too if needed. The error says what went wrong, for showing as is.
+fn open_db(db_dir: &Path) -> Result<(PathBuf, rusqlite::Connection), String> {
+    // absolute, so messages and the exit summary make sense from anywhere
+    let db_dir = std::path::absolute(db_dir).unwrap_or_else(|_| db_dir.to_owned());
+    fs::create_dir_all(&db_dir)
+        .map_err(|e| format!("cannot create database directory {}: {}", db_dir.display(), e))?;
+    let db_path = db_dir.join(format!("{}.db3", unix_millis()));
+    debug!("Creating database at {}", db_path.display());
+    let conn = rusqlite::Connection::open(&db_path)
+        .and_then(|conn| {
+            // has to be set before anything is written, WAL included, for
+            // `LogStore::vacuum`
+            conn.pragma_update(None, "auto_vacuum", "INCREMENTAL")?;
+            // readers, e.g. sqlite3 on the session while it's running, don't
+            // block writes, and writes wait a while for them rather than fail
+            conn.pragma_update(None, "journal_mode", "WAL")?;
+            conn.busy_timeout(DB_BUSY_TIMEOUT)?;
+            create_tables(&conn).map(|_| conn)
+        })
+        .map_err(|e| format!("cannot create database {}: {}", db_path.display(), e))?;
+    debug!("Database opened successfully");
+    Ok((db_path, conn))
+}
+
+/// The database of an earlier session to carry on with, see `Args::resume`,
+/// and `sessions::find` for what `session` can be
+fn open_session(db_dir: &Path, session: &str) -> Result<(PathBuf, rusqlite::Connection), String> {
+    let db_path = sessions::find(db_dir, session)?;
+    let db_path = std::path::absolute(&db_path).unwrap_or(db_path);
+    debug!("Resuming session {}", db_path.display());
+    // not created if it isn't there, that's a mistyped path
+    let flags = rusqlite::OpenFlags::default() - rusqlite::OpenFlags::SQLITE_OPEN_CREATE;
+    let conn = rusqlite::Connection::open_with_flags(&db_path, flags)
+        .and_then(|conn| {
+            conn.pragma_update(None, "journal_mode", "WAL")?;
+            conn.busy_timeout(DB_BUSY_TIMEOUT)?;
+            Ok(conn)
+        })
+        .map_err(|e| format!("cannot open session {}: {}", db_path.display(), e))?;
+    Ok((db_path, conn))
+}
+
+/// The database of a session to show, see `SessionsCommand::Show`, or
+/// export, opened read-only. One made by another version is refused rather
+/// than migrated.
+fn open_session_read_only(db_dir: &Path, session: &str) -> Result<(PathBuf, rusqlite::Connection), String> {
+    let db_path = sessions::find(db_dir, session)?;
+    let db_path = std::path::absolute(&db_path).unwrap_or(db_path);
+    debug!("Reading session {}", db_path.display());
+    // without setting WAL, which writes to it
+    let conn = store::open_read_only(&db_path)
+        .and_then(|conn| conn.busy_timeout(DB_BUSY_TIMEOUT).map(|_| conn))
+        .map_err(|e| format!("cannot open session {}: {}", db_path.display(), e))?;
+    let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(|e| format!("{}: cannot read schema version: {}", db_path.display(), e))?;
+    if version > SCHEMA_VERSION {
+        return Err(format!("{}: database is from a newer version of filewatch (schema {}, this one knows up to {})", db_path.display(), version, SCHEMA_VERSION));
+    }
+    if version < SCHEMA_VERSION {
+        return Err(format!("{}: database is from an older version of filewatch (schema {}), `--resume` it to bring it up to date first", db_path.display(), version));
+    }
+    Ok((db_path, conn))
+}
+
+/// Write the rows of `session` to `path`, see `Command::Export`
+fn export(db_dir: &Path, session: &str, path: &Path, format: store::ExportFormat, events: bool) -> Result<u64, String> {
+    let (_, conn) = open_session_read_only(db_dir, session)?;
+    let store = LogStore::new(conn, false)
+        .and_then(|mut store| store.set_show_events(events).map(|_| store))
+        .map_err(|e| format!("cannot set up database: {}", e))?;
+    store.export(0, path, format)
+}
+
+/// A database that only lives as long as the connection, see `Args::no_persist`
+fn open_memory_db() -> Result<rusqlite::Connection, String> {
+    rusqlite::Connection::open_in_memory()
+        .and_then(|conn| create_tables(&conn).map(|_| conn))
+        .map_err(|e| format!("cannot create in-memory database: {}", e))
+}
+
+/// `display` is only set where it differs from the raw message.
+/// `line_ts` is when the line says it was logged, see `--timestamp-format`.
+/// `level` is only set for lines a severity was found in. `repeats` is how
+/// many times the line came again straight after, see `--collapse-repeats`.
+/// `log_fts` is the full-text index for searching, see `store::CREATE_FTS`.
+/// `event` has what happened to sources, see `store::CREATE_EVENTS`.
+/// `session` has a row for each run, see `sessions::CREATE_TABLES`
+fn create_tables(conn: &rusqlite::Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE log ( id INTEGER PRIMARY KEY, ts INTEGER NOT NULL, file_id TEXT NOT NULL, message TEXT NOT NULL, display TEXT, line_ts INTEGER, level TEXT, repeats INTEGER NOT NULL DEFAULT 0 )",
+        (),
+    )?;
+    conn.execute_batch(store::CREATE_FTS)?;
+    conn.execute_batch(store::CREATE_EVENTS)?;
+    conn.execute_batch(sessions::CREATE_TABLES)?;
+    conn.pragma_update(None, "user_version", SCHEMA_VERSION)
+}
+
+/// The version of the tables `create_tables` makes, kept in the database's
+/// `user_version`. Bumped whenever they change, with a step in `migrate`.
+const SCHEMA_VERSION: i64 = 5;
+
+/// What the status bar says after copying `lines` lines
+fn copied_message(copied: Result<(), String>, lines: usize) -> String {
+    match copied {
+        Ok(()) if lines == 1 => String::from("copied 1 line"),
+        Ok(()) => format!("copied {} lines", lines),
+        Err(e) => format!("copying failed: {}", e),
+    }
+}
+
+/// Columns ← and → scroll the line in the details popup by, see `ui::Details`
+const DETAILS_SIDEWAYS: isize = 8;
+
+/// Whether rows are wrapped at words, kept in the session, see `Wrap::words`
+const WORD_WRAP_SETTING: &str = "word_wrap";
+
+/// Bring the tables of a database made by an older version up to date, or
+/// refuse one made by a newer version, which may store rows differently.
+/// The error says what went wrong, for showing as is.
+fn migrate(conn: &rusqlite::Connection) -> Result<(), String> {
+    let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(|e| format!("cannot read schema version: {}", e))?;
+    if version > SCHEMA_VERSION {
+        return Err(format!("database is from a newer version of filewatch (schema {}, this one knows up to {})", version, SCHEMA_VERSION));
+    }
+    if version == SCHEMA_VERSION {
+        return Ok(());
+    }
+    let has_log: bool = conn.query_row("SELECT count(*) > 0 FROM sqlite_master WHERE name = 'log'", (), |row| row.get(0))
+        .map_err(|e| format!("cannot read schema: {}", e))?;
+    if !has_log {
+        return Err(String::from("not a filewatch session, there's no log table"));
+    }
+    let steps = || -> rusqlite::Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        if version < 1 {
+            add_columns(&tx)?;
+        }
+        if version < 2 {
+            tx.execute_batch(sessions::CREATE_TABLES)?;
+            sessions::backfill(&tx)?;
+        } else if version < 3 {
+            tx.execute_batch("ALTER TABLE session ADD COLUMN hostname TEXT")?;
+            tx.execute_batch(sessions::CREATE_TABLES)?;
+        }
+        if version < 4 {
+            move_events(&tx)?;
+        }
+        if version < 5 {
+            tx.execute_batch(sessions::CREATE_TABLES)?;
+        }
+        tx.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        tx.commit()
+    };
+    steps().map_err(|e| format!("cannot migrate database from schema {}: {}", version, e))
+}
+
+/// Before versions were kept, columns were added to `log` one at a time, so
+/// any of them may be missing. Rows from then have no time, and show as 1970.
+fn add_columns(conn: &rusqlite::Connection) -> rusqlite::Result<()> {
+    let columns = conn.prepare("SELECT name FROM pragma_table_info('log')")?
+        .query_map((), |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (name, definition) in [
+        ("ts", "INTEGER NOT NULL DEFAULT 0"),
+        ("display", "TEXT"),
+        ("kind", "TEXT"),
+        ("line_ts", "INTEGER"),
+        ("level", "TEXT"),
+        ("repeats", "INTEGER NOT NULL DEFAULT 0"),
+    ] {
+        if !columns.iter().any(|column| column == name) {
+            conn.execute(&format!("ALTER TABLE log ADD COLUMN {} {}", name, definition), ())?;
+        }
+    }
+    Ok(())
+}
+
+/// Events used to be stored in `log` as rows with a `kind`. They're moved
+/// to `event` as they are, ids and all, and taken out of the search index.
+fn move_events(conn: &rusqlite::Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(store::CREATE_EVENTS)?;
+    conn.execute(
+        "INSERT INTO event (id, ts, file_id, kind, detail, line_ts) SELECT id, ts, file_id, kind, coalesce(display, message), line_ts FROM log WHERE kind IS NOT NULL",
+        (),
+    )?;
+    let has_index: bool = conn.query_row("SELECT count(*) > 0 FROM sqlite_master WHERE name = 'log_fts'", (), |row| row.get(0))?;
+    if has_index {
+        conn.execute("DELETE FROM log_fts WHERE rowid IN (SELECT id FROM event)", ())?;
+    }
+    conn.execute("DELETE FROM log WHERE kind IS NOT NULL", ())?;
+    conn.execute("ALTER TABLE log DROP COLUMN kind", ())?;
+    Ok(())
 }
 
+/// How a stored row is shown, after `log_prefix`, the file it's from as
+/// `ui::FilePrefixes` has it
+fn log_line(row: store::Row, log_prefix: &str, color: ColorMode, timestamps: Timestamps, order_by_line_ts: bool) -> ui::LogLine {
+    let store::Row { id, ts, file_id, message, kind, level, repeats, line_ts, skipped } = row;
+    let message = if kind.is_some() {
+        format!("filewatch: {}", message)
+    } else {
+        message
+    };
+    let (message, colors) = match color {
+        ColorMode::Render => {
+            let (message, sgr) = file_watch::split_sgr(&message);
+            let colors = ui::sgr_styles(&sgr);
+            (message, colors)
+        }
+        ColorMode::Never => (file_watch::strip_ansi(&message).into_owned(), vec![]),
+        ColorMode::Strip => (message, vec![]),
+    };
+    let mut text = String::new();
+    // a prefix would make a blank line look like it isn't
+    if !message.is_empty() {
+        match timestamps {
+            Timestamps::Hidden => {}
+            Timestamps::Absolute => text.push_str(&format!("{} ", format_timestamp(ts))),
+            Timestamps::Relative => text.push_str(&format!("{:>6} ", format_age(unix_millis() - ts))),
+        }
+        if !log_prefix.is_empty() {
+            text.push_str(log_prefix);
+            text.push(' ');
+        }
+        text.push_str(&message);
+    }
+    let level = level.and_then(|level| level.parse().ok());
+    // colors are at char indexes in the message, which comes after the prefix
+    let prefix_len = text.chars().count() - message.chars().count();
+    let colors = colors.into_iter().map(|(at, style)| (at + prefix_len, style)).collect();
+    if repeats > 0 {
+        text.push_str(&ui::repeat_suffix(repeats));
+    }
+    // the same order as `coalesce(line_ts, ts), id`, lines without a
+    // timestamp of their own go after the line before
+    let order = if order_by_line_ts { (line_ts.unwrap_or(ts), id) } else { (0, id) };
+    let file = if message.is_empty() { String::new() } else { log_prefix.to_owned() };
+    ui::LogLine { id, order, text, message_start: prefix_len, file, file_id, ts, line_ts, is_event: kind.is_some(), level, colors, repeats, gap: None, skipped: (skipped > 0).then_some(skipped) }
+}
+
+
+/// See `Args::color`
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ColorMode {
+    Strip,
+    Render,
+    Never,
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "strip" => Ok(ColorMode::Strip),
+            "render" => Ok(ColorMode::Render),
+            "never" => Ok(ColorMode::Never),
+            _ => Err(format!("unknown color mode '{}', expected strip, render or never", s)),
+        }
+    }
+}
+
+/// How long relative timestamps are shown before they're queried again
+const RELATIVE_REFRESH: Duration = Duration::from_secs(1);
+
+/// What's shown in front of each line for when it was read, which `t`
+/// cycles through
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum Timestamps {
+    #[default]
+    Hidden,
+    /// `HH:MM:SS.mmm`, see `format_timestamp`
+    Absolute,
+    /// How long ago, see `format_age`
+    Relative,
+}
+
+impl Timestamps {
+    fn next(self) -> Self {
+        match self {
+            Timestamps::Hidden => Timestamps::Absolute,
+            Timestamps::Absolute => Timestamps::Relative,
+            Timestamps::Relative => Timestamps::Hidden,
+        }
+    }
+}
+
+impl fmt::Display for Timestamps {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Timestamps::Hidden => "hidden",
+            Timestamps::Absolute => "time of day",
+            Timestamps::Relative => "how long ago",
+        })
+    }
+}
+
+/// See `Args::color_files`
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ColorFiles {
+    Always,
+    Never,
+}
+
+impl FromStr for ColorFiles {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "always" => Ok(ColorFiles::Always),
+            "never" => Ok(ColorFiles::Never),
+            _ => Err(format!("unknown value '{}', expected always or never", s)),
+        }
+    }
+}
 
 fn get_file_tags(file_names: &[String]) -> HashMap<String, String> {
-    if file_names.len() <= 1 {
+    if file_names.len() == 1 {
         let key = file_names[0].clone();
         let val = String::from(" >");
-        return HashMap::from([(key, val); 1])
+        HashMap::from([(key, val); 1])
     }
     else {
-        return file_names.into_iter()
+        file_names.iter()
             .map(|n| (n.clone(), n.clone()))
             .collect()
     }
-}
\ No newline at end of file
+}
+
+/// Milliseconds since the epoch, as stored in the `ts` column
+fn unix_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_millis() as i64
+}
+
+/// `HH:MM:SS.mmm` in local time
+fn format_timestamp(ts: i64) -> String {
+    match chrono::DateTime::from_timestamp_millis(ts) {
+        Some(time) => time.with_timezone(&chrono::Local).format("%H:%M:%S%.3f").to_string(),
+        None => String::from("??:??:??.???"),
+    }
+}
+
+/// `ms` as how long ago, e.g. `2s`, `5m` or `1h3m`: the largest unit there's
+/// any of, and for hours and days the one after it
+fn format_age(ms: i64) -> String {
+    let secs = ms.max(0) / 1000;
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h{}m", secs / 3600, secs / 60 % 60),
+        _ => format!("{}d{}h", secs / 86400, secs / 3600 % 24),
+    }
+}
+
+fn get_tail(args: &Args) -> Option<file_watch::Tail> {
+    if args.no_history {
+        Some(file_watch::Tail::Nothing)
+    }
+    else if let Some(lines) = args.tail_lines {
+        Some(file_watch::Tail::Lines(lines))
+    }
+    else {
+        args.tail_bytes.map(file_watch::Tail::Bytes)
+    }
+}
+
+/// Polling is used for everything when `--poll` is given without `--poll-paths`,
+/// otherwise only for paths under one of the `--poll-paths`.
+fn get_backend(path: &str, poll_interval: Option<u64>, poll_paths: &[PathBuf]) -> file_watch::Backend {
+    let interval = Duration::from_millis(poll_interval.unwrap_or(1000));
+    if poll_paths.is_empty() {
+        return match poll_interval {
+            Some(_) => file_watch::Backend::Poll(interval),
+            None => file_watch::Backend::Native,
+        };
+    }
+    let path = fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+    let should_poll = poll_paths.iter()
+        .map(|p| fs::canonicalize(p).unwrap_or_else(|_| p.clone()))
+        .any(|p| path.starts_with(p));
+    if should_poll {
+        file_watch::Backend::Poll(interval)
+    } else {
+        file_watch::Backend::Native
+    }
+}
+
+/// Expands each glob pattern into the paths it matches, in argument order.
+/// Paths matched by more than one pattern are only returned once.
+/// With `allow_missing`, a pattern that matches nothing is kept as a literal path.
+fn expand_file_patterns(specs: &[FileSpec], allow_missing: bool) -> Result<Vec<(String, FileSpec)>, String> {
+    let mut seen = HashSet::new();
+    let mut paths = vec![];
+    for spec in specs {
+        let pattern = &spec.pattern;
+        // stdin, network and command sources aren't paths
+        let is_path = pattern != "-" && ![file_watch::TCP_PREFIX, file_watch::SYSLOG_PREFIX, file_watch::EXEC_PREFIX]
+            .iter()
+            .any(|prefix| pattern.starts_with(prefix));
+        if !is_path {
+            if seen.insert(pattern.clone()) {
+                paths.push((pattern.clone(), spec.clone()));
+            }
+            continue;
+        }
+        let entries = glob::glob(pattern)
+            .map_err(|e| format!("invalid pattern '{}': {}", pattern, e))?;
+        let mut matched = false;
+        for entry in entries {
+            let path = entry
+                .map_err(|e| format!("cannot read {}: {}", e.path().display(), e.error()))?;
+            matched = true;
+            let path = path.to_string_lossy().into_owned();
+            if seen.insert(path.clone()) {
+                paths.push((path, spec.clone()));
+            }
+        }
+        if !matched {
+            if !allow_missing {
+                return Err(format!("'{}' did not match any files", pattern));
+            }
+            if seen.insert(pattern.clone()) {
+                paths.push((pattern.clone(), spec.clone()));
+            }
+        }
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_show_takes_no_files_or_retention() {
+        assert!(Args::try_parse_from(["filewatch", "sessions", "show", "latest"]).is_ok());
+        for args in [
+            &["filewatch", "sessions", "show", "latest", "app.log"][..],
+            &["filewatch", "app.log", "sessions", "show", "latest"],
+            &["filewatch", "--retain-lines", "10", "sessions", "show", "latest"],
+            &["filewatch", "--retain-hours", "1", "sessions", "show", "latest"],
+        ] {
+            let parsed = Args::try_parse_from(args);
+            assert!(!matches!(parsed, Ok(Args { command: Some(Command::Sessions { .. }), .. })), "{:?}", args);
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+
+/// `session` has a row for each run that stored lines in the session: when
+/// it started, its command line and the files it was given after globs were
+/// expanded, as JSON arrays, and the machine it ran on. `session_file` has
+/// a row for each of those files, with its label and how big it was then,
+/// if it's a file. `setting` has how the pager was left, e.g. whether it
+/// wrapped at words, for showing the session the same way when it's resumed,
+/// see `LogStore::setting`.
+pub const CREATE_TABLES: &str = "
+    CREATE TABLE IF NOT EXISTS session ( id INTEGER PRIMARY KEY, started_at INTEGER NOT NULL, argv TEXT NOT NULL, files TEXT NOT NULL, hostname TEXT );
+    CREATE TABLE IF NOT EXISTS session_file ( session_id INTEGER NOT NULL REFERENCES session (id), path TEXT NOT NULL, label TEXT, initial_size INTEGER );
+    CREATE TABLE IF NOT EXISTS setting ( name TEXT PRIMARY KEY, value TEXT NOT NULL );
+";
+
+/// A file argument of this run, after globs were expanded, see `record`
+pub struct SessionFile<'a> {
+    pub path: &'a str,
+    pub label: Option<&'a str>,
+}
+
+/// Add this run to the `session` tables
+pub fn record(conn: &Connection, started_at: i64, files: &[SessionFile]) -> rusqlite::Result<()> {
+    let argv: Vec<String> = std::env::args().collect();
+    let paths: Vec<&str> = files.iter().map(|file| file.path).collect();
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO session (started_at, argv, files, hostname) VALUES (?, ?, ?, ?)",
+        (started_at, serde_json::to_string(&argv).unwrap_or_default(), serde_json::to_string(&paths).unwrap_or_default(), hostname()),
+    )?;
+    let session_id = tx.last_insert_rowid();
+    for file in files {
+        // streams and commands have no size
+        let size = fs::metadata(file.path).ok().filter(|meta| meta.is_file()).map(|meta| meta.len());
+        tx.execute(
+            "INSERT INTO session_file (session_id, path, label, initial_size) VALUES (?, ?, ?, ?)",
+            (session_id, file.path, file.label, size),
+        )?;
+    }
+    tx.commit()
+}
+
+/// Stand in for the runs of a session from before they were recorded, with
+/// what can still be told: when the first line was stored, and the files
+/// lines came from. The command line and machine are unknown.
+pub fn backfill(conn: &Connection) -> rusqlite::Result<()> {
+    let recorded: bool = conn.query_row("SELECT count(*) > 0 FROM session", (), |row| row.get(0))?;
+    if recorded {
+        return Ok(());
+    }
+    let file_ids = conn.prepare("SELECT DISTINCT file_id FROM log")?
+        .query_map((), |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    conn.execute(
+        "INSERT INTO session (started_at, argv, files) SELECT coalesce(min(ts), 0), '[]', ? FROM log",
+        (serde_json::to_string(&file_ids).unwrap_or_default(),),
+    )?;
+    let session_id = conn.last_insert_rowid();
+    for file_id in &file_ids {
+        conn.execute("INSERT INTO session_file (session_id, path) VALUES (?, ?)", (session_id, file_id))?;
+    }
+    Ok(())
+}
+
+/// The name of this machine, if it can be told
+#[cfg(unix)]
+fn hostname() -> Option<String> {
+    let mut name = [0u8; 256];
+    // SAFETY: the buffer is as long as it's said to be
+    if unsafe { libc::gethostname(name.as_mut_ptr().cast(), name.len()) } != 0 {
+        return None;
+    }
+    let len = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+    Some(String::from_utf8_lossy(&name[..len]).into_owned())
+}
+
+#[cfg(not(unix))]
+fn hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME").ok()
+}
+
+/// A session database in the db directory, named after when it was
+/// created, see `open_db` in main
+struct Session {
+    path: PathBuf,
+    /// Milliseconds since the epoch, from the name
+    created: i64,
+}
+
+impl Session {
+    /// The files that make up the session: the database, its WAL files, and
+    /// how far it read each file, see `Args::resume`
+    fn files(&self) -> Vec<PathBuf> {
+        let mut files = vec![self.path.with_extension("offsets.json")];
+        for suffix in ["", "-wal", "-shm"] {
+            let mut path = self.path.clone().into_os_string();
+            path.push(suffix);
+            files.push(path.into());
+        }
+        files
+    }
+
+    /// Bytes taken on disk, by all of `files`
+    fn size(&self) -> u64 {
+        self.files().iter().filter_map(|file| fs::metadata(file).ok()).map(|meta| meta.len()).sum()
+    }
+
+    /// When anything was last written to it
+    fn modified(&self) -> Option<SystemTime> {
+        self.files().iter().filter_map(|file| fs::metadata(file).and_then(|meta| meta.modified()).ok()).max()
+    }
+
+    /// How many rows it has, and the files the first run that recorded them
+    /// was given, if any did
+    fn contents(&self) -> rusqlite::Result<(u64, Option<Vec<String>>)> {
+        let conn = Connection::open_with_flags(&self.path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let rows = conn.query_row("SELECT count(*) FROM log", (), |row| row.get(0))?;
+        let has_table: bool = conn.query_row("SELECT count(*) > 0 FROM sqlite_master WHERE name = 'session'", (), |row| row.get(0))?;
+        if !has_table {
+            return Ok((rows, None));
+        }
+        let files: Option<String> = conn
+            .query_row("SELECT files FROM session ORDER BY id LIMIT 1", (), |row| row.get(0))
+            .optional()?;
+        Ok((rows, files.and_then(|files| serde_json::from_str(&files).ok())))
+    }
+}
+
+/// The sessions in `db_dir`, oldest first
+fn sessions(db_dir: &Path) -> Result<Vec<Session>, String> {
+    let entries = fs::read_dir(db_dir)
+        .map_err(|e| format!("cannot read database directory {}: {}", db_dir.display(), e))?;
+    let mut sessions: Vec<Session> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            let created = path.file_name()?.to_str()?.strip_suffix(".db3")?.parse().ok()?;
+            Some(Session { path, created })
+        })
+        .collect();
+    sessions.sort_by_key(|session| session.created);
+    Ok(sessions)
+}
+
+/// The database of a session: `latest` for the last one created in
+/// `db_dir`, its name there with or without `.db3`, or its path
+pub fn find(db_dir: &Path, session: &str) -> Result<PathBuf, String> {
+    if session == "latest" {
+        return sessions(db_dir)?
+            .pop()
+            .map(|session| session.path)
+            .ok_or_else(|| format!("no sessions in {}", db_dir.display()));
+    }
+    let name = session.strip_suffix(".db3").unwrap_or(session);
+    if name.parse::<i64>().is_ok() && !Path::new(session).exists() {
+        return Ok(db_dir.join(format!("{}.db3", name)));
+    }
+    Ok(PathBuf::from(session))
+}
+
+/// Print a line for each session in `db_dir`
+pub fn list(db_dir: &Path) -> Result<(), String> {
+    let sessions = sessions(db_dir)?;
+    if sessions.is_empty() {
+        println!("no sessions in {}", db_dir.display());
+        return Ok(());
+    }
+    println!("{:<15} {:<19} {:>9} {:>10}  FILES", "SESSION", "STARTED", "SIZE", "ROWS");
+    for session in &sessions {
+        let (rows, files) = match session.contents() {
+            Ok((rows, files)) => (rows.to_string(), files.map_or(String::from("?"), |files| files.join(", "))),
+            Err(e) => (String::from("?"), format!("cannot read: {}", e)),
+        };
+        println!(
+            "{:<15} {:<19} {:>9} {:>10}  {}",
+            session.created,
+            format_time(session.created),
+            format_size(session.size()),
+            rows,
+            files,
+        );
+    }
+    Ok(())
+}
+
+/// Delete the sessions in `db_dir` that aren't among the `keep_last` most
+/// recent, and haven't been written to for `older_than`. Either can be left
+/// out, but not both.
+pub fn prune(db_dir: &Path, keep_last: Option<usize>, older_than: Option<Duration>) -> Result<(), String> {
+    let mut sessions = sessions(db_dir)?;
+    sessions.truncate(sessions.len().saturating_sub(keep_last.unwrap_or(0)));
+    let now = SystemTime::now();
+    let old = |session: &Session| match (older_than, session.modified()) {
+        (None, _) => true,
+        (Some(older_than), Some(modified)) => now.duration_since(modified).is_ok_and(|age| age > older_than),
+        (Some(_), None) => false,
+    };
+    let mut deleted = 0;
+    let mut freed = 0;
+    for session in sessions.iter().filter(|session| old(session)) {
+        freed += session.size();
+        for file in session.files() {
+            match fs::remove_file(&file) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(format!("cannot delete {}: {}", file.display(), e)),
+            }
+        }
+        println!("deleted {}", session.path.display());
+        deleted += 1;
+    }
+    println!("{} sessions deleted, {} freed", deleted, format_size(freed));
+    Ok(())
+}
+
+/// A duration like `90s`, `30m`, `12h`, `7d` or `2w`, for `prune --older-than`
+/// and `--gap-threshold`
+pub fn parse_age(age: &str) -> Result<Duration, String> {
+    let split = age.find(|c: char| !c.is_ascii_digit()).unwrap_or(age.len());
+    let (count, unit) = age.split_at(split);
+    let count: u64 = count.parse().map_err(|_| format!("expected a number and a unit, e.g. 7d, not {:?}", age))?;
+    let secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("unknown unit {:?}, expected s, m, h, d or w", unit)),
+    };
+    Ok(Duration::from_secs(count * secs))
+}
+
+fn format_time(millis: i64) -> String {
+    match chrono::DateTime::from_timestamp_millis(millis) {
+        Some(time) => time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string(),
+        None => String::from("?"),
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
@@ -0,0 +1,233 @@
+use std::collections::VecDeque;
+
+use regex::Regex;
+
+use super::{row, LogStore, Row, SELECT_EVENTS, SELECT_ROWS};
+
+/// The rows a pane shows around the ones a pattern matches, like `grep -C`,
+/// in place of all of them, see `LogStore::set_context`. They're kept as
+/// ranges of row numbers, counting the rows that get past the rest of the
+/// pane's `Filter`, which are numbered from 0 again after them for the UI.
+pub(super) struct Context {
+    matcher: Regex,
+    /// Rows shown before and after each match
+    lines: usize,
+    groups: Vec<Group>,
+    /// Rows may have gone in between others since `groups` were found, and
+    /// they have to be found again, see `LogStore::commit`
+    stale: bool,
+}
+
+/// Rows next to each other shown by `Context`, those around one match or
+/// more that are close enough together
+struct Group {
+    /// Row number of the first one
+    start: usize,
+    /// Row number after the last one, which can be past the rows there are
+    /// for the ones still to come after a match
+    end: usize,
+    /// `ui::LogLine::order` of the first one, to query them by, which
+    /// stays the same when rows before it are deleted
+    first: (i64, i64),
+}
+
+impl Context {
+    /// How many rows it shows, out of the `count` past the filter
+    fn shown(&self, count: usize) -> usize {
+        self.groups.iter().map(|group| group.end.min(count).saturating_sub(group.start)).sum()
+    }
+
+    /// Row number, among the rows it shows, of the one that's at `row`
+    /// among the `count` past the filter, or of the first one after if it
+    /// doesn't show it
+    fn number(&self, row: usize, count: usize) -> usize {
+        let mut number = 0;
+        for group in &self.groups {
+            if row < group.start {
+                break;
+            }
+            let end = group.end.min(count);
+            number += row.min(end).saturating_sub(group.start);
+        }
+        number
+    }
+
+    /// Rows were deleted, `deleted` of them that got past the filter, all
+    /// before the others
+    pub(super) fn forget(&mut self, deleted: usize) {
+        for group in &mut self.groups {
+            group.start = group.start.saturating_sub(deleted);
+            group.end = group.end.saturating_sub(deleted);
+        }
+        self.groups.retain(|group| group.end > 0);
+    }
+}
+
+/// Count the row at `number` as a match in `groups`, adding to the last one
+/// if its rows are next to this one's or some are the same. `first` is the
+/// `ui::LogLine::order` of the row `lines` before it, or the first one if
+/// there are fewer, only needed for a new group.
+fn add_match(groups: &mut Vec<Group>, number: usize, lines: usize, first: impl FnOnce() -> rusqlite::Result<(i64, i64)>) -> rusqlite::Result<()> {
+    let start = number.saturating_sub(lines);
+    let end = number + lines + 1;
+    match groups.last_mut() {
+        Some(last) if last.end >= start => last.end = end,
+        _ => groups.push(Group { start, end, first: first()? }),
+    }
+    Ok(())
+}
+
+impl LogStore {
+    /// Only show the rows `matcher` matches in `pane`, with `lines` rows
+    /// before and after each, or all of them again. Rows left out in
+    /// between are counted on the row after them, see `Row::skipped`.
+    pub fn set_context(&mut self, pane: usize, context: Option<(Regex, usize)>) -> rusqlite::Result<()> {
+        self.filters[pane].context = context.map(|(matcher, lines)| Context { matcher, lines, groups: vec![], stale: true });
+        self.find_groups(pane)
+    }
+
+    /// Find the rows the context of `pane` shows again, if it has one, as
+    /// the rows past the rest of its filter changed
+    pub(super) fn find_groups(&mut self, pane: usize) -> rusqlite::Result<()> {
+        let Some(context) = &self.filters[pane].context else {
+            return Ok(());
+        };
+        let (order, _) = self.order();
+        let sql = format!("{} ORDER BY {}", self.select_shown(pane), order);
+        let mut statement = self.conn.prepare(&sql)?;
+        let mut rows = statement.query(())?;
+        let mut groups = vec![];
+        // the orders of the rows from `lines` before the current one to it
+        let mut recent = VecDeque::with_capacity(context.lines + 1);
+        let mut number = 0;
+        while let Some(row) = rows.next()? {
+            recent.push_back(self.row_order(row)?);
+            if recent.len() > context.lines + 1 {
+                recent.pop_front();
+            }
+            // borrowed from the row, most of them don't match, and events
+            // never do
+            let is_row = row.get_ref("kind")?.as_str_or_null()?.is_none();
+            if is_row && context.matcher.is_match(row.get_ref("message")?.as_str()?) {
+                add_match(&mut groups, number, context.lines, || Ok(recent[0]))?;
+            }
+            number += 1;
+        }
+        drop(rows);
+        drop(statement);
+        if let Some(context) = &mut self.filters[pane].context {
+            context.groups = groups;
+            context.stale = false;
+        }
+        Ok(())
+    }
+
+    /// Show a row just inserted with `message`, or an event, in the context
+    /// of `pane` if it's a match, as the last of the rows past its filter
+    pub(super) fn add_to_context(&mut self, pane: usize, message: Option<&str>) -> rusqlite::Result<()> {
+        let Some(context) = &self.filters[pane].context else {
+            return Ok(());
+        };
+        // anywhere among the others, so they're found again once it's
+        // committed
+        if self.order_by_line_ts {
+            if let Some(context) = &mut self.filters[pane].context {
+                context.stale = true;
+            }
+            return Ok(());
+        }
+        if !message.is_some_and(|message| context.matcher.is_match(message)) {
+            return Ok(());
+        }
+        let number = self.filters[pane].count - 1;
+        let lines = context.lines;
+        let (_, descending) = self.order();
+        let sql = format!("{} ORDER BY {} LIMIT 1 OFFSET ?", self.select_shown(pane), descending);
+        let mut groups = std::mem::take(&mut self.filters[pane].context.as_mut().expect("there's a context").groups);
+        let added = add_match(&mut groups, number, lines, || {
+            self.conn.prepare_cached(&sql)?.query_row((lines.min(number),), |row| self.row_order(row))
+        });
+        if let Some(context) = &mut self.filters[pane].context {
+            context.groups = groups;
+        }
+        added
+    }
+
+    /// Find the rows of contexts that rows went in between of again, see
+    /// `Context::stale`
+    pub(super) fn refresh_stale_contexts(&mut self) -> rusqlite::Result<()> {
+        for pane in 0..self.filters.len() {
+            if self.filters[pane].context.as_ref().is_some_and(|context| context.stale) {
+                self.find_groups(pane)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The window of rows from `window_start` the context of `pane` shows,
+    /// if it has one, at most `limit` of them, with how many it shows in
+    /// all. Each group is queried on its own from its first row, after
+    /// which it only steps over its own rows.
+    pub(super) fn context_rows(&self, pane: usize, window_start: usize, limit: usize) -> rusqlite::Result<Option<(Vec<Row>, usize)>> {
+        let Some(context) = &self.filters[pane].context else {
+            return Ok(None);
+        };
+        let count = self.filters[pane].count;
+        let window_end = window_start.saturating_add(limit);
+        let (order, _) = self.order();
+        let from_first = if self.order_by_line_ts { "(coalesce(line_ts, ts), id) >= (?1, ?2)" } else { "id >= ?2" };
+        let sql = format!(
+            "{} WHERE {} AND {from_first} UNION ALL {} WHERE {} AND {from_first} ORDER BY {} LIMIT ?3 OFFSET ?4",
+            SELECT_ROWS,
+            self.filter(pane),
+            SELECT_EVENTS,
+            self.event_filter(pane),
+            order,
+        );
+        let mut statement = self.conn.prepare_cached(&sql)?;
+        let mut rows = vec![];
+        // row number of the group's first row among the ones shown
+        let mut number = 0;
+        let mut last_end = None;
+        for group in &context.groups {
+            let len = group.end.min(count).saturating_sub(group.start);
+            let (from, to) = (window_start.max(number), window_end.min(number + len));
+            if from < to {
+                let offset = from - number;
+                let first = rows.len();
+                rows.extend(statement.query_map((group.first.0, group.first.1, to - from, offset), row)?.collect::<rusqlite::Result<Vec<_>>>()?);
+                if let (0, Some(last_end), Some(row)) = (offset, last_end, rows.get_mut(first)) {
+                    row.skipped = group.start - last_end;
+                }
+            }
+            number += len;
+            last_end = Some(group.end.min(count));
+            if number >= window_end {
+                break;
+            }
+        }
+        Ok(Some((rows, context.shown(count))))
+    }
+
+    /// Row number, among the rows the context of `pane` shows if it has
+    /// one, of the one at `row` among those past the rest of its filter
+    pub(super) fn context_number(&self, pane: usize, row: usize) -> usize {
+        match &self.filters[pane].context {
+            Some(context) => context.number(row, self.filters[pane].count),
+            None => row,
+        }
+    }
+
+    /// How many rows `pane` shows, only counting those its context does if
+    /// it has one
+    pub(super) fn shown_count(&self, pane: usize) -> usize {
+        let count = self.filters[pane].count;
+        self.filters[pane].context.as_ref().map_or(count, |context| context.shown(count))
+    }
+
+    /// `ui::LogLine::order` of a row selected with `sort_ts` and `id`
+    fn row_order(&self, row: &rusqlite::Row) -> rusqlite::Result<(i64, i64)> {
+        let id = row.get("id")?;
+        Ok(if self.order_by_line_ts { (row.get("sort_ts")?, id) } else { (0, id) })
+    }
+}
@@ -0,0 +1,183 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use serde_json::json;
+
+use rusqlite::Connection;
+
+use super::{add_regexp, LogStore};
+
+/// How `LogStore::export` writes rows
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    /// `file_id: message`, as rows are shown
+    Text,
+    /// A JSON object a line, with every column
+    Jsonl,
+    /// Every column, after a header row
+    Csv,
+}
+
+impl ExportFormat {
+    /// The format for a file named `path`, going by its extension, text
+    /// unless it's `.jsonl`, `.json` or `.csv`
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("jsonl" | "json") => ExportFormat::Jsonl,
+            Some("csv") => ExportFormat::Csv,
+            _ => ExportFormat::Text,
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ExportFormat::Text),
+            "jsonl" => Ok(ExportFormat::Jsonl),
+            "csv" => Ok(ExportFormat::Csv),
+            _ => Err(format!("unknown format {:?}, expected text, jsonl or csv", s)),
+        }
+    }
+}
+
+const COLUMNS: [&str; 9] = ["id", "ts", "file_id", "message", "display", "kind", "line_ts", "level", "repeats"];
+
+/// The `COLUMNS` of rows, and of events as if they were rows, see
+/// `LogStore::select_shown`
+const EXPORT_ROWS: &str = "SELECT id, ts, file_id, message, display, NULL AS kind, line_ts, level, repeats, coalesce(line_ts, ts) AS sort_ts FROM log";
+const EXPORT_EVENTS: &str = "SELECT id, ts, file_id, detail, NULL, kind, line_ts, NULL, 0, coalesce(line_ts, ts) FROM event";
+
+impl LogStore {
+    /// Write the rows `pane` shows to `path`, or stdout for `-`, in the
+    /// order they're shown, with events if they're shown. They're written as
+    /// they're read, however many there are. Returns how many were written,
+    /// or what went wrong, for showing as is.
+    pub fn export(&self, pane: usize, path: &Path, format: ExportFormat) -> Result<u64, String> {
+        self.export_query(pane).run(&self.conn, path, format)
+    }
+
+    /// What `export` runs for `pane`, as it's filtered now and with only
+    /// the rows stored so far, to run later on a connection of its own, see
+    /// `reader`
+    pub(super) fn export_query(&self, pane: usize) -> ExportQuery {
+        let (order, _) = self.order();
+        let sql = format!(
+            "{} WHERE id < {} AND {} UNION ALL {} WHERE id < {} AND {} ORDER BY {}",
+            EXPORT_ROWS,
+            self.next_id,
+            self.filter(pane),
+            EXPORT_EVENTS,
+            self.next_id,
+            self.event_filter(pane),
+            order,
+        );
+        ExportQuery { sql }
+    }
+
+    /// A read-only connection of its own to the database, for an export to
+    /// run on while rows keep being written, or None for an in-memory one,
+    /// which no other connection can read
+    pub(super) fn reader(&self) -> rusqlite::Result<Option<Connection>> {
+        let Some(path) = self.conn.path().filter(|path| !path.is_empty()) else {
+            return Ok(None);
+        };
+        let conn = super::open_read_only(Path::new(path))?;
+        add_regexp(&conn)?;
+        Ok(Some(conn))
+    }
+}
+
+/// The rows a pane shows, see `LogStore::export_query`
+pub(super) struct ExportQuery {
+    sql: String,
+}
+
+impl ExportQuery {
+    /// Write the rows to `path` as `LogStore::export` does, reading them with
+    /// `conn`
+    pub(super) fn run(&self, conn: &Connection, path: &Path, format: ExportFormat) -> Result<u64, String> {
+        let result = if path == Path::new("-") {
+            self.write_rows(conn, &mut io::stdout().lock(), format)
+        } else {
+            let file = File::create(path).map_err(|e| format!("cannot create {}: {}", path.display(), e))?;
+            self.write_rows(conn, &mut BufWriter::new(file), format)
+        };
+        result.map_err(|e| format!("cannot export to {}: {}", path.display(), e))
+    }
+
+    fn write_rows(&self, conn: &Connection, out: &mut impl Write, format: ExportFormat) -> Result<u64, ExportError> {
+        let mut statement = conn.prepare(&self.sql)?;
+        let mut rows = statement.query(())?;
+        if format == ExportFormat::Csv {
+            writeln!(out, "{}", COLUMNS.join(","))?;
+        }
+        let mut written = 0;
+        while let Some(row) = rows.next()? {
+            let file_id: String = row.get("file_id")?;
+            let message: String = row.get("message")?;
+            let display: Option<String> = row.get("display")?;
+            let kind: Option<String> = row.get("kind")?;
+            match format {
+                ExportFormat::Text => {
+                    let shown = display.as_ref().unwrap_or(&message);
+                    let event = if kind.is_some() { "filewatch: " } else { "" };
+                    writeln!(out, "{}: {}{}", file_id, event, shown)?;
+                }
+                ExportFormat::Jsonl => {
+                    let record = json!({
+                        "id": row.get::<_, i64>("id")?,
+                        "ts": row.get::<_, i64>("ts")?,
+                        "file_id": file_id,
+                        "message": message,
+                        "display": display,
+                        "kind": kind,
+                        "line_ts": row.get::<_, Option<i64>>("line_ts")?,
+                        "level": row.get::<_, Option<String>>("level")?,
+                        "repeats": row.get::<_, i64>("repeats")?,
+                    });
+                    writeln!(out, "{}", record)?;
+                }
+                ExportFormat::Csv => {
+                    let fields = [
+                        row.get::<_, i64>("id")?.to_string(),
+                        row.get::<_, i64>("ts")?.to_string(),
+                        csv_field(&file_id),
+                        csv_field(&message),
+                        display.as_deref().map(csv_field).unwrap_or_default(),
+                        kind.as_deref().map(csv_field).unwrap_or_default(),
+                        row.get::<_, Option<i64>>("line_ts")?.map(|ts| ts.to_string()).unwrap_or_default(),
+                        row.get::<_, Option<String>>("level")?.as_deref().map(csv_field).unwrap_or_default(),
+                        row.get::<_, i64>("repeats")?.to_string(),
+                    ];
+                    writeln!(out, "{}", fields.join(","))?;
+                }
+            }
+            written += 1;
+        }
+        out.flush()?;
+        Ok(written)
+    }
+}
+
+/// Why rows couldn't be written, see `LogStore::export`
+#[derive(Debug, thiserror::Error)]
+enum ExportError {
+    #[error(transparent)]
+    Db(#[from] rusqlite::Error),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// `field` quoted as RFC 4180 has it, if it needs to be
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
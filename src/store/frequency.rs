@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use super::LogStore;
+
+/// Templates `LogStore::frequencies` keeps, the most common ones
+const FREQUENCY_TOP: usize = 100;
+
+/// What changes from one line to the next of the same kind, in the order
+/// they're tried at each char, with what they're replaced with in a
+/// `Template` and the regex its rows are matched with in their place. A
+/// word with a number in it and numbers with dots in between are matched
+/// too, to be told apart from numbers on their own, see `Template::new`.
+static VARYING: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?<uuid>\b[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}\b)|(?<hex>\b0x[0-9a-fA-F]+\b|\b[0-9a-fA-F]{8,}\b)|(?<dotted>\b[0-9]+(?:\.[0-9]+){2,}\b)|(?<word>\b[a-zA-Z_]+[0-9][0-9a-zA-Z_]*(?:\.[0-9]+)*)|(?<number>[0-9]+)")
+        .expect("the pattern is valid")
+});
+
+/// `VARYING`'s groups, their placeholders and what they match
+const PLACEHOLDERS: [(&str, &str, &str); 3] = [
+    ("uuid", "<UUID>", "[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}"),
+    ("hex", "<HEX>", "(?:0x[0-9a-fA-F]+|[0-9a-fA-F]{8,})"),
+    ("number", "<N>", "[0-9]+"),
+];
+
+/// Lines that are the same but for ids and numbers, e.g. `user <N> logged
+/// in from <N>.<N>.<N>.<N>`, see `Template::new`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    /// The line, with placeholders where it varies
+    pub text: String,
+    /// A regex for the whole of each line it stands for
+    pub pattern: String,
+}
+
+impl Template {
+    /// The template `message` is one of: with `normalize`, UUIDs become
+    /// `<UUID>`, hex ids of 8 digits or more (or with `0x` in front) with a
+    /// number in them `<HEX>`, and other runs of digits `<N>`, so that
+    /// `request 4f2a9c1e took 35ms` is `request <HEX> took <N>ms`. Versions,
+    /// `1.2.3` or `v1.2`, and words with a number in them, like `sha256`,
+    /// are kept, as lines that differ in them say different things. Without,
+    /// it's `message` as it is.
+    pub fn new(message: &str, normalize: bool) -> Self {
+        let mut template = Template { text: String::new(), pattern: String::from("^") };
+        let mut last = 0;
+        let varying = normalize.then(|| VARYING.captures_iter(message)).into_iter().flatten();
+        for captures in varying {
+            let found = captures.get(0).expect("there's a whole match");
+            if captures.name("word").is_some() {
+                continue;
+            }
+            // more than three numbers is an address, e.g. 10.0.0.1, with the
+            // dots kept between them
+            if let Some(dotted) = captures.name("dotted") {
+                if dotted.as_str().split('.').count() == 3 {
+                    continue;
+                }
+                template.push_literal(&message[last..found.start()]);
+                for (i, _) in dotted.as_str().split('.').enumerate() {
+                    if i > 0 {
+                        template.push_literal(".");
+                    }
+                    template.text.push_str("<N>");
+                    template.pattern.push_str("[0-9]+");
+                }
+                last = found.end();
+                continue;
+            }
+            let (placeholder, pattern) = PLACEHOLDERS.iter()
+                .find(|(group, _, _)| captures.name(group).is_some())
+                .map(|(_, placeholder, pattern)| (*placeholder, *pattern))
+                .expect("one of the groups matched");
+            // hex without a digit is more likely a word, e.g. `deadbeef`
+            if placeholder == "<HEX>" && !found.as_str().contains(|c: char| c.is_ascii_digit()) {
+                continue;
+            }
+            template.push_literal(&message[last..found.start()]);
+            template.text.push_str(placeholder);
+            template.pattern.push_str(pattern);
+            last = found.end();
+        }
+        template.push_literal(&message[last..]);
+        template.pattern.push('$');
+        template
+    }
+
+    fn push_literal(&mut self, literal: &str) {
+        self.text.push_str(literal);
+        self.pattern.push_str(&regex::escape(literal));
+    }
+}
+
+/// How often lines of a `Template` came, see `LogStore::frequencies`
+pub struct Frequency {
+    pub template: Template,
+    pub count: u64,
+    /// When the first and the last of them were logged, or read if that's
+    /// not known, in ms since the epoch
+    pub first: i64,
+    pub last: i64,
+}
+
+impl LogStore {
+    /// The templates of the rows `pane` shows from `since` on, by when
+    /// they were logged or else read, the most common first, and how many
+    /// rows there were in all. Events aren't counted.
+    pub fn frequencies(&self, pane: usize, since: Option<i64>, normalize: bool) -> rusqlite::Result<(u64, Vec<Frequency>)> {
+        let sql = format!(
+            "SELECT coalesce(line_ts, ts), coalesce(display, message) FROM log WHERE {} AND coalesce(line_ts, ts) >= ?",
+            self.filter(pane),
+        );
+        let mut statement = self.conn.prepare(&sql)?;
+        let mut rows = statement.query((since.unwrap_or(i64::MIN),))?;
+        let mut frequencies: HashMap<String, Frequency> = HashMap::new();
+        let mut total = 0;
+        while let Some(row) = rows.next()? {
+            let at: i64 = row.get(0)?;
+            let template = Template::new(row.get_ref(1)?.as_str()?, normalize);
+            total += 1;
+            let frequency = frequencies.entry(template.text.clone())
+                .or_insert(Frequency { template, count: 0, first: at, last: at });
+            frequency.count += 1;
+            frequency.first = frequency.first.min(at);
+            frequency.last = frequency.last.max(at);
+        }
+        let mut frequencies: Vec<Frequency> = frequencies.into_values().collect();
+        frequencies.sort_by(|a, b| b.count.cmp(&a.count).then(b.last.cmp(&a.last)));
+        frequencies.truncate(FREQUENCY_TOP);
+        Ok((total, frequencies))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalized_templates() {
+        for (message, text) in [
+            ("request 4f2a9c1e took 35ms", "request <HEX> took <N>ms"),
+            ("user 42 logged in from 10.0.0.1", "user <N> logged in from <N>.<N>.<N>.<N>"),
+            ("job 550e8400-e29b-41d4-a716-446655440000 done", "job <UUID> done"),
+            ("JOB 550E8400-E29B-41D4-A716-446655440000 DONE", "JOB <UUID> DONE"),
+            ("fault at 0x7ffd5a2c, flags 0x1f", "fault at <HEX>, flags <HEX>"),
+            ("commit 9fceb02d0ae598e95dc970b74767f19372d61af8", "commit <HEX>"),
+            ("took 1.5s, 3 retries", "took <N>.<N>s, <N> retries"),
+            ("GET /api/items/977?page=2 200", "GET /api/items/<N>?page=<N> <N>"),
+            ("listening on [::1]:8080", "listening on [::<N>]:<N>"),
+            // kept, see the must-not-merge cases below
+            ("filewatch 1.25.3 started", "filewatch 1.25.3 started"),
+            ("upgraded to v2.1", "upgraded to v2.1"),
+            ("checksum sha256 ok, utf8 input", "checksum sha256 ok, utf8 input"),
+            ("cache deadbeef faced a decade of feedback", "cache deadbeef faced a decade of feedback"),
+            ("no numbers here", "no numbers here"),
+            ("", ""),
+        ] {
+            let template = Template::new(message, true);
+            assert_eq!(template.text, text, "{:?}", message);
+            // each line is one of its own template
+            assert!(Regex::new(&template.pattern).unwrap().is_match(message), "{:?} isn't matched by {}", message, template.pattern);
+        }
+    }
+
+    #[test]
+    fn lines_that_differ_in_what_varies_merge() {
+        for (a, b) in [
+            ("request 4f2a9c1e took 35ms", "request 0b7d33a1 took 1200ms"),
+            ("user 42 logged in from 10.0.0.1", "user 7 logged in from 192.168.100.23"),
+            ("job 550e8400-e29b-41d4-a716-446655440000 done", "job 6ba7b810-9dad-11d1-80b4-00c04fd430c8 done"),
+            ("fault at 0x7ffd5a2c", "fault at 0x1"),
+        ] {
+            assert_eq!(Template::new(a, true), Template::new(b, true), "{:?} and {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn lines_that_say_different_things_do_not_merge() {
+        for (a, b) in [
+            // versions
+            ("filewatch 1.25.3 started", "filewatch 1.26.0 started"),
+            ("upgraded to v2.1", "upgraded to v3.0"),
+            ("using python3.11", "using python3.12"),
+            // words with hex letters, or a number, in them
+            ("cache deadbeef", "cache cafebabe"),
+            ("record faced", "record added"),
+            ("hash sha256 ok", "hash sha1 ok"),
+            ("listening on ipv4", "listening on ipv6"),
+            ("disk sda1 full", "disk sdb1 full"),
+            // the same numbers, in different words
+            ("read 3 rows", "wrote 3 rows"),
+        ] {
+            assert_ne!(Template::new(a, true).text, Template::new(b, true).text, "{:?} and {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn not_normalized_is_the_line_as_it_is() {
+        let message = "request 4f2a9c1e took 35ms (v1.2, [x])";
+        let template = Template::new(message, false);
+        assert_eq!(template.text, message);
+        assert_eq!(template.pattern, format!("^{}$", regex::escape(message)));
+    }
+}
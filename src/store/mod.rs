@@ -0,0 +1,637 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::Arc;
+
+use filewatch_rs::file_watch::Level;
+use context::Context;
+use regex::Regex;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::{Connection, DatabaseName, OpenFlags, OptionalExtension};
+
+mod context;
+mod export;
+mod frequency;
+mod search;
+mod thread;
+
+pub use export::ExportFormat;
+pub use frequency::{Frequency, Template};
+pub use search::{SearchMode, CREATE_FTS};
+pub use thread::{spawn, Request, StoreOptions, Update};
+
+/// Rows queried at a time, the window the screen scrolls through, see
+/// `ui::App::wanted_window`
+pub const WINDOW_ROWS: usize = 2000;
+
+/// Panes the screen can be split into, each showing the rows past a
+/// `Filter` of its own, see `ui::App::toggle_split`
+pub const PANES: usize = 2;
+
+/// Which rows to query for the screen, see `LogStore::window`
+pub enum Window {
+    /// The last ones, to follow new rows
+    Last,
+    /// From this row number on
+    From(usize),
+    /// Around the row that's at this `ui::LogLine::order`, as rows before
+    /// it may have come or gone
+    Around((i64, i64)),
+}
+
+/// A row to be stored, see `create_tables` in main for what the columns
+/// mean. Rows with a `kind` are events, which go in the `event` table.
+pub struct NewRow {
+    pub ts: i64,
+    pub file_id: String,
+    pub message: String,
+    pub display: Option<String>,
+    pub kind: Option<&'static str>,
+    pub line_ts: Option<i64>,
+    pub level: Option<&'static str>,
+}
+
+/// A stored row, as it's shown
+pub struct Row {
+    pub id: i64,
+    pub ts: i64,
+    pub file_id: String,
+    /// `display` where there is one, otherwise the raw message
+    pub message: String,
+    pub kind: Option<String>,
+    pub level: Option<String>,
+    pub repeats: u64,
+    pub line_ts: Option<i64>,
+    /// Rows left out just before it by the pane's context, see
+    /// `LogStore::set_context`
+    pub skipped: usize,
+}
+
+/// The log table, which the UI reads a window of rows at a time so that
+/// only what's on screen is kept in memory, however many rows there are.
+/// Events are kept in a table of their own and shown in between, ids are
+/// shared between the two so that they still go in the order they came.
+///
+/// Rows are numbered from 0 in the order they're shown, counting only the
+/// ones that get past the pane's `Filter`, and events if they're shown.
+pub struct LogStore {
+    conn: Connection,
+    /// Order rows by `coalesce(line_ts, ts), id` instead of by `id`
+    order_by_line_ts: bool,
+    /// What each pane shows, by its index
+    filters: [Filter; PANES],
+    /// Id of the last row in `log_fts`, the full-text index, if there is
+    /// one, see `index_rows`
+    indexed_up_to: Option<i64>,
+    /// `log_fts` has every row, so rows are added to it as they're inserted
+    index_complete: bool,
+    /// Show events, see `set_show_events`
+    show_events: bool,
+    /// The id the next row or event gets
+    next_id: i64,
+}
+
+/// The rows a pane shows, and how many there are
+#[derive(Default)]
+struct Filter {
+    min_level: Option<Level>,
+    /// Files whose rows and events are hidden, see `LogStore::set_hidden_files`
+    hidden_files: BTreeSet<String>,
+    /// Only this file's rows and events are shown, see `LogStore::set_file`
+    file: Option<String>,
+    /// Only rows this matches all of are shown, see `LogStore::set_template`
+    template: Option<Regex>,
+    /// Only the rows around those a pattern matches are shown of the rest,
+    /// see `LogStore::set_context`
+    context: Option<Context>,
+    /// Rows that get past the filter, kept up to date on insert so that it
+    /// doesn't take a scan of the table every tick
+    count: usize,
+}
+
+impl LogStore {
+    pub fn new(conn: Connection, order_by_line_ts: bool) -> rusqlite::Result<Self> {
+        // a session opened read-only, see `sessions show`, goes without
+        if order_by_line_ts && !conn.is_readonly(DatabaseName::Main)? {
+            conn.execute("CREATE INDEX IF NOT EXISTS log_line_ts ON log (coalesce(line_ts, ts), id)", ())?;
+        }
+        add_regexp(&conn)?;
+        let mut store = LogStore {
+            conn,
+            order_by_line_ts,
+            filters: Default::default(),
+            indexed_up_to: None,
+            index_complete: false,
+            show_events: true,
+            next_id: 0,
+        };
+        store.next_id = store.last_id()? + 1;
+        store.count_all()?;
+        store.check_index()?;
+        Ok(store)
+    }
+
+    /// The rows of `window` that `pane` shows, with the row number they
+    /// start at and how many rows it shows
+    pub fn window(&self, pane: usize, window: Window) -> rusqlite::Result<(usize, Vec<Row>, usize)> {
+        let count = self.shown_count(pane);
+        let start = match window {
+            Window::Last => count.saturating_sub(WINDOW_ROWS),
+            Window::From(start) => start,
+            Window::Around(order) => self.context_number(pane, self.position(pane, order)?).saturating_sub(WINDOW_ROWS / 2),
+        };
+        if let Some((rows, count)) = self.context_rows(pane, start, WINDOW_ROWS)? {
+            return Ok((start, rows, count));
+        }
+        Ok((start, self.rows(pane, start, WINDOW_ROWS)?, count))
+    }
+
+    /// Rows `pane` shows from `offset` on, at most `limit` of them
+    fn rows(&self, pane: usize, offset: usize, limit: usize) -> rusqlite::Result<Vec<Row>> {
+        let (order, descending) = self.order();
+        let count = self.filters[pane].count;
+        if offset.saturating_add(limit) < count {
+            let sql = format!("{} ORDER BY {} LIMIT ? OFFSET ?", self.select_shown(pane), order);
+            return self.conn.prepare_cached(&sql)?.query_map((limit, offset), row)?.collect();
+        }
+        // counted back from the end, which doesn't step over every row before
+        // them like an offset does, as this is what's asked for while following
+        let sql = format!("{} ORDER BY {} LIMIT ?", self.select_shown(pane), descending);
+        let mut rows = self.conn.prepare_cached(&sql)?
+            .query_map((count.saturating_sub(offset),), row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows.reverse();
+        Ok(rows)
+    }
+
+    /// How many rows `pane` shows before one at `order`, see
+    /// `ui::LogLine::order`. The row doesn't have to exist anymore.
+    fn position(&self, pane: usize, order: (i64, i64)) -> rusqlite::Result<usize> {
+        let (sql, params) = if self.order_by_line_ts {
+            ("(coalesce(line_ts, ts), id) < (?1, ?2)", order)
+        } else {
+            ("id < ?2", order)
+        };
+        let sql = format!(
+            "SELECT (SELECT count(*) FROM log WHERE {} AND {sql}) + (SELECT count(*) FROM event WHERE {} AND {sql})",
+            self.filter(pane),
+            self.event_filter(pane),
+        );
+        self.conn.prepare_cached(&sql)?.query_row(params, |row| row.get(0))
+    }
+
+    /// Hide rows less severe than `min_level` in `pane`, see
+    /// `ui::App::cycle_min_level`
+    pub fn set_min_level(&mut self, pane: usize, min_level: Option<Level>) -> rusqlite::Result<()> {
+        self.filters[pane].min_level = min_level;
+        self.filters[pane].count = self.count_shown(pane)?;
+        self.find_groups(pane)
+    }
+
+    /// Show events in between rows, or leave them out, see `ui::App::show_events`
+    pub fn set_show_events(&mut self, show_events: bool) -> rusqlite::Result<()> {
+        self.show_events = show_events;
+        self.count_all()
+    }
+
+    /// Hide the rows and events of `hidden_files` in `pane`, by file id, see
+    /// `ui::App::toggle_file`
+    pub fn set_hidden_files(&mut self, pane: usize, hidden_files: BTreeSet<String>) -> rusqlite::Result<()> {
+        self.filters[pane].hidden_files = hidden_files;
+        self.filters[pane].count = self.count_shown(pane)?;
+        self.find_groups(pane)
+    }
+
+    /// Only show the rows and events of `file` in `pane`, by file id, or
+    /// those of all files again, see `ui::App::switch_tab`
+    pub fn set_file(&mut self, pane: usize, file: Option<String>) -> rusqlite::Result<()> {
+        self.filters[pane].file = file;
+        self.filters[pane].count = self.count_shown(pane)?;
+        self.find_groups(pane)
+    }
+
+    /// Only show the rows of `template` in `pane`, or all of them again,
+    /// see `ui::App::set_template`
+    pub fn set_template(&mut self, pane: usize, template: Option<&Template>) -> rusqlite::Result<()> {
+        // the pattern is the template's text escaped, with what
+        // `Template::new` puts placeholders in for in between
+        self.filters[pane].template = template.map(|template| Regex::new(&template.pattern).expect("a template's pattern is valid"));
+        self.filters[pane].count = self.count_shown(pane)?;
+        self.find_groups(pane)
+    }
+
+    /// Store `row`, returning its id
+    pub fn insert(&mut self, row: &NewRow) -> rusqlite::Result<i64> {
+        let id = self.next_id;
+        if let Some(kind) = row.kind {
+            self.conn
+                .prepare_cached("INSERT INTO event (id, ts, file_id, kind, detail, line_ts) VALUES (?, ?, ?, ?, ?, ?)")?
+                .execute((id, row.ts, &row.file_id, kind, &row.message, row.line_ts))?;
+            self.next_id += 1;
+            if self.show_events {
+                for pane in 0..PANES {
+                    if self.filters[pane].shows_file(&row.file_id) {
+                        self.filters[pane].count += 1;
+                        self.add_to_context(pane, None)?;
+                    }
+                }
+            }
+            return Ok(id);
+        }
+        self.conn
+            .prepare_cached("INSERT INTO log (id, ts, file_id, message, display, line_ts, level) VALUES (?, ?, ?, ?, ?, ?, ?)")?
+            .execute((id, row.ts, &row.file_id, &row.message, &row.display, row.line_ts, row.level))?;
+        self.next_id += 1;
+        let message = row.display.as_ref().unwrap_or(&row.message);
+        if self.index_complete {
+            self.conn.prepare_cached("INSERT INTO log_fts (rowid, message) VALUES (?, ?)")?.execute((id, message))?;
+        }
+        for pane in 0..PANES {
+            if self.filters[pane].shows(&row.file_id, row.level, message) {
+                self.filters[pane].count += 1;
+                self.add_to_context(pane, Some(message))?;
+            }
+        }
+        Ok(id)
+    }
+
+    /// Count a repeat of the row with `id`, see `Args::collapse_repeats`
+    pub fn count_repeat(&self, id: i64) -> rusqlite::Result<()> {
+        self.conn.prepare_cached("UPDATE log SET repeats = repeats + 1 WHERE id = ?")?.execute((id,))?;
+        Ok(())
+    }
+
+    /// Start writing rows that are only stored on `commit`, which saves
+    /// syncing to disk for every one of them
+    pub fn begin(&mut self) -> rusqlite::Result<()> {
+        self.conn.execute_batch("BEGIN")
+    }
+
+    pub fn commit(&mut self) -> rusqlite::Result<()> {
+        self.conn.execute_batch("COMMIT")?;
+        self.refresh_stale_contexts()
+    }
+
+    /// Undo everything since `begin`
+    pub fn rollback(&mut self) -> rusqlite::Result<()> {
+        // a failed statement may have rolled back already
+        if !self.conn.is_autocommit() {
+            self.conn.execute_batch("ROLLBACK")?;
+        }
+        self.next_id = self.last_id()? + 1;
+        self.count_all()
+    }
+
+    /// How many rows there are, shown or not, not counting events
+    pub fn stored_rows(&self) -> rusqlite::Result<u64> {
+        self.conn.query_row("SELECT count(*) FROM log", (), |row| row.get(0))
+    }
+
+    /// Delete the `count` oldest rows, by when they were stored, and the
+    /// events from before them. Returns how many rows were deleted.
+    pub fn delete_oldest(&mut self, count: u64) -> rusqlite::Result<usize> {
+        // counted from the start, so only the rows to delete are stepped over
+        let up_to_id = self.conn
+            .query_row("SELECT id FROM log ORDER BY id LIMIT 1 OFFSET ?", (count.saturating_sub(1),), |row| row.get(0))
+            .optional()?;
+        match up_to_id {
+            Some(up_to_id) => self.delete_up_to(up_to_id),
+            None => Ok(0),
+        }
+    }
+
+    /// Delete rows and events stored before `ts`. Returns how many rows were
+    /// deleted.
+    pub fn delete_before(&mut self, ts: i64) -> rusqlite::Result<usize> {
+        // rows are stored in the order they're read, so this only steps over
+        // the ones to delete
+        let first_kept: Option<i64> = self.conn.query_row(
+            "SELECT min(id) FROM (
+                SELECT * FROM (SELECT id FROM log WHERE ts >= ?1 ORDER BY id LIMIT 1)
+                UNION ALL SELECT * FROM (SELECT id FROM event WHERE ts >= ?1 ORDER BY id LIMIT 1)
+            )",
+            (ts,),
+            |row| row.get(0),
+        )?;
+        match first_kept {
+            Some(first_kept) => self.delete_up_to(first_kept - 1),
+            None => self.delete_up_to(i64::MAX),
+        }
+    }
+
+    /// Give the pages freed by deleting rows back to the filesystem
+    pub fn vacuum(&self) -> rusqlite::Result<()> {
+        self.conn.execute_batch("PRAGMA incremental_vacuum")
+    }
+
+    pub fn close(self) -> rusqlite::Result<()> {
+        self.conn.close().map_err(|(_, e)| e)
+    }
+
+    fn delete_up_to(&mut self, id: i64) -> rusqlite::Result<usize> {
+        let mut shown = [0; PANES];
+        for pane in 0..PANES {
+            if let Some(same) = self.same_filter_before(pane) {
+                shown[pane] = shown[same];
+                continue;
+            }
+            let sql = format!(
+                "SELECT (SELECT count(*) FROM log WHERE {} AND id <= ?1) + (SELECT count(*) FROM event WHERE {} AND id <= ?1)",
+                self.filter(pane),
+                self.event_filter(pane),
+            );
+            shown[pane] = self.conn.prepare_cached(&sql)?.query_row((id,), |row| row.get(0))?;
+        }
+        let deleted = self.conn.execute("DELETE FROM log WHERE id <= ?", (id,))?;
+        self.conn.execute("DELETE FROM event WHERE id <= ?", (id,))?;
+        if self.indexed_up_to.is_some() {
+            self.conn.execute("DELETE FROM log_fts WHERE rowid <= ?", (id,))?;
+        }
+        for (filter, shown) in self.filters.iter_mut().zip(shown) {
+            filter.count -= shown;
+            if let Some(context) = &mut filter.context {
+                context.forget(shown);
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// The value of a setting kept in the session, see `sessions::CREATE_TABLES`
+    pub fn setting(&self, name: &str) -> rusqlite::Result<Option<String>> {
+        self.conn.query_row("SELECT value FROM setting WHERE name = ?", (name,), |row| row.get(0)).optional()
+    }
+
+    pub fn set_setting(&self, name: &str, value: &str) -> rusqlite::Result<()> {
+        self.conn.execute("INSERT OR REPLACE INTO setting (name, value) VALUES (?, ?)", (name, value))?;
+        Ok(())
+    }
+
+    fn count_shown(&self, pane: usize) -> rusqlite::Result<usize> {
+        let sql = format!(
+            "SELECT (SELECT count(*) FROM log WHERE {}) + (SELECT count(*) FROM event WHERE {})",
+            self.filter(pane),
+            self.event_filter(pane),
+        );
+        self.conn.query_row(&sql, (), |row| row.get(0))
+    }
+
+    /// Count the rows each pane shows again, see `Filter::count`, and find
+    /// those of their contexts
+    fn count_all(&mut self) -> rusqlite::Result<()> {
+        for pane in 0..PANES {
+            self.filters[pane].count = match self.same_filter_before(pane) {
+                Some(same) => self.filters[same].count,
+                None => self.count_shown(pane)?,
+            };
+            self.find_groups(pane)?;
+        }
+        Ok(())
+    }
+
+    /// A pane before `pane` that shows the same rows, so that counting them
+    /// again can be skipped, as most of the time there's only the one pane
+    fn same_filter_before(&self, pane: usize) -> Option<usize> {
+        let filter = &self.filters[pane];
+        let template = filter.template.as_ref().map(Regex::as_str);
+        (0..pane).find(|other| {
+            let other = &self.filters[*other];
+            other.min_level == filter.min_level
+                && other.hidden_files == filter.hidden_files
+                && other.file == filter.file
+                && other.template.as_ref().map(Regex::as_str) == template
+        })
+    }
+
+    /// The highest id of a row or event, or 0 if there are none
+    fn last_id(&self) -> rusqlite::Result<i64> {
+        self.conn.query_row("SELECT max(coalesce((SELECT max(id) FROM log), 0), coalesce((SELECT max(id) FROM event), 0))", (), |row| row.get(0))
+    }
+
+    /// The rows and events `pane` shows, as one query, without an order
+    fn select_shown(&self, pane: usize) -> String {
+        format!("{} WHERE {} UNION ALL {} WHERE {}", SELECT_ROWS, self.filter(pane), SELECT_EVENTS, self.event_filter(pane))
+    }
+
+    /// ORDER BY terms for the order rows are shown in, and the reverse.
+    /// `sort_ts` is `coalesce(line_ts, ts)`, which a query has to select.
+    fn order(&self) -> (&'static str, &'static str) {
+        if self.order_by_line_ts {
+            ("sort_ts, id", "sort_ts DESC, id DESC")
+        } else {
+            ("id", "id DESC")
+        }
+    }
+
+    /// SQL for rows `pane` shows, see `Filter::shows`
+    fn filter(&self, pane: usize) -> String {
+        self.filters[pane].sql()
+    }
+
+    /// SQL for events `pane` shows, which the level filter doesn't hide
+    fn event_filter(&self, pane: usize) -> String {
+        if !self.show_events {
+            return String::from("0");
+        }
+        self.filters[pane].file_sql().unwrap_or_else(|| String::from("1"))
+    }
+}
+
+impl Filter {
+    /// SQL for rows that get past the level filter, aren't from a hidden
+    /// file and are of the template if there is one, the same as `shows`
+    fn sql(&self) -> String {
+        let hidden: Vec<String> = Level::ALL.iter()
+            .filter(|level| self.min_level.is_some_and(|min_level| **level < min_level))
+            .map(|level| format!("'{}'", level.as_str()))
+            .collect();
+        let mut filters = vec![];
+        if !hidden.is_empty() {
+            filters.push(format!("coalesce(level, 'INFO') NOT IN ({})", hidden.join(", ")));
+        }
+        filters.extend(self.file_sql());
+        if let Some(template) = &self.template {
+            filters.push(format!("coalesce(display, message) REGEXP '{}'", template.as_str().replace('\'', "''")));
+        }
+        if filters.is_empty() {
+            return String::from("1");
+        }
+        filters.join(" AND ")
+    }
+
+    /// SQL for rows and events that aren't from a hidden file, and are
+    /// from the one file shown if there is one, if either is the case
+    fn file_sql(&self) -> Option<String> {
+        let quoted = |file_id: &str| format!("'{}'", file_id.replace('\'', "''"));
+        let mut filters = vec![];
+        if !self.hidden_files.is_empty() {
+            let hidden: Vec<String> = self.hidden_files.iter().map(|file_id| quoted(file_id)).collect();
+            filters.push(format!("file_id NOT IN ({})", hidden.join(", ")));
+        }
+        if let Some(file) = &self.file {
+            filters.push(format!("file_id = {}", quoted(file)));
+        }
+        (!filters.is_empty()).then(|| filters.join(" AND "))
+    }
+
+    /// Whether rows and events from `file_id` get past the file filters
+    fn shows_file(&self, file_id: &str) -> bool {
+        !self.hidden_files.contains(file_id) && self.file.as_ref().is_none_or(|file| file == file_id)
+    }
+
+    /// Whether a row from `file_id` with `message` gets past the level
+    /// filter, the file filters and the template, lines without a level
+    /// count as INFO
+    fn shows(&self, file_id: &str, level: Option<&str>, message: &str) -> bool {
+        if !self.shows_file(file_id) || self.template.as_ref().is_some_and(|template| !template.is_match(message)) {
+            return false;
+        }
+        let Some(min_level) = self.min_level else {
+            return true;
+        };
+        level.and_then(|level| level.parse().ok()).unwrap_or(Level::Info) >= min_level
+    }
+}
+
+/// Open the database at `path` read-only, without changing it in any way,
+/// e.g. for a session that's only shown or exported
+pub fn open_read_only(path: &Path) -> rusqlite::Result<Connection> {
+    let flags = (OpenFlags::default() - OpenFlags::SQLITE_OPEN_CREATE - OpenFlags::SQLITE_OPEN_READ_WRITE)
+        | OpenFlags::SQLITE_OPEN_READ_ONLY;
+    Connection::open_with_flags(path, flags)
+}
+
+/// `text REGEXP pattern` in SQL, which SQLite leaves to the application.
+/// Each pattern is compiled once per statement.
+fn add_regexp(conn: &Connection) -> rusqlite::Result<()> {
+    conn.create_scalar_function("regexp", 2, FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC, |ctx| {
+        let pattern: Arc<Regex> = ctx.get_or_create_aux(0, |pattern| -> Result<_, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(Regex::new(pattern.as_str()?)?)
+        })?;
+        let text = ctx.get_raw(1).as_str().map_err(|e| rusqlite::Error::UserFunctionError(e.into()))?;
+        Ok(pattern.is_match(text))
+    })
+}
+
+/// Events: what happened to a source, like a file being rotated, rather than
+/// lines read from it. `kind` is `SourceEvent::kind`, `detail` what's shown,
+/// and `line_ts` the timestamp of the line before, to go by it when rows are
+/// ordered by theirs, see `create_tables` in main.
+pub const CREATE_EVENTS: &str = "CREATE TABLE IF NOT EXISTS event ( id INTEGER PRIMARY KEY, ts INTEGER NOT NULL, file_id TEXT NOT NULL, kind TEXT NOT NULL, detail TEXT NOT NULL, line_ts INTEGER )";
+
+const SELECT_ROWS: &str = "SELECT id, ts, file_id, coalesce(display, message) AS message, NULL AS kind, level, repeats, line_ts, coalesce(line_ts, ts) AS sort_ts FROM log";
+
+const SELECT_EVENTS: &str = "SELECT id, ts, file_id, detail, kind, NULL, 0, line_ts, coalesce(line_ts, ts) FROM event";
+
+fn row(row: &rusqlite::Row) -> rusqlite::Result<Row> {
+    Ok(Row {
+        id: row.get("id")?,
+        ts: row.get("ts")?,
+        file_id: row.get("file_id")?,
+        message: row.get("message")?,
+        kind: row.get("kind")?,
+        level: row.get("level")?,
+        repeats: row.get("repeats")?,
+        line_ts: row.get("line_ts")?,
+        skipped: 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    fn row(n: usize) -> NewRow {
+        NewRow {
+            ts: n as i64,
+            file_id: String::from("app.log"),
+            message: format!("line {} GET /api/items/{} 200 in {}ms", n, n % 977, n % 131),
+            display: None,
+            kind: None,
+            line_ts: None,
+            level: None,
+        }
+    }
+
+    #[test]
+    fn export_runs_on_a_reader_beside_writes() {
+        let memory = LogStore::new(crate::open_memory_db().unwrap(), false).unwrap();
+        assert!(memory.reader().unwrap().is_none());
+
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("filewatch-test-{}-export.db", std::process::id()));
+        let out = dir.join(format!("filewatch-test-{}-export.txt", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let conn = Connection::open(&path).unwrap();
+        conn.pragma_update(None, "journal_mode", "WAL").unwrap();
+        crate::create_tables(&conn).unwrap();
+        let mut store = LogStore::new(conn, false).unwrap();
+        store.begin().unwrap();
+        for n in 0..3 {
+            store.insert(&row(n)).unwrap();
+        }
+        store.commit().unwrap();
+        let query = store.export_query(0);
+        let reader = store.reader().unwrap().unwrap();
+
+        // neither held up by a write that's under way nor taking its rows,
+        // even once they're committed
+        store.begin().unwrap();
+        store.insert(&row(3)).unwrap();
+        assert_eq!(query.run(&reader, &out, ExportFormat::Text), Ok(3));
+        store.commit().unwrap();
+        assert_eq!(query.run(&reader, &out, ExportFormat::Text), Ok(3));
+        let exported = std::fs::read_to_string(&out).unwrap();
+        assert!(exported.lines().zip(0..).all(|(line, n)| line.starts_with(&format!("app.log: line {} ", n))), "{}", exported);
+        assert_eq!(store.export(0, &out, ExportFormat::Text), Ok(4));
+
+        drop((reader, store));
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&out);
+    }
+
+    /// What a tick costs with a few hundred thousand rows stored: the new
+    /// rows written and the window of the last ones queried, as the UI does
+    /// while following, against reading the whole table as it did before
+    /// the window. Timed rather than checked, run it with
+    /// `cargo test --release -- --ignored --nocapture tick_cost`.
+    #[test]
+    #[ignore]
+    fn tick_cost() {
+        let conn = crate::open_memory_db().unwrap();
+        let mut store = LogStore::new(conn, false).unwrap();
+        let mut stored = 0;
+        for total in [100_000, 300_000, 500_000] {
+            store.begin().unwrap();
+            while stored < total {
+                store.insert(&row(stored)).unwrap();
+                stored += 1;
+            }
+            store.commit().unwrap();
+
+            let ticks = 20;
+            let start = Instant::now();
+            for _ in 0..ticks {
+                store.begin().unwrap();
+                for _ in 0..100 {
+                    store.insert(&row(stored)).unwrap();
+                    stored += 1;
+                }
+                store.commit().unwrap();
+                let (_, rows, _) = store.window(0, Window::Last).unwrap();
+                assert_eq!(rows.len(), WINDOW_ROWS);
+            }
+            let windowed = start.elapsed() / ticks;
+
+            let start = Instant::now();
+            for _ in 0..ticks {
+                let lines: Vec<String> = store.conn.prepare("SELECT file_id, message FROM log").unwrap()
+                    .query_map((), |row| Ok(format!("{} {}", row.get::<_, String>(0)?, row.get::<_, String>(1)?))).unwrap()
+                    .collect::<rusqlite::Result<_>>().unwrap();
+                assert!(lines.len() >= total);
+            }
+            let whole_table = start.elapsed() / ticks;
+            println!("{:>7} rows: {:?} a tick with 100 new rows and the window, {:?} reading every row", stored, windowed, whole_table);
+        }
+    }
+}
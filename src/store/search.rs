@@ -0,0 +1,195 @@
+use regex::{Regex, RegexBuilder};
+use rusqlite::OptionalExtension;
+
+use super::LogStore;
+
+/// How `LogStore::search` matches rows
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SearchMode {
+    /// The query is somewhere in the row, ignoring case unless it has
+    /// capitals in it
+    #[default]
+    Text,
+    /// The query is a regular expression that matches part of the row
+    Regex,
+    /// The query is FTS5 syntax, matching whole words with the full-text
+    /// index, which is the quickest in a big session once it's built
+    Words,
+}
+
+impl SearchMode {
+    /// The mode Ctrl-r in the search prompt switches to
+    pub fn next(self) -> Self {
+        match self {
+            SearchMode::Text => SearchMode::Regex,
+            SearchMode::Regex => SearchMode::Words,
+            SearchMode::Words => SearchMode::Text,
+        }
+    }
+
+    /// A regex for the parts of a row `query` matches, to show where they
+    /// are, see `ui::App::set_matches`. For `Words` it's the words and
+    /// phrases of the query, ignoring case, whatever operators join them.
+    /// The error says what's wrong with the query, for showing as is.
+    pub fn matcher(self, query: &str) -> Result<Regex, String> {
+        let (pattern, ignore_case) = match self {
+            SearchMode::Text => (regex::escape(query), !query.chars().any(char::is_uppercase)),
+            SearchMode::Regex => (query.to_owned(), false),
+            SearchMode::Words => {
+                let terms: Vec<String> = fts_terms(query).iter().map(|term| regex::escape(term)).collect();
+                (terms.join("|"), true)
+            }
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl LogStore {
+    /// Whether `search` can be run yet, or `index_rows` has to catch up first
+    pub fn index_complete(&self) -> bool {
+        self.index_complete
+    }
+
+    /// Add the next `limit` rows to the full-text index, creating it if
+    /// there isn't one, e.g. in a database from before there was search.
+    /// Rows inserted in the meantime are picked up too, as they come after.
+    pub fn index_rows(&mut self, limit: usize) -> rusqlite::Result<()> {
+        if self.indexed_up_to.is_none() {
+            self.conn.execute_batch(CREATE_FTS)?;
+            self.indexed_up_to = Some(0);
+        }
+        let after = self.indexed_up_to.unwrap_or(0);
+        let last: Option<i64> = self.conn
+            .query_row("SELECT max(id) FROM (SELECT id FROM log WHERE id > ? ORDER BY id LIMIT ?)", (after, limit), |row| row.get(0))?;
+        let Some(last) = last else {
+            self.index_complete = true;
+            return Ok(());
+        };
+        self.conn.execute(
+            "INSERT INTO log_fts (rowid, message) SELECT id, coalesce(display, message) FROM log WHERE id > ? AND id <= ?",
+            (after, last),
+        )?;
+        self.indexed_up_to = Some(last);
+        Ok(())
+    }
+
+    /// Rows in the full-text index so far, and rows there are
+    pub fn index_progress(&self) -> rusqlite::Result<(u64, u64)> {
+        self.conn.query_row(
+            "SELECT count(*) FILTER (WHERE id <= ?), count(*) FROM log",
+            (self.indexed_up_to.unwrap_or(0),),
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+    }
+
+    /// The `ui::LogLine::order` of the rows that match `query`, in the
+    /// order they're shown. Only rows `pane` shows are searched, events
+    /// aren't. Text and regex searches go through every row,
+    /// `Words` needs `index_complete`. The error says what went wrong, for
+    /// showing as is.
+    pub fn search(&self, pane: usize, query: &str, mode: SearchMode) -> Result<Vec<(i64, i64)>, String> {
+        let matches = match mode {
+            SearchMode::Words => self.search_words(pane, query),
+            SearchMode::Text | SearchMode::Regex => self.search_rows(pane, &mode.matcher(query)?),
+        };
+        matches.map_err(|e| e.to_string())
+    }
+
+    fn search_rows(&self, pane: usize, matcher: &Regex) -> rusqlite::Result<Vec<(i64, i64)>> {
+        let (order, _) = self.order();
+        let sql = format!(
+            "SELECT coalesce(line_ts, ts) AS sort_ts, id, coalesce(display, message) FROM log WHERE {} ORDER BY {}",
+            self.filter(pane),
+            order,
+        );
+        let mut statement = self.conn.prepare(&sql)?;
+        let mut rows = statement.query(())?;
+        let mut matches = vec![];
+        while let Some(row) = rows.next()? {
+            // borrowed from the row, most of them don't match
+            let text = row.get_ref(2)?.as_str()?;
+            if matcher.is_match(text) {
+                let id = row.get(1)?;
+                matches.push(if self.order_by_line_ts { (row.get(0)?, id) } else { (0, id) });
+            }
+        }
+        Ok(matches)
+    }
+
+    fn search_words(&self, pane: usize, query: &str) -> rusqlite::Result<Vec<(i64, i64)>> {
+        let (order, _) = self.order();
+        let sql = format!(
+            "SELECT coalesce(line_ts, ts) AS sort_ts, id FROM log_fts JOIN log ON log.id = log_fts.rowid WHERE log_fts MATCH ? AND {} ORDER BY {}",
+            self.filter(pane),
+            order,
+        );
+        let order_by_line_ts = self.order_by_line_ts;
+        self.conn.prepare(&sql)?
+            .query_map((fts_query(query),), |row| {
+                let id = row.get(1)?;
+                Ok(if order_by_line_ts { (row.get(0)?, id) } else { (0, id) })
+            })?
+            .collect()
+    }
+
+    /// Pick up where indexing was left, if the index is there
+    pub(super) fn check_index(&mut self) -> rusqlite::Result<()> {
+        let exists = self.conn
+            .query_row("SELECT 1 FROM sqlite_master WHERE name = 'log_fts'", (), |_| Ok(()))
+            .optional()?
+            .is_some();
+        if !exists {
+            return Ok(());
+        }
+        let up_to: i64 = self.conn
+            .query_row("SELECT rowid FROM log_fts ORDER BY rowid DESC LIMIT 1", (), |row| row.get(0))
+            .optional()?
+            .unwrap_or(0);
+        let unindexed: Option<i64> = self.conn
+            .query_row("SELECT id FROM log WHERE id > ? LIMIT 1", (up_to,), |row| row.get(0))
+            .optional()?;
+        self.indexed_up_to = Some(up_to);
+        self.index_complete = unindexed.is_none();
+        Ok(())
+    }
+}
+
+/// The full-text index of what rows show, `coalesce(display, message)`. It
+/// doesn't keep a copy of the text, rows are joined with `log` for that.
+pub const CREATE_FTS: &str = "CREATE VIRTUAL TABLE IF NOT EXISTS log_fts USING fts5(message, content='', contentless_delete=1)";
+
+/// `query` as FTS5 syntax. Words with anything but letters, digits and the
+/// operators in them, like `req-1234` or `/api/users`, are quoted as
+/// phrases, which FTS5 would otherwise reject. A query with quotes of its
+/// own is taken as it is.
+fn fts_query(query: &str) -> String {
+    if query.contains('"') {
+        return query.to_owned();
+    }
+    let operator = |c: char| c.is_alphanumeric() || "_*^()".contains(c);
+    query.split_whitespace()
+        .map(|word| if word.chars().all(operator) { word.to_owned() } else { format!("\"{}\"", word) })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The words and quoted phrases of an FTS5 query, without its operators,
+/// brackets and prefix stars
+fn fts_terms(query: &str) -> Vec<String> {
+    let mut terms = vec![];
+    for (i, part) in query.split('"').enumerate() {
+        if i % 2 == 1 {
+            terms.push(part.to_owned());
+            continue;
+        }
+        let words = part.split(|c: char| c.is_whitespace() || "()^".contains(c))
+            .map(|word| word.trim_end_matches('*'))
+            .filter(|word| !word.is_empty() && !["AND", "OR", "NOT", "NEAR"].contains(word));
+        terms.extend(words.map(str::to_owned));
+    }
+    terms.retain(|term| !term.is_empty());
+    terms
+}
@@ -0,0 +1,676 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
+
+use filewatch_rs::file_watch::{Level, LogsMessage, ReadStats, SourceEvent};
+use regex::Regex;
+
+use super::{ExportFormat, Frequency, LogStore, NewRow, Row, SearchMode, Template, Window};
+
+/// Rows written in one transaction, beyond which the rest of a backlog
+/// waits for the next one, so requests get answered in between
+const MAX_ROWS_PER_WRITE: usize = 50_000;
+
+/// How long to wait for lines before checking for requests again
+const REQUEST_POLL: Duration = Duration::from_millis(10);
+
+/// How long to wait before trying again to write rows that couldn't be
+const RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// Rows added to the full-text index at a time, when a database from
+/// before there was search is searched by words, see `LogStore::index_rows`
+const INDEX_ROWS_PER_STEP: usize = 20_000;
+
+/// How often rows older than `StoreOptions::max_age` are deleted, and the
+/// space freed by deleting rows is given back
+const RETENTION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// What the UI asks the db thread for. Panes are by their index, up to
+/// `PANES`.
+pub enum Request {
+    /// Rows for a pane, answered with `Update::Window`
+    Window { pane: usize, window: Window },
+    /// See `LogStore::set_min_level`
+    MinLevel { pane: usize, min_level: Option<Level> },
+    /// See `StoreOptions::collapse_repeats`
+    CollapseRepeats(bool),
+    /// See `LogStore::set_show_events`
+    ShowEvents(bool),
+    /// See `LogStore::set_hidden_files`
+    HiddenFiles { pane: usize, hidden_files: BTreeSet<String> },
+    /// Keep a setting in the session, see `LogStore::setting`
+    Setting { name: &'static str, value: String },
+    /// Rows a pane shows that match a query, answered with `Update::Matches`
+    Search { pane: usize, query: String, mode: SearchMode },
+    /// Write the rows a pane shows to a file, answered with
+    /// `Update::Exported`
+    Export { pane: usize, path: PathBuf, format: ExportFormat },
+    /// See `LogStore::set_template`
+    Template { pane: usize, template: Option<Template> },
+    /// See `LogStore::set_file`
+    File { pane: usize, file: Option<String> },
+    /// Pick numbers out of lines with these from now on, in place of
+    /// `StoreOptions::graphs`
+    Graphs(Vec<Regex>),
+    /// See `LogStore::set_context`
+    Context { pane: usize, context: Option<(Regex, usize)> },
+    /// The most common lines a pane shows, answered with
+    /// `Update::Frequencies`, see `LogStore::frequencies`
+    Frequencies { pane: usize, since: Option<i64>, normalize: bool },
+}
+
+/// What the db thread tells the UI
+pub enum Update {
+    /// Rows were written, `added` of them, events included. They, or rows
+    /// that were deleted, may have been before the screen if `moved`, see
+    /// `ui::App::wanted_window`
+    Stored {
+        added: u64,
+        moved: bool,
+        /// Ids of rows that were repeated, and their new repeat counts
+        repeats: Vec<(i64, u64)>,
+        /// Lines `--timestamp-format` didn't match, so far
+        unparsed_timestamps: usize,
+        /// Rows deleted to stay under `StoreOptions::max_rows` or
+        /// `StoreOptions::max_age`, so far
+        dropped: u64,
+    },
+    Stats { file_id: String, stats: ReadStats },
+    /// Progress of a file's initial read, see `SourceEvent::Loading`
+    Loading { file_id: String, done: u64, total: u64 },
+    /// The rows asked for with `Request::Window`, which start at row
+    /// number `start` out of the `total` the pane shows
+    Window { pane: usize, start: usize, rows: Vec<Row>, total: usize },
+    /// Progress of building the full-text index before a search can run
+    Indexing { done: u64, total: u64 },
+    /// The `ui::LogLine::order` of the rows that match `query`, asked for
+    /// with `Request::Search`, or why it couldn't be run
+    Matches { query: String, mode: SearchMode, matches: Result<Vec<(i64, i64)>, String> },
+    /// How many rows `Request::Export` wrote, or why it couldn't
+    Exported { path: PathBuf, rows: Result<u64, String> },
+    /// The rows counted for `Request::Frequencies` and their most common
+    /// templates, or why they couldn't be counted
+    Frequencies(Result<(u64, Vec<Frequency>), String>),
+    /// Lines that matched one of `StoreOptions::alerts`, as they were
+    /// stored, after the `Update::Stored` that added them
+    Alerts(Vec<Row>),
+    /// Numbers `StoreOptions::graphs` picked out of lines: the index of the
+    /// regex, when the line was read and the number
+    Values(Vec<(usize, i64, f64)>),
+}
+
+pub struct StoreOptions {
+    /// Store a line that's the same as the one before it from the same file
+    /// as a count on that one instead
+    pub collapse_repeats: bool,
+    /// Start counting again when a repeat comes more than this long after
+    /// the one before
+    pub repeat_gap: Duration,
+    /// Delete the oldest rows beyond this many
+    pub max_rows: Option<u64>,
+    /// Delete rows stored longer ago than this
+    pub max_age: Option<Duration>,
+    /// Rows are ordered by `coalesce(line_ts, ts), id`, so new ones can go
+    /// anywhere
+    pub order_by_line_ts: bool,
+    /// Lines matching any of these are sent back with `Update::Alerts`,
+    /// repeats of them too
+    pub alerts: Vec<Regex>,
+    /// The first group of each of these is parsed as a number in the lines
+    /// they match, and sent back with `Update::Values`, see `ui::Graphs`
+    pub graphs: Vec<Regex>,
+}
+
+/// Store what the watchers send on `rx` in a thread of its own, which
+/// answers `Request`s in between and sends `Update`s as things are stored.
+/// It stops, closing the store, once the returned sender is dropped.
+pub fn spawn<T: From<Update> + Send + 'static>(
+    store: LogStore,
+    rx: Receiver<LogsMessage>,
+    options: StoreOptions,
+    updates: Sender<T>,
+) -> (Sender<Request>, JoinHandle<()>) {
+    let (requests_tx, requests) = mpsc::channel();
+    let handle = std::thread::spawn(move || {
+        // there are already rows if the database isn't new
+        let stored_rows = store.stored_rows()
+            .inspect_err(|err| log::error!("Failed to count rows ({:?}): {:?}", err.sqlite_error_code(), err.sqlite_error()))
+            .unwrap_or(0);
+        let mut writer = Writer {
+            store,
+            options,
+            updates,
+            pending: vec![],
+            last_lines: HashMap::new(),
+            last_line_ts: HashMap::new(),
+            unparsed_timestamps: 0,
+            stored_rows,
+            dropped_rows: 0,
+            last_retained: None,
+            vacuum_due: false,
+            search: None,
+        };
+        writer.run(rx, requests);
+        if let Err(e) = writer.store.close() {
+            log::error!("Failed to close database: {}", e);
+        }
+    });
+    (requests_tx, handle)
+}
+
+struct Writer<T> {
+    store: LogStore,
+    options: StoreOptions,
+    updates: Sender<T>,
+    /// Rows received but not written yet, which are kept to try again if
+    /// writing them fails
+    pending: Vec<NewRow>,
+    /// The last line stored from each file, while repeats are collapsed
+    last_lines: HashMap<String, LastLine>,
+    /// Lines without a timestamp of their own are ordered after the last
+    /// line from the same file that had one
+    last_line_ts: HashMap<String, i64>,
+    unparsed_timestamps: usize,
+    /// Rows in the log, and how many were deleted to stay under `max_rows`
+    /// or `max_age`
+    stored_rows: u64,
+    dropped_rows: u64,
+    /// When rows older than `max_age` were last deleted, see `retain`. Not
+    /// yet at first, so rows that are too old are deleted straight away
+    last_retained: Option<Instant>,
+    /// Rows were deleted since the space they took was last given back
+    vacuum_due: bool,
+    /// A search waiting to be run, and the pane it's for, full-text ones
+    /// until the index is built
+    search: Option<(usize, String, SearchMode)>,
+}
+
+impl<T: From<Update> + Send + 'static> Writer<T> {
+    fn run(&mut self, rx: Receiver<LogsMessage>, requests: Receiver<Request>) {
+        // watchers can all be done, e.g. when only archives are read
+        let mut watching = true;
+        loop {
+            // requests first, they're what someone is waiting on
+            loop {
+                match requests.try_recv() {
+                    Ok(request) => self.handle(request),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        // a last try for rows that couldn't be written
+                        self.write();
+                        return;
+                    }
+                }
+            }
+            // a chunk at a time, so lines keep being stored meanwhile
+            if self.search.is_some() {
+                self.index();
+            }
+            if !watching {
+                if self.search.is_some() {
+                    continue;
+                }
+                match requests.recv_timeout(RETENTION_INTERVAL) {
+                    Ok(request) => self.handle(request),
+                    Err(RecvTimeoutError::Timeout) => self.retain_idle(),
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+                continue;
+            }
+            // rows that couldn't be written last time go first, and no more
+            // are taken until they are, so watchers wait rather than memory
+            // filling up
+            if self.pending.is_empty() {
+                match rx.recv_timeout(REQUEST_POLL) {
+                    Ok(msg) => self.receive(msg),
+                    Err(RecvTimeoutError::Timeout) => {
+                        self.retain_idle();
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => {
+                        watching = false;
+                        continue;
+                    }
+                }
+                while self.pending.len() < MAX_ROWS_PER_WRITE {
+                    let Ok(msg) = rx.try_recv() else {
+                        break;
+                    };
+                    self.receive(msg);
+                }
+            }
+            if !self.write() {
+                std::thread::sleep(RETRY_DELAY);
+            }
+        }
+    }
+
+    fn handle(&mut self, request: Request) {
+        match request {
+            Request::Window { pane, window } => match self.store.window(pane, window) {
+                Ok((start, rows, total)) => self.send(Update::Window { pane, start, rows, total }),
+                Err(err) => log::error!("Failed to query database ({:?}): {:?}", err.sqlite_error_code(), err.sqlite_error()),
+            },
+            Request::MinLevel { pane, min_level } => {
+                if let Err(err) = self.store.set_min_level(pane, min_level) {
+                    log::error!("Failed to filter by level ({:?}): {:?}", err.sqlite_error_code(), err.sqlite_error());
+                }
+            }
+            Request::ShowEvents(show_events) => {
+                if let Err(err) = self.store.set_show_events(show_events) {
+                    log::error!("Failed to filter events ({:?}): {:?}", err.sqlite_error_code(), err.sqlite_error());
+                }
+            }
+            Request::HiddenFiles { pane, hidden_files } => {
+                if let Err(err) = self.store.set_hidden_files(pane, hidden_files) {
+                    log::error!("Failed to filter files ({:?}): {:?}", err.sqlite_error_code(), err.sqlite_error());
+                }
+            }
+            Request::Setting { name, value } => {
+                if let Err(err) = self.store.set_setting(name, &value) {
+                    log::error!("Failed to save setting ({:?}): {:?}", err.sqlite_error_code(), err.sqlite_error());
+                }
+            }
+            Request::CollapseRepeats(collapse_repeats) => {
+                self.options.collapse_repeats = collapse_repeats;
+                self.last_lines.clear();
+            }
+            Request::Search { pane, query, mode } => {
+                self.search = Some((pane, query, mode));
+                self.index();
+            }
+            Request::Export { pane, path, format } => self.export(pane, path, format),
+            Request::Template { pane, template } => {
+                if let Err(err) = self.store.set_template(pane, template.as_ref()) {
+                    log::error!("Failed to filter by template ({:?}): {:?}", err.sqlite_error_code(), err.sqlite_error());
+                }
+            }
+            Request::File { pane, file } => {
+                if let Err(err) = self.store.set_file(pane, file) {
+                    log::error!("Failed to filter by file ({:?}): {:?}", err.sqlite_error_code(), err.sqlite_error());
+                }
+            }
+            Request::Graphs(graphs) => self.options.graphs = graphs,
+            Request::Context { pane, context } => {
+                if let Err(err) = self.store.set_context(pane, context) {
+                    log::error!("Failed to show context ({:?}): {:?}", err.sqlite_error_code(), err.sqlite_error());
+                }
+            }
+            Request::Frequencies { pane, since, normalize } => {
+                let frequencies = self.store.frequencies(pane, since, normalize).map_err(|e| e.to_string());
+                self.send(Update::Frequencies(frequencies));
+            }
+        }
+    }
+
+    /// Write the rows `pane` shows to `path` on a connection and thread of
+    /// their own, so lines keep being stored meanwhile, however many there
+    /// are. An in-memory database is exported here, it has no other.
+    fn export(&self, pane: usize, path: PathBuf, format: ExportFormat) {
+        let reader = match self.store.reader() {
+            Ok(Some(reader)) => reader,
+            Ok(None) => {
+                let rows = self.store.export(pane, &path, format);
+                self.send(Update::Exported { path, rows });
+                return;
+            }
+            Err(err) => {
+                let rows = Err(format!("cannot open database to export from: {}", err));
+                self.send(Update::Exported { path, rows });
+                return;
+            }
+        };
+        let query = self.store.export_query(pane);
+        let updates = self.updates.clone();
+        std::thread::spawn(move || {
+            let rows = query.run(&reader, &path, format);
+            let _ = updates.send(Update::Exported { path, rows }.into());
+        });
+    }
+
+    /// Build the next chunk of the full-text index if the waiting search
+    /// needs it and it isn't complete, then run the search once it is
+    fn index(&mut self) {
+        let words = self.search.as_ref().is_some_and(|(_, _, mode)| *mode == SearchMode::Words);
+        if words && !self.store.index_complete() {
+            let progress = self.store.index_rows(INDEX_ROWS_PER_STEP)
+                .and_then(|_| self.store.index_progress());
+            match progress {
+                Ok((done, total)) => self.send(Update::Indexing { done, total }),
+                Err(err) => {
+                    log::error!("Failed to index rows ({:?}): {:?}", err.sqlite_error_code(), err.sqlite_error());
+                    if let Some((_, query, mode)) = self.search.take() {
+                        self.send(Update::Matches { query, mode, matches: Err(err.to_string()) });
+                    }
+                }
+            }
+            if !self.store.index_complete() {
+                return;
+            }
+        }
+        if let Some((pane, query, mode)) = self.search.take() {
+            let matches = self.store.search(pane, &query, mode);
+            self.send(Update::Matches { query, mode, matches });
+        }
+    }
+
+    /// Turn `msg` into rows to write, or pass it on if it's not stored
+    fn receive(&mut self, msg: LogsMessage) {
+        let ts = unix_millis(msg.received_at);
+        let file_id = msg.file_id;
+        let new_row = |message, display, kind, line_ts, level| NewRow { ts, file_id: file_id.clone(), message, display, kind, line_ts, level };
+        match msg.event {
+            SourceEvent::Stats(stats) => {
+                // only shown while running, not stored
+                self.send(Update::Stats { file_id, stats });
+            }
+            SourceEvent::Loading { done, total } => {
+                // progress for the status bar, not stored either
+                self.send(Update::Loading { file_id, done, total });
+            }
+            SourceEvent::Lines { lines, display, timestamps, levels } => {
+                let mut display = display.map(Vec::into_iter);
+                let mut timestamps = timestamps.map(Vec::into_iter);
+                let rows = lines.into_iter()
+                    .zip(levels)
+                    .map(|(line, level)| {
+                        let shown = display.as_mut()
+                            .and_then(Iterator::next)
+                            .filter(|shown| *shown != line);
+                        let line_ts = match timestamps.as_mut().and_then(Iterator::next) {
+                            Some(Some(line_ts)) => {
+                                self.last_line_ts.insert(file_id.clone(), line_ts);
+                                Some(line_ts)
+                            }
+                            Some(None) => {
+                                self.unparsed_timestamps += 1;
+                                self.last_line_ts.get(&file_id).copied()
+                            }
+                            None => None,
+                        };
+                        new_row(line, shown, None, line_ts, level.map(Level::as_str))
+                    });
+                self.pending.extend(rows);
+            }
+            event => {
+                let line_ts = self.last_line_ts.get(&file_id).copied();
+                self.pending.push(new_row(event.describe(), None, event.kind(), line_ts, None));
+            }
+        }
+    }
+
+    /// Write the pending rows, and delete old ones beyond `max_rows`.
+    /// Returns false if they have to be tried again.
+    fn write(&mut self) -> bool {
+        if self.pending.is_empty() {
+            return true;
+        }
+        let written = match self.write_rows() {
+            Ok(written) => written,
+            Err(err) => {
+                log::error!("Failed to write {} rows to database, trying again ({:?}): {:?}", self.pending.len(), err.sqlite_error_code(), err.sqlite_error());
+                return false;
+            }
+        };
+        self.pending.clear();
+        // events don't count towards `max_rows`
+        self.stored_rows += written.inserted - written.events;
+        // lines ordered by their own timestamps can go anywhere
+        let moved = written.inserted > 0 && self.options.order_by_line_ts;
+        let deleted = self.retain();
+        self.send_stored(written.inserted, moved || deleted, written.repeats);
+        if !written.alerts.is_empty() {
+            self.send(Update::Alerts(written.alerts));
+        }
+        if !written.values.is_empty() {
+            self.send(Update::Values(written.values));
+        }
+        true
+    }
+
+    /// `retain` while there's nothing to write, e.g. for `max_age`
+    fn retain_idle(&mut self) {
+        if self.retain() {
+            self.send_stored(0, true, vec![]);
+        }
+    }
+
+    /// Delete the oldest rows beyond `max_rows`, and every so often those
+    /// older than `max_age`. Returns whether any were deleted.
+    fn retain(&mut self) -> bool {
+        let mut deleted = 0;
+        if let Some(max_rows) = self.options.max_rows.filter(|max_rows| self.stored_rows > *max_rows) {
+            match self.store.delete_oldest(self.stored_rows - max_rows) {
+                Ok(rows) => deleted += rows,
+                Err(err) => log::error!("Failed to drop old lines ({:?}): {:?}", err.sqlite_error_code(), err.sqlite_error()),
+            }
+        }
+        if self.last_retained.is_none_or(|at| at.elapsed() >= RETENTION_INTERVAL) {
+            self.last_retained = Some(Instant::now());
+            let oldest = self.options.max_age.and_then(|max_age| SystemTime::now().checked_sub(max_age));
+            if let Some(oldest) = oldest {
+                match self.store.delete_before(unix_millis(oldest)) {
+                    Ok(rows) => deleted += rows,
+                    Err(err) => log::error!("Failed to drop old lines ({:?}): {:?}", err.sqlite_error_code(), err.sqlite_error()),
+                }
+            }
+            if std::mem::take(&mut self.vacuum_due) {
+                if let Err(err) = self.store.vacuum() {
+                    log::error!("Failed to vacuum ({:?}): {:?}", err.sqlite_error_code(), err.sqlite_error());
+                }
+            }
+        }
+        self.stored_rows -= deleted as u64;
+        self.dropped_rows += deleted as u64;
+        self.vacuum_due |= deleted > 0;
+        deleted > 0
+    }
+
+    fn send_stored(&self, added: u64, moved: bool, repeats: Vec<(i64, u64)>) {
+        self.send(Update::Stored {
+            added,
+            moved,
+            repeats,
+            unparsed_timestamps: self.unparsed_timestamps,
+            dropped: self.dropped_rows,
+        });
+    }
+
+    /// Store the pending rows in one transaction, or none of them. A row
+    /// that's the same as the one before it from the same file is counted
+    /// as a repeat of that one instead, if `collapse_repeats`, which is why
+    /// `last_lines` has to be put back as it was if writing fails.
+    fn write_rows(&mut self) -> rusqlite::Result<Written> {
+        let before = self.last_lines.clone();
+        let repeat_gap = self.options.repeat_gap.as_millis() as i64;
+        let mut written = Written { inserted: 0, events: 0, repeats: vec![], alerts: vec![], values: vec![] };
+        let result = self.store.begin().and_then(|_| {
+            for row in &self.pending {
+                let alert = row.kind.is_none() && self.options.alerts.iter().any(|alert| alert.is_match(&row.message));
+                if row.kind.is_none() {
+                    // repeats too, they're values as much as the first one
+                    let values = self.options.graphs.iter().enumerate()
+                        .filter_map(|(i, graph)| Some((i, row.ts, graph_value(graph, &row.message)?)));
+                    written.values.extend(values);
+                }
+                if row.kind.is_some() {
+                    // something happening to the file breaks a run of repeats
+                    self.last_lines.remove(&row.file_id);
+                    written.events += 1;
+                }
+                else if let Some(last) = self.last_lines.get_mut(&row.file_id)
+                    .filter(|last| last.message == row.message && row.ts - last.seen_at <= repeat_gap) {
+                    last.seen_at = row.ts;
+                    self.store.count_repeat(last.id)?;
+                    last.repeats += 1;
+                    written.repeats.push((last.id, last.repeats));
+                    if alert {
+                        written.alerts.push(stored(row, last.id, last.repeats));
+                    }
+                    continue;
+                }
+                let id = self.store.insert(row)?;
+                written.inserted += 1;
+                if alert {
+                    written.alerts.push(stored(row, id, 0));
+                }
+                if self.options.collapse_repeats && row.kind.is_none() {
+                    self.last_lines.insert(row.file_id.clone(), LastLine { id, message: row.message.clone(), seen_at: row.ts, repeats: 0 });
+                }
+            }
+            self.store.commit()
+        });
+        if let Err(err) = result {
+            self.last_lines = before;
+            if let Err(err) = self.store.rollback() {
+                log::error!("Failed to roll back ({:?}): {:?}", err.sqlite_error_code(), err.sqlite_error());
+            }
+            return Err(err);
+        }
+        Ok(written)
+    }
+
+    fn send(&self, update: Update) {
+        // the UI is gone when it's shutting down, nothing to do about it
+        let _ = self.updates.send(update.into());
+    }
+}
+
+/// What `Writer::write_rows` did
+struct Written {
+    /// Rows inserted, events included
+    inserted: u64,
+    /// Events inserted
+    events: u64,
+    /// Ids of rows that were repeated, and their new repeat counts
+    repeats: Vec<(i64, u64)>,
+    /// Rows that matched `StoreOptions::alerts`, see `Update::Alerts`
+    alerts: Vec<Row>,
+    /// Numbers picked out of rows, see `Update::Values`
+    values: Vec<(usize, i64, f64)>,
+}
+
+/// The number in the first group of `graph` in `message`, if it matches and
+/// it's one
+fn graph_value(graph: &Regex, message: &str) -> Option<f64> {
+    graph.captures(message)?.get(1)?.as_str().parse().ok()
+}
+
+/// `row` as it's read back once it's stored with `id`, or counted as its
+/// `repeats`th repeat
+fn stored(row: &NewRow, id: i64, repeats: u64) -> Row {
+    Row {
+        id,
+        ts: row.ts,
+        file_id: row.file_id.clone(),
+        message: row.display.clone().unwrap_or_else(|| row.message.clone()),
+        kind: None,
+        level: row.level.map(str::to_owned),
+        repeats,
+        line_ts: row.line_ts,
+        skipped: 0,
+    }
+}
+
+/// The last line stored from a file, which its repeats are counted on, see
+/// `StoreOptions::collapse_repeats`
+#[derive(Clone)]
+struct LastLine {
+    /// Row id in the db
+    id: i64,
+    message: String,
+    /// When it, or its last repeat, was read
+    seen_at: i64,
+    repeats: u64,
+}
+
+/// Milliseconds since the epoch, as stored in the `ts` column
+fn unix_millis(time: SystemTime) -> i64 {
+    time.duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::TrySendError;
+
+    use super::*;
+
+    fn options() -> StoreOptions {
+        StoreOptions {
+            collapse_repeats: false,
+            repeat_gap: Duration::from_secs(60),
+            max_rows: None,
+            max_age: None,
+            order_by_line_ts: false,
+            alerts: vec![],
+            graphs: vec![],
+        }
+    }
+
+    fn lines(lines: Vec<String>) -> SourceEvent {
+        SourceEvent::Lines { levels: vec![None; lines.len()], lines, display: None, timestamps: None }
+    }
+
+    #[test]
+    fn full_channel_loses_and_reorders_nothing() {
+        let path = std::env::temp_dir().join(format!("filewatch-test-{}-backlog.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        crate::create_tables(&conn).unwrap();
+        // it's written to in small transactions, as the channel lets lines in
+        conn.pragma_update(None, "synchronous", "OFF").unwrap();
+        let store = LogStore::new(conn, false).unwrap();
+        // a few messages' room, so the watchers below have to wait for the
+        // writer, with more lines than it writes at a time
+        let (tx, rx) = mpsc::sync_channel(4);
+        let (updates_tx, updates) = mpsc::channel::<Update>();
+        let (requests, writer) = spawn(store, rx, options(), updates_tx);
+        let per_message = 100;
+        let messages = MAX_ROWS_PER_WRITE / per_message;
+        let watchers: Vec<_> = ["a.log", "b.log"].into_iter()
+            .map(|file_id| {
+                let tx = tx.clone();
+                std::thread::spawn(move || {
+                    let mut full = 0;
+                    for i in 0..messages {
+                        let batch = (i * per_message..(i + 1) * per_message).map(|n| format!("{} {}", file_id, n)).collect();
+                        let mut msg = LogsMessage::new(file_id.to_owned(), lines(batch));
+                        while let Err(TrySendError::Full(unsent)) = tx.try_send(msg) {
+                            full += 1;
+                            msg = unsent;
+                            std::thread::sleep(Duration::from_micros(100));
+                        }
+                    }
+                    full
+                })
+            })
+            .collect();
+        drop(tx);
+        let full: usize = watchers.into_iter().map(|watcher| watcher.join().unwrap()).sum();
+        // the writer stops once there are no more requests, whatever's left
+        let mut stored = 0;
+        while stored < 2 * messages * per_message {
+            if let Update::Stored { added, .. } = updates.recv_timeout(Duration::from_secs(30)).unwrap() {
+                stored += added as usize;
+            }
+        }
+        drop(requests);
+        writer.join().unwrap();
+        assert!(full > 0, "the channel was never full");
+
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        for file_id in ["a.log", "b.log"] {
+            let stored: Vec<String> = conn.prepare("SELECT message FROM log WHERE file_id = ? ORDER BY id").unwrap()
+                .query_map((file_id,), |row| row.get(0)).unwrap()
+                .collect::<rusqlite::Result<_>>().unwrap();
+            let sent: Vec<String> = (0..messages * per_message).map(|n| format!("{} {}", file_id, n)).collect();
+            assert!(stored == sent, "{}: {} of {} stored, first difference at {:?}", file_id, stored.len(), sent.len(),
+                stored.iter().zip(&sent).position(|(stored, sent)| stored != sent));
+        }
+        drop(conn);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use filewatch_rs::file_watch::thousands;
+
+/// Seconds rows are counted for, which the rate in the status bar is over
+const SECONDS: usize = 60;
+
+/// Seconds the sparkline shows, one column each, the last of `SECONDS`
+const SPARKLINE_SECONDS: usize = 20;
+
+/// A column for a second with at least one row, from the fewest to the most
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// How many rows were stored in each of the last `SECONDS` seconds, to show
+/// how busy the files are in the status bar. They're counted as the store's
+/// `Update::Stored` come in, so nothing is asked of it for them.
+#[derive(Default)]
+pub struct Activity {
+    /// Rows stored each second, the current second last
+    counts: VecDeque<u64>,
+    /// When the current second started
+    second: Option<Instant>,
+}
+
+impl Activity {
+    /// Count `rows` as stored now
+    pub fn add(&mut self, rows: u64) {
+        let now = Instant::now();
+        let passed = self.passed(now);
+        self.counts.extend(std::iter::repeat_n(0, passed.min(SECONDS)));
+        if self.counts.is_empty() {
+            self.counts.push_back(0);
+        }
+        while self.counts.len() > SECONDS {
+            self.counts.pop_front();
+        }
+        self.second = Some(self.second.map_or(now, |second| second + Duration::from_secs(passed as u64)));
+        *self.counts.back_mut().expect("there's a current second") += rows;
+    }
+
+    /// Seconds started since the current one, as of `now`
+    fn passed(&self, now: Instant) -> usize {
+        self.second.map_or(0, |second| now.duration_since(second).as_secs() as usize)
+    }
+
+    /// The counts of the last `seconds` seconds as of now, oldest first,
+    /// with none for the ones nothing was stored in since
+    fn last(&self, seconds: usize) -> impl Iterator<Item = u64> + '_ {
+        let passed = self.passed(Instant::now()).min(seconds);
+        let kept = (seconds - passed).min(self.counts.len());
+        let missing = seconds - passed - kept;
+        let kept = self.counts.iter().skip(self.counts.len() - kept).copied();
+        std::iter::repeat_n(0, missing).chain(kept).chain(std::iter::repeat_n(0, passed))
+    }
+
+    /// A column for each of the last `SPARKLINE_SECONDS` seconds, as high
+    /// as its count is next to the highest, blank for none, then how many
+    /// rows there were in the last minute, e.g. `  ▁▁▃█▂  1,204/min`
+    pub fn sparkline(&self) -> String {
+        let counts: Vec<u64> = self.last(SPARKLINE_SECONDS).collect();
+        let highest = counts.iter().copied().max().unwrap_or(0).max(1);
+        let bars: String = counts.iter()
+            .map(|&count| match count {
+                0 => ' ',
+                // at least the lowest for a single row
+                _ => BARS[(count * BARS.len() as u64).div_ceil(highest) as usize - 1],
+            })
+            .collect();
+        format!("{} {}/min", bars, thousands(self.last(SECONDS).sum()))
+    }
+}
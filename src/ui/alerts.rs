@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ratatui::style::{Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Clear, Paragraph};
+use ratatui::Frame;
+
+use super::frequency::format_time;
+use super::marks::Mark;
+use super::stats::centered;
+use super::LogLine;
+
+/// Alerts kept, the oldest are forgotten beyond this many
+const KEPT: usize = 100;
+
+/// How long the status bar is red, then not, while there are unseen alerts
+const FLASH: Duration = Duration::from_millis(500);
+
+/// Chars of the newest alert's line the status bar shows
+const PREVIEW_CHARS: usize = 48;
+
+/// A line that matched `--alert`, see `Alerts`
+struct Alert {
+    /// Where it is, to jump to it as to a mark
+    mark: Mark,
+    file_id: String,
+    /// When it was read, in ms since the epoch
+    ts: i64,
+}
+
+/// Lines that matched `--alert`, the newest first. The status bar flashes
+/// with them until they're seen in the popup `!` opens, from which Enter
+/// jumps to one.
+#[derive(Default)]
+pub struct Alerts {
+    alerts: VecDeque<Alert>,
+    /// Alerts since the popup was last open
+    unseen: usize,
+    /// Index of the alert the cursor is on while the popup is open
+    cursor: Option<usize>,
+}
+
+impl Alerts {
+    pub fn add(&mut self, log: &LogLine) {
+        self.alerts.push_front(Alert { mark: Mark::new(log), file_id: log.file_id.clone(), ts: log.ts });
+        self.alerts.truncate(KEPT);
+        match &mut self.cursor {
+            // the cursor stays on the alert it was on
+            Some(cursor) => *cursor = (*cursor + 1).min(self.alerts.len() - 1),
+            None => self.unseen += 1,
+        }
+    }
+
+    /// How many alerts haven't been seen and the newest one's line, for
+    /// the status bar, flashing red
+    pub fn status(&self) -> Option<Span<'static>> {
+        let latest = self.alerts.front().filter(|_| self.unseen > 0)?;
+        let preview: String = latest.mark.preview.chars().take(PREVIEW_CHARS).collect();
+        let span = Span::from(format!("ALERT ({}) {}", self.unseen, preview)).bold();
+        let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let red = (since_epoch.as_millis() / FLASH.as_millis()).is_multiple_of(2);
+        Some(if red { span.white().on_red() } else { span.red() })
+    }
+
+    /// Open the popup, which counts as seeing the alerts
+    pub fn open(&mut self) {
+        self.cursor = Some(0);
+        self.unseen = 0;
+    }
+
+    pub fn close(&mut self) {
+        self.cursor = None;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.cursor.is_some()
+    }
+
+    /// Move the cursor `step` alerts down, or up if negative
+    pub fn move_cursor(&mut self, step: isize) {
+        let last = self.alerts.len().saturating_sub(1);
+        if let Some(cursor) = &mut self.cursor {
+            *cursor = cursor.saturating_add_signed(step).min(last);
+        }
+    }
+
+    /// Where the alert the cursor is on is
+    pub fn selected(&self) -> Option<&Mark> {
+        self.alerts.get(self.cursor?).map(|alert| &alert.mark)
+    }
+
+    /// A line for each alert, with when it was read and which file it's
+    /// from
+    pub fn render_popup(&self, frame: &mut Frame) {
+        let screen = frame.area();
+        let area = centered(screen, screen.width.saturating_sub(4), screen.height.saturating_sub(2));
+        // inside the borders
+        let room = usize::from(area.height.saturating_sub(2)).max(1);
+        let width = usize::from(area.width.saturating_sub(2));
+        let cursor = self.cursor.unwrap_or(0);
+        let mut lines: Vec<Line> = self.alerts.iter().enumerate()
+            .skip(cursor.saturating_sub(room - 1))
+            .take(room)
+            .map(|(i, alert)| {
+                let text = format!("{}  {}  {}", format_time(alert.ts), alert.file_id, alert.mark.preview);
+                let line = Line::from(text.chars().take(width).collect::<String>());
+                if i == cursor { line.reversed() } else { line }
+            })
+            .collect();
+        if lines.is_empty() {
+            lines.push(Line::from("no alerts, --alert picks the lines that are").dim());
+        }
+        frame.render_widget(Clear, area);
+        let block = Block::bordered()
+            .title(" alerts, newest first ")
+            .title_bottom(Line::from(" Enter: jump to it  Esc: close ").dim());
+        frame.render_widget(Paragraph::new(lines).block(block).style(Style::default()), area);
+    }
+}
@@ -0,0 +1,113 @@
+use std::str::FromStr;
+
+use ratatui::style::{Color, Modifier, Style};
+
+/// Colors files' prefixes are shown in, leaving out red and yellow, which
+/// are for errors and warnings
+const FILE_COLORS: [Color; 8] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Green,
+    Color::Blue,
+    Color::LightCyan,
+    Color::LightMagenta,
+    Color::LightGreen,
+    Color::LightBlue,
+];
+
+/// The color of `file`'s prefix, the same every time for the same file.
+/// FNV-1a, as std's hasher may change between Rust versions.
+pub fn file_color(file: &str) -> Color {
+    let hash = file.bytes().fold(0xcbf29ce484222325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3));
+    FILE_COLORS[(hash % FILE_COLORS.len() as u64) as usize]
+}
+
+/// A style written as words, e.g. `red bold` or `black on_yellow`: a color
+/// for the text, `on_` and a color for the background, and any of `bold`,
+/// `dim`, `italic`, `underlined`, `reversed` and `blink`. Colors are names
+/// like `red` or `lightblue`, `#rrggbb`, or a palette index.
+pub fn parse_style(s: &str) -> Result<Style, String> {
+    let mut style = Style::default();
+    for word in s.split_whitespace() {
+        let modifier = match word {
+            "bold" => Some(Modifier::BOLD),
+            "dim" => Some(Modifier::DIM),
+            "italic" => Some(Modifier::ITALIC),
+            "underlined" => Some(Modifier::UNDERLINED),
+            "reversed" => Some(Modifier::REVERSED),
+            "blink" => Some(Modifier::SLOW_BLINK),
+            _ => None,
+        };
+        if let Some(modifier) = modifier {
+            style = style.add_modifier(modifier);
+        } else if let Some(color) = word.strip_prefix("on_") {
+            style = style.bg(Color::from_str(color).map_err(|_| format!("unknown color '{}'", color))?);
+        } else {
+            style = style.fg(Color::from_str(word).map_err(|_| format!("unknown color or modifier '{}'", word))?);
+        }
+    }
+    if style == Style::default() {
+        return Err(String::from("expected a color or modifier, e.g. 'red bold'"));
+    }
+    Ok(style)
+}
+
+/// The style each SGR sequence of a line leaves the terminal in, from the
+/// char index it applies from, see `file_watch::split_sgr`. Styles only say
+/// what the sequences set, so they can be laid over a line's own style.
+pub fn sgr_styles(sgr: &[(usize, &str)]) -> Vec<(usize, Style)> {
+    let mut style = Style::default();
+    sgr.iter()
+        .map(|(at, params)| {
+            style = apply_sgr(style, params);
+            (*at, style)
+        })
+        .collect()
+}
+
+fn apply_sgr(mut style: Style, params: &str) -> Style {
+    // `38:5:n` is the same as `38;5;n`, and a missing number is 0
+    let mut codes = params.split([';', ':']).map(|code| code.parse::<u16>().unwrap_or(0));
+    while let Some(code) = codes.next() {
+        match code {
+            0 => style = Style::default(),
+            1 => style = style.add_modifier(Modifier::BOLD),
+            2 => style = style.add_modifier(Modifier::DIM),
+            3 => style = style.add_modifier(Modifier::ITALIC),
+            4 => style = style.add_modifier(Modifier::UNDERLINED),
+            5 | 6 => style = style.add_modifier(Modifier::SLOW_BLINK),
+            7 => style = style.add_modifier(Modifier::REVERSED),
+            8 => style = style.add_modifier(Modifier::HIDDEN),
+            9 => style = style.add_modifier(Modifier::CROSSED_OUT),
+            22 => style = style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style = style.remove_modifier(Modifier::ITALIC),
+            24 => style = style.remove_modifier(Modifier::UNDERLINED),
+            25 => style = style.remove_modifier(Modifier::SLOW_BLINK),
+            27 => style = style.remove_modifier(Modifier::REVERSED),
+            28 => style = style.remove_modifier(Modifier::HIDDEN),
+            29 => style = style.remove_modifier(Modifier::CROSSED_OUT),
+            30..=37 => style.fg = Some(Color::Indexed((code - 30) as u8)),
+            38 => style.fg = extended_color(&mut codes).or(style.fg),
+            39 => style.fg = None,
+            40..=47 => style.bg = Some(Color::Indexed((code - 40) as u8)),
+            48 => style.bg = extended_color(&mut codes).or(style.bg),
+            49 => style.bg = None,
+            90..=97 => style.fg = Some(Color::Indexed((code - 90 + 8) as u8)),
+            100..=107 => style.bg = Some(Color::Indexed((code - 100 + 8) as u8)),
+            _ => {}
+        }
+    }
+    style
+}
+
+/// The color after a 38 or 48: `5;n` from the 256 color palette or `2;r;g;b`
+fn extended_color(codes: &mut impl Iterator<Item = u16>) -> Option<Color> {
+    match codes.next()? {
+        5 => Some(Color::Indexed(codes.next()?.min(255) as u8)),
+        2 => {
+            let (r, g, b) = (codes.next()?, codes.next()?, codes.next()?);
+            Some(Color::Rgb(r.min(255) as u8, g.min(255) as u8, b.min(255) as u8))
+        }
+        _ => None,
+    }
+}
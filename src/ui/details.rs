@@ -0,0 +1,78 @@
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Clear, Paragraph};
+use ratatui::Frame;
+use unicode_width::UnicodeWidthStr;
+
+use super::stats::centered;
+use super::LogLine;
+
+/// A row shown whole in a popup, lines as they are rather than wrapped,
+/// along with where it's from and when, see `App::open_details`
+pub struct Details {
+    log: LogLine,
+    /// Lines scrolled down and columns scrolled right
+    scroll: (usize, usize),
+}
+
+impl Details {
+    pub fn new(log: LogLine) -> Self {
+        Details { log, scroll: (0, 0) }
+    }
+
+    /// The row's line, as it's copied, see `LogLine::line`
+    pub fn line(&self) -> &str {
+        self.log.line()
+    }
+
+    /// Scroll the line `down` lines and `right` columns, up or left if
+    /// they're negative, no further than its last line and widest column
+    pub fn scroll(&mut self, down: isize, right: isize) {
+        let lines = self.line().lines().count();
+        let width = self.line().lines().map(UnicodeWidthStr::width).max().unwrap_or(0);
+        self.scroll.0 = self.scroll.0.saturating_add_signed(down).min(lines.saturating_sub(1));
+        self.scroll.1 = self.scroll.1.saturating_add_signed(right).min(width.saturating_sub(1));
+    }
+
+    pub fn render_popup(&self, frame: &mut Frame) {
+        let log = &self.log;
+        let mut about = vec![
+            Line::from(format!("file    {}", log.file_id)),
+            Line::from(format!("row     {}", log.id)),
+            Line::from(format!("read    {}", format_time(log.ts))),
+        ];
+        if let Some(line_ts) = log.line_ts {
+            about.push(Line::from(format!("logged  {}", format_time(line_ts))));
+        }
+        if let Some(level) = log.level {
+            about.push(Line::from(format!("level   {}", level)));
+        }
+        about.push(Line::from(format!("bytes   {}", self.line().len())));
+        if log.repeats > 0 {
+            about.push(Line::from(format!("repeats {}", log.repeats)));
+        }
+
+        let screen = frame.area();
+        let area = centered(screen, screen.width.saturating_sub(4), screen.height.saturating_sub(2));
+        frame.render_widget(Clear, area);
+        let block = Block::bordered()
+            .title(" line (Esc to close, y to copy) ")
+            .title_bottom(Line::from(" ←/→/↑/↓ to scroll ").dim());
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        let [about_area, line_area] = Layout::vertical([Constraint::Length(about.len() as u16 + 1), Constraint::Fill(1)]).areas(inner);
+        frame.render_widget(Paragraph::new(about).dim(), about_area);
+        let (down, right) = self.scroll;
+        let line = Paragraph::new(self.line()).style(Style::default()).scroll((down as u16, right as u16));
+        frame.render_widget(line, line_area);
+    }
+}
+
+/// `ts`, in ms since the epoch, as a local date and time
+fn format_time(ts: i64) -> String {
+    match chrono::DateTime::from_timestamp_millis(ts) {
+        Some(time) => time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+        None => String::from("unknown"),
+    }
+}
@@ -0,0 +1,177 @@
+use std::fmt;
+
+use filewatch_rs::file_watch::thousands;
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Clear, Paragraph};
+use ratatui::Frame;
+
+use super::stats::centered;
+use crate::store::{Frequency, Template};
+
+/// How far back lines are counted in the popup, which `t` cycles through
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Since {
+    #[default]
+    TenMinutes,
+    Hour,
+    Day,
+    Always,
+}
+
+impl Since {
+    fn next(self) -> Self {
+        match self {
+            Since::TenMinutes => Since::Hour,
+            Since::Hour => Since::Day,
+            Since::Day => Since::Always,
+            Since::Always => Since::TenMinutes,
+        }
+    }
+
+    /// When lines are counted from, for it being `now`, in ms since the
+    /// epoch, or none to count them all
+    pub fn start(self, now: i64) -> Option<i64> {
+        let minutes = match self {
+            Since::TenMinutes => 10,
+            Since::Hour => 60,
+            Since::Day => 24 * 60,
+            Since::Always => return None,
+        };
+        Some(now - minutes * 60 * 1000)
+    }
+}
+
+impl fmt::Display for Since {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Since::TenMinutes => "last 10 minutes",
+            Since::Hour => "last hour",
+            Since::Day => "last day",
+            Since::Always => "all lines",
+        })
+    }
+}
+
+/// The lines shown most often, grouped by `Template`, in a popup. Enter on
+/// one only shows lines of its template, see `App::set_template`.
+pub struct Frequencies {
+    pub since: Since,
+    /// Group lines that only differ in numbers and ids, see `Template::new`
+    pub normalize: bool,
+    /// Order them by when they were last seen rather than how many there are
+    by_last_seen: bool,
+    /// The rows counted and the most common templates, or why they
+    /// couldn't be counted, none until the store answers
+    counted: Option<Result<(u64, Vec<Frequency>), String>>,
+    /// Index of the template the cursor is on
+    cursor: usize,
+}
+
+impl Default for Frequencies {
+    fn default() -> Self {
+        Frequencies { since: Since::default(), normalize: true, by_last_seen: false, counted: None, cursor: 0 }
+    }
+}
+
+impl Frequencies {
+    /// Count lines from further back, or from more recently again
+    pub fn cycle_since(&mut self) {
+        self.since = self.since.next();
+        self.counted = None;
+    }
+
+    /// Group similar lines, or only the same ones
+    pub fn toggle_normalize(&mut self) {
+        self.normalize = !self.normalize;
+        self.counted = None;
+    }
+
+    /// What the store counted, see `LogStore::frequencies`
+    pub fn set(&mut self, counted: Result<(u64, Vec<Frequency>), String>) {
+        self.counted = Some(counted);
+        self.cursor = 0;
+        self.sort();
+    }
+
+    /// Order templates by when they were last seen, or how many there are
+    pub fn toggle_sort(&mut self) {
+        self.by_last_seen = !self.by_last_seen;
+        self.cursor = 0;
+        self.sort();
+    }
+
+    fn sort(&mut self) {
+        if let Some(Ok((_, frequencies))) = &mut self.counted {
+            if self.by_last_seen {
+                frequencies.sort_by(|a, b| b.last.cmp(&a.last).then(b.count.cmp(&a.count)));
+            } else {
+                frequencies.sort_by(|a, b| b.count.cmp(&a.count).then(b.last.cmp(&a.last)));
+            }
+        }
+    }
+
+    /// Move the cursor `step` templates down, or up if negative
+    pub fn move_cursor(&mut self, step: isize) {
+        let count = self.frequencies().len();
+        self.cursor = self.cursor.saturating_add_signed(step).min(count.saturating_sub(1));
+    }
+
+    /// The template the cursor is on
+    pub fn selected(&self) -> Option<&Template> {
+        self.frequencies().get(self.cursor).map(|frequency| &frequency.template)
+    }
+
+    fn frequencies(&self) -> &[Frequency] {
+        match &self.counted {
+            Some(Ok((_, frequencies))) => frequencies,
+            _ => &[],
+        }
+    }
+
+    /// A line for each template, with how many of the lines counted were
+    /// of it, as a share too, and when the first and last of them were seen
+    pub fn render_popup(&self, frame: &mut Frame) {
+        let screen = frame.area();
+        let area = centered(screen, screen.width.saturating_sub(4), screen.height.saturating_sub(2));
+        let mut lines = vec![Line::from(format!("{:>9} {:>4}  {:<8}  {:<8}  line", "count", "%", "first", "last")).bold()];
+        match &self.counted {
+            None => lines.push(Line::from("counting...").dim()),
+            Some(Err(err)) => lines.push(Line::from(format!("counting failed: {}", err)).red()),
+            Some(Ok((_, frequencies))) if frequencies.is_empty() => lines.push(Line::from("no lines").dim()),
+            Some(Ok((total, frequencies))) => {
+                // under the header, inside the borders
+                let room = usize::from(area.height.saturating_sub(3)).max(1);
+                let first = self.cursor.saturating_sub(room - 1);
+                // the borders and the columns before the line
+                let width = usize::from(area.width.saturating_sub(2)).saturating_sub(38);
+                for (i, frequency) in frequencies.iter().enumerate().skip(first).take(room) {
+                    let line = Line::from(format!(
+                        "{:>9} {:>4}  {:<8}  {:<8}  {}",
+                        thousands(frequency.count),
+                        frequency.count * 100 / total.max(&1),
+                        format_time(frequency.first),
+                        format_time(frequency.last),
+                        frequency.template.text.replace('\n', "↵").chars().take(width).collect::<String>(),
+                    ));
+                    lines.push(if i == self.cursor { line.reversed() } else { line });
+                }
+            }
+        }
+        let order = if self.by_last_seen { "by last seen" } else { "by count" };
+        let grouping = if self.normalize { "similar grouped" } else { "same grouped" };
+        frame.render_widget(Clear, area);
+        let block = Block::bordered()
+            .title(format!(" most common lines, {}, {}, {} ", self.since, order, grouping))
+            .title_bottom(Line::from(" Enter: only these  x: all lines  s: sort  n: grouping  t: time range  Esc: close ").dim());
+        frame.render_widget(Paragraph::new(lines).block(block).style(Style::default()), area);
+    }
+}
+
+/// `ts`, in ms since the epoch, as a local time of day
+pub(super) fn format_time(ts: i64) -> String {
+    match chrono::DateTime::from_timestamp_millis(ts) {
+        Some(time) => time.with_timezone(&chrono::Local).format("%H:%M:%S").to_string(),
+        None => String::from("?"),
+    }
+}
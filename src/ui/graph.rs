@@ -0,0 +1,161 @@
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ratatui::layout::{Constraint, Rect};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::symbols::Marker;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Axis, Block, Borders, Chart, Dataset, GraphType, LegendPosition};
+use ratatui::Frame;
+use regex::Regex;
+
+use super::frequency::format_time;
+
+/// How far back the chart goes, older values are forgotten
+const SPAN: Duration = Duration::from_secs(5 * 60);
+
+/// Values kept for each graph, the oldest are forgotten beyond this many
+/// even if they're not older than `SPAN`
+const KEPT: usize = 10_000;
+
+/// Screen rows the chart takes, with the line above it
+const HEIGHT: u16 = 10;
+
+/// Each graph's color, in the order they were added, going round
+const COLORS: [Color; 6] = [Color::Cyan, Color::Magenta, Color::Yellow, Color::Green, Color::Blue, Color::Red];
+
+/// `s` as a regex for `--graph` and the prompt `a` opens, which needs a
+/// group around the number, e.g. `latency_ms=(\d+)`
+pub fn parse_graph(s: &str) -> Result<Regex, String> {
+    let regex = Regex::new(s).map_err(|e| e.to_string())?;
+    if regex.captures_len() < 2 {
+        return Err(format!("'{}' has no group around the number, e.g. latency_ms=(\\d+)", s));
+    }
+    Ok(regex)
+}
+
+/// A regex's values, see `Graphs`
+struct Graph {
+    regex: Regex,
+    /// When each value's line was read, in ms since the epoch, and the
+    /// value, oldest first
+    values: VecDeque<(i64, f64)>,
+}
+
+/// Numbers picked out of lines by `--graph` regexes, or ones added with
+/// `a`, charted against when they were read over the last `SPAN` below the
+/// logs, a line in a color of its own for each regex. The store picks them
+/// out as it stores the lines, which are shown as usual, and sends them
+/// with `Update::Values`.
+#[derive(Default)]
+pub struct Graphs {
+    graphs: Vec<Graph>,
+}
+
+impl Graphs {
+    pub fn new(regexes: Vec<Regex>) -> Self {
+        Graphs { graphs: regexes.into_iter().map(|regex| Graph { regex, values: VecDeque::new() }).collect() }
+    }
+
+    /// Chart `regex` too, or stop charting it if it is. Returns whether it's
+    /// charted.
+    pub fn toggle(&mut self, regex: Regex) -> bool {
+        if let Some(i) = self.graphs.iter().position(|graph| graph.regex.as_str() == regex.as_str()) {
+            self.graphs.remove(i);
+            return false;
+        }
+        self.graphs.push(Graph { regex, values: VecDeque::new() });
+        true
+    }
+
+    /// The regexes charted, in order, which the store's values go by
+    pub fn regexes(&self) -> Vec<Regex> {
+        self.graphs.iter().map(|graph| graph.regex.clone()).collect()
+    }
+
+    /// Add `value`, read at `ts`, to the graph at index `graph`
+    pub fn add(&mut self, graph: usize, ts: i64, value: f64) {
+        // values picked out before a graph was removed can still come in
+        let Some(graph) = self.graphs.get_mut(graph) else {
+            return;
+        };
+        graph.values.push_back((ts, value));
+        if graph.values.len() > KEPT {
+            graph.values.pop_front();
+        }
+    }
+
+    /// Screen rows the chart takes, none without graphs
+    pub fn height(&self) -> u16 {
+        if self.graphs.is_empty() { 0 } else { HEIGHT }
+    }
+
+    /// A line for each graph over the last `SPAN`, with its regex and the
+    /// lowest, middle and highest of its values then in the legend, from
+    /// the lowest of all of them to the highest
+    pub fn render(&mut self, frame: &mut Frame, area: Rect) {
+        if area.height == 0 {
+            return;
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as i64;
+        let start = now - SPAN.as_millis() as i64;
+        let mut lowest = f64::INFINITY;
+        let mut highest = f64::NEG_INFINITY;
+        let mut points: Vec<Vec<(f64, f64)>> = vec![];
+        let mut names = vec![];
+        for graph in &mut self.graphs {
+            while graph.values.front().is_some_and(|(ts, _)| *ts < start) {
+                graph.values.pop_front();
+            }
+            let mut sorted: Vec<f64> = graph.values.iter().map(|(_, value)| *value).collect();
+            sorted.sort_by(f64::total_cmp);
+            let name = match (sorted.first(), sorted.last()) {
+                (Some(min), Some(max)) => {
+                    lowest = lowest.min(*min);
+                    highest = highest.max(*max);
+                    format!("{}  min {}  p50 {}  max {}", graph.regex, min, sorted[sorted.len() / 2], max)
+                }
+                _ => format!("{}  no values", graph.regex),
+            };
+            names.push(name);
+            points.push(graph.values.iter().map(|(ts, value)| (*ts as f64, *value)).collect());
+        }
+        if lowest > highest {
+            (lowest, highest) = (0.0, 1.0);
+        }
+        // a flat line in the middle rather than on an edge
+        let (low, high) = if lowest == highest { (lowest - 1.0, highest + 1.0) } else { (lowest, highest) };
+        let datasets = points.iter().zip(names).enumerate()
+            .map(|(i, (points, name))| {
+                Dataset::default()
+                    .name(name)
+                    .marker(Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(Style::default().fg(COLORS[i % COLORS.len()]))
+                    .data(points)
+            })
+            .collect();
+        let x_axis = Axis::default()
+            .bounds([start as f64, now as f64])
+            .labels([Span::from(format_time(start)), Span::from(format_time(now))])
+            .style(Style::default().dim());
+        let y_axis = Axis::default()
+            .bounds([low, high])
+            .labels([Span::from(lowest.to_string()), Span::from(highest.to_string())])
+            .style(Style::default().dim());
+        let block = Block::new()
+            .borders(Borders::TOP)
+            .border_style(Style::default().dim())
+            .title(Line::from(format!(" graphs, last {}m ", SPAN.as_secs() / 60)).dim())
+            .title(Line::from(" a: add or remove one ").dim().right_aligned());
+        let chart = Chart::new(datasets)
+            .block(block)
+            .x_axis(x_axis)
+            .y_axis(y_axis)
+            // the legend is where the values are, so it's always shown, on
+            // the left as the newest are on the right
+            .legend_position(Some(LegendPosition::TopLeft))
+            .hidden_legend_constraints((Constraint::Percentage(100), Constraint::Percentage(100)));
+        frame.render_widget(chart, area);
+    }
+}
@@ -0,0 +1,38 @@
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Clear, Paragraph};
+use ratatui::Frame;
+
+use super::stats::centered;
+use crate::input::{Category, KEYMAP};
+
+/// The keys of `KEYMAP` by category, in a popup that any key closes. On a
+/// terminal too short for all of them, the last line says how many more
+/// there are.
+pub fn render_popup(frame: &mut Frame) {
+    let keys_width = KEYMAP.iter().map(|binding| binding.keys_label().chars().count()).max().unwrap_or(0);
+    let mut lines = vec![];
+    for category in Category::ALL {
+        if !lines.is_empty() {
+            lines.push(Line::default());
+        }
+        lines.push(Line::from(category.name()).bold());
+        for binding in KEYMAP.iter().filter(|binding| binding.category == category) {
+            lines.push(Line::from(format!("  {:<keys_width$}  {}", binding.keys_label(), binding.help)));
+        }
+    }
+    lines.push(Line::default());
+    lines.push(Line::from("the mouse wheel scrolls, a click puts the cursor on a line").dim());
+
+    let width = lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 2;
+    let area = centered(frame.area(), width, lines.len() as u16 + 2);
+    let room = usize::from(area.height.saturating_sub(2));
+    if lines.len() > room {
+        let more = lines.len() + 1 - room;
+        lines.truncate(room.saturating_sub(1));
+        lines.push(Line::from(format!("… {} more lines, on a taller terminal", more)).dim());
+    }
+    frame.render_widget(Clear, area);
+    let block = Block::bordered().title(" keys (any key to close) ");
+    frame.render_widget(Paragraph::new(lines).block(block).style(Style::default()), area);
+}
@@ -0,0 +1,73 @@
+use std::str::FromStr;
+
+use ratatui::style::{Modifier, Style, Stylize};
+use regex::Regex;
+
+use super::{color, LogLine};
+
+/// Text picked out in a style of its own wherever it's shown, see
+/// `Args::highlights`
+#[derive(Clone, Debug)]
+pub struct Highlight {
+    pub regex: Regex,
+    pub style: Style,
+}
+
+impl Highlight {
+    pub fn new(pattern: &str, style: Style) -> Result<Self, String> {
+        let regex = Regex::new(pattern).map_err(|e| e.to_string())?;
+        Ok(Highlight { regex, style })
+    }
+
+    /// How matches are shown, as `parse_style` has it, but bold if it only
+    /// gives colors
+    pub fn parse_style(s: &str) -> Result<Style, String> {
+        let style = color::parse_style(s)?;
+        if style.add_modifier.is_empty() {
+            return Ok(style.add_modifier(Modifier::BOLD));
+        }
+        Ok(style)
+    }
+
+    /// The current search's matches, see `App::set_matches`
+    pub fn search(regex: Regex) -> Self {
+        Highlight { regex, style: Style::default().reversed() }
+    }
+}
+
+impl FromStr for Highlight {
+    type Err = String;
+
+    /// `REGEX:STYLE`, e.g. `ERROR:red bold` or `user=\w+:#00afff`, or just
+    /// `REGEX` for bold yellow, see `parse_style`. What's after the last `:`
+    /// is only taken as the style if it is one, so regexes can have colons
+    /// in them.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (pattern, style) = match s.rsplit_once(':').and_then(|(pattern, style)| Some((pattern, Highlight::parse_style(style).ok()?))) {
+            Some((pattern, style)) => (pattern, style),
+            None => (s, Style::default().yellow().bold()),
+        };
+        Highlight::new(pattern, style)
+    }
+}
+
+/// Char ranges in `log.text` that `regex` matches, only looking at the
+/// message and not the file and time before it
+pub fn message_matches(log: &LogLine, regex: &Regex) -> Vec<(usize, usize)> {
+    let message_byte = log.text.char_indices().nth(log.message_start).map_or(log.text.len(), |(i, _)| i);
+    let mut chars = log.message_start;
+    let mut counted_to = message_byte;
+    let mut ranges = vec![];
+    for found in regex.find_iter(&log.text[message_byte..]) {
+        let (start, end) = (message_byte + found.start(), message_byte + found.end());
+        // counted on from the last match, rather than from the start each time
+        chars += log.text[counted_to..start].chars().count();
+        let start_char = chars;
+        chars += log.text[start..end].chars().count();
+        counted_to = end;
+        if start < end {
+            ranges.push((start_char, chars));
+        }
+    }
+    ranges
+}
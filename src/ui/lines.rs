@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+
+use super::LogLine;
+
+/// Screen lines each of the logs shown takes, and how many come before each,
+/// so that drawing the screen only wraps the logs on it rather than every
+/// one in the window. Logs keep their count from one `update` to the next
+/// until the width changes or they're `forget`-ed, e.g. as their text
+/// changed.
+#[derive(Default)]
+pub struct LineCounts {
+    /// Columns the logs were laid out in
+    width: usize,
+    /// Id of each log, in the order they're shown, and the lines it takes
+    logs: Vec<(i64, usize)>,
+    /// Lines before each log, and then all of them
+    offsets: Vec<usize>,
+}
+
+impl LineCounts {
+    /// Count the lines of `logs` laid out in `width` columns, by `lines`,
+    /// for the ones that weren't counted already
+    pub fn update(&mut self, logs: &[&LogLine], width: usize, lines: impl Fn(&LogLine) -> usize) {
+        if width != self.width {
+            self.width = width;
+            self.logs.clear();
+        }
+        // as it nearly always is, between one frame and the next
+        if self.logs.len() == logs.len() && self.offsets.len() == logs.len() + 1 && self.logs.iter().zip(logs).all(|((id, _), log)| *id == log.id) {
+            return;
+        }
+        let counted: HashMap<i64, usize> = self.logs.drain(..).collect();
+        self.logs = logs.iter().map(|log| (log.id, counted.get(&log.id).copied().unwrap_or_else(|| lines(log)))).collect();
+        self.offsets = Vec::with_capacity(self.logs.len() + 1);
+        let mut before = 0;
+        for (_, lines) in &self.logs {
+            self.offsets.push(before);
+            before += lines;
+        }
+        self.offsets.push(before);
+    }
+
+    /// Count the lines of the log with `id` again on the next `update`
+    pub fn forget(&mut self, id: i64) {
+        self.logs.retain(|(log_id, _)| *log_id != id);
+    }
+
+    /// Count every log's lines again on the next `update`, e.g. as they're
+    /// wrapped differently
+    pub fn clear(&mut self) {
+        self.logs.clear();
+    }
+
+    /// Lines before each log as of the last `update`, and then all of them
+    pub fn offsets(&self) -> &[usize] {
+        &self.offsets
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::BTreeMap;
+
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Clear, Paragraph};
+use ratatui::Frame;
+
+use super::stats::centered;
+use super::LogLine;
+
+/// A row the screen was at, to go back to, see `Marks`
+#[derive(Clone)]
+pub struct Mark {
+    /// Row id, which stays the same as rows are added and filtered
+    pub id: i64,
+    /// Where the row goes among the others, which jumping to it goes by, so
+    /// that the row after it is shown if it's filtered out
+    pub order: (i64, i64),
+    /// The row's line, as the marks popup shows it
+    pub preview: String,
+}
+
+impl Mark {
+    pub fn new(log: &LogLine) -> Self {
+        Mark { id: log.id, order: log.order, preview: log.line().lines().next().unwrap_or("").to_string() }
+    }
+}
+
+/// Rows marked with `m` and a letter, which `'` and the letter jumps back
+/// to. They're kept for the session only.
+#[derive(Default)]
+pub struct Marks {
+    marks: BTreeMap<char, Mark>,
+    /// Where the screen was before the last jump to a mark, which `''` goes
+    /// back to
+    pub back: Option<Mark>,
+}
+
+impl Marks {
+    pub fn set(&mut self, letter: char, mark: Mark) {
+        self.marks.insert(letter, mark);
+    }
+
+    pub fn get(&self, letter: char) -> Option<&Mark> {
+        self.marks.get(&letter)
+    }
+
+    /// Each mark with its letter and a line of the row, in a popup that any
+    /// key closes
+    pub fn render_popup(&self, frame: &mut Frame) {
+        let screen = frame.area();
+        // letter, two spaces, and the borders
+        let room = usize::from(screen.width.saturating_sub(4)).saturating_sub(3);
+        let mut lines: Vec<Line> = self.marks.iter()
+            .map(|(letter, mark)| Line::from(format!("{}  {}", letter, mark.preview.chars().take(room).collect::<String>())))
+            .collect();
+        if let Some(back) = &self.back {
+            lines.push(Line::from(format!("'  {}", back.preview.chars().take(room).collect::<String>())).dim());
+        }
+        if lines.is_empty() {
+            lines.push(Line::from("no marks yet, m and a letter sets one").dim());
+        }
+
+        let width = lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 2;
+        let area = centered(screen, width.max(28), lines.len() as u16 + 2);
+        frame.render_widget(Clear, area);
+        let block = Block::bordered().title(" marks (any key to close) ");
+        frame.render_widget(Paragraph::new(lines).block(block).style(Style::default()), area);
+    }
+}
@@ -1,27 +1,267 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use filewatch_rs::file_watch::{thousands, Level};
 use ratatui::buffer::Buffer;
-use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::layout::{Constraint, Layout, Position, Rect};
 use ratatui::style::{Style, Stylize};
+use ratatui::symbols::scrollbar;
 use ratatui::text::Span;
-use ratatui::widgets::{Block, StatefulWidget};
+use ratatui::widgets::{Block, Borders, Scrollbar, ScrollbarOrientation, ScrollbarState, StatefulWidget};
 use ratatui::Frame;
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::store::{SearchMode, Template, Window, WINDOW_ROWS};
+use lines::LineCounts;
+use activity::Activity;
+use alerts::Alerts;
+use marks::{Mark, Marks};
+use pins::Pins;
+use sidebar::Sidebar;
+use status::{Segment, Side};
+use tabs::Tabs;
+
+mod activity;
+mod alerts;
+mod color;
+mod details;
+mod frequency;
+mod graph;
+mod help;
+mod highlight;
+mod lines;
+mod marks;
+mod pins;
+mod prefix;
+mod severity;
+mod sidebar;
+mod stats;
+mod status;
+mod tabs;
+
+pub use color::{parse_style, sgr_styles};
+pub use details::Details;
+pub use frequency::Frequencies;
+pub use graph::{parse_graph, Graphs};
+pub use highlight::Highlight;
+pub use prefix::FilePrefixes;
+pub use severity::{LevelStyle, LevelStyles};
+pub use stats::IngestStats;
+
+/// A row of the pager
+#[derive(Clone, Default)]
+pub struct LogLine {
+    /// Row id in the db, which stays with the line when others are ordered
+    /// before it
+    pub id: i64,
+    /// Where the line goes among the others, lowest first: the line's
+    /// timestamp and id when ordering by timestamp, otherwise 0 and its id
+    pub order: (i64, i64),
+    pub text: String,
+    /// Char index in `text` the message starts at, after the file it's from
+    /// and the time
+    pub message_start: usize,
+    /// The file it's from, as `text` shows it just before the message
+    pub file: String,
+    /// The file it's from, as it's stored, see `store::Row::file_id`
+    pub file_id: String,
+    /// When it was read, in ms since the epoch
+    pub ts: i64,
+    /// When it was logged, if `--timestamp-format` found it in the line
+    pub line_ts: Option<i64>,
+    /// Something that happened to a source, e.g. a rotation, rather than a
+    /// line read from it
+    pub is_event: bool,
+    /// The severity found in the line, if any
+    pub level: Option<Level>,
+    /// Colors from the line's own escape sequences, see `sgr_styles`. Each
+    /// applies from its char index up to the next one.
+    pub colors: Vec<(usize, Style)>,
+    /// How many times the line came again straight after, in which case
+    /// `text` ends with `repeat_suffix`
+    pub repeats: u64,
+    /// Ms since the row before it, when that's long enough for a line above
+    /// it saying so, see `App::mark_gaps`
+    pub gap: Option<i64>,
+    /// Rows left out just before it, around search matches, which a line
+    /// above it says in place of a gap, see `App::toggle_context`
+    pub skipped: Option<usize>,
+}
+
+impl LogLine {
+    /// `text` from `message_start` on
+    pub fn message(&self) -> &str {
+        self.text.char_indices().nth(self.message_start).map_or("", |(i, _)| &self.text[i..])
+    }
+
+    /// `message` without the repeat count after it, as the line was read
+    pub fn line(&self) -> &str {
+        let message = self.message();
+        match self.repeats {
+            0 => message,
+            repeats => message.strip_suffix(&repeat_suffix(repeats)).unwrap_or(message),
+        }
+    }
+}
+
+/// How rows are wrapped, and their tabs expanded, see `line_starts`
+#[derive(Clone, Default)]
+pub struct Wrap {
+    /// At words rather than anywhere. It's kept in the session, see
+    /// `LogStore::setting`
+    pub words: bool,
+    /// Shown at the start of each screen line a row is wrapped onto, see
+    /// `Args::wrap_indicator`
+    pub indicator: String,
+    /// Those screen lines are also indented as much as the line of the row
+    /// they're wrapped from
+    pub indent: bool,
+    /// Tabs go to the next multiple of this many columns, see
+    /// `shown_grapheme`
+    pub tab_width: usize,
+}
+
+impl Wrap {
+    /// What's shown before a screen line wrapped from a line that starts
+    /// with `leading` whitespace, see `leading_whitespace`. It's cut to half
+    /// of `width`, so there's always room for the rest.
+    fn continuation(&self, leading: &str, width: usize) -> String {
+        let indent = if self.indent { leading } else { "" };
+        let mut continuation = String::new();
+        let mut column: usize = 0;
+        let indicator_width = self.indicator.width();
+        for grapheme in self.indicator.graphemes(true).chain(indent.graphemes(true)) {
+            // the indent's tabs go to the tab stops it would have on a line
+            // of its own, and the rest of its whitespace is a space each
+            let grapheme = match grapheme {
+                "\t" => shown_grapheme(grapheme, column.saturating_sub(indicator_width), self.tab_width),
+                _ if grapheme.chars().all(char::is_whitespace) => Cow::Borrowed(" "),
+                _ => Cow::Borrowed(grapheme),
+            };
+            column += grapheme.width();
+            if column > width / 2 {
+                break;
+            }
+            continuation.push_str(&grapheme);
+        }
+        continuation
+    }
+}
+
+/// When wrapping at words, at most 1/this of a line is taken to the next
+/// one to keep a word whole, see `line_starts`
+const WORD_WRAP_MAX_CARRY: usize = 3;
+
+/// Rows there have to be between the top of the screen and either end of
+/// the window, or the rows around it are queried again
+const WINDOW_MARGIN: usize = 500;
+
+/// Rows shown before and after each match at first, see
+/// `App::toggle_context`
+const CONTEXT_LINES: usize = 3;
+
+/// Rows `+` shows around each match at the most
+const MAX_CONTEXT_LINES: usize = 100;
+
+/// What's added to a line that was repeated
+pub fn repeat_suffix(repeats: u64) -> String {
+    format!("  (last message repeated {} times)", repeats)
+}
+
+/// Screen lines `log` takes above its own, for the line saying how long
+/// after the row before it came, see `gap_line`, or how many rows were left
+/// out before it, see `skipped_line`
+fn gap_lines(log: &LogLine) -> usize {
+    usize::from(log.gap.is_some() || log.skipped.is_some())
+}
+
+/// The line above a row that came `gap` ms after the one before, e.g.
+/// `── 4m 12s gap ─────`, `width` columns wide
+fn gap_line(gap: i64, width: usize) -> String {
+    let secs = gap / 1000;
+    let units = [(secs / 86400, "d"), (secs / 3600 % 24, "h"), (secs / 60 % 60, "m"), (secs % 60, "s")];
+    // the largest unit there's any of, and the one after it
+    let first = units.iter().position(|(count, _)| *count > 0).unwrap_or(units.len() - 1);
+    let text: Vec<String> = units[first..].iter().take(2).enumerate()
+        .filter(|(i, (count, _))| *i == 0 || *count > 0)
+        .map(|(_, (count, unit))| format!("{}{}", count, unit))
+        .collect();
+    let line = format!("── {} gap ", text.join(" "));
+    let rest = width.saturating_sub(line.width());
+    line + &"─".repeat(rest)
+}
 
+/// The line above a row that `skipped` rows were left out before, between
+/// the matches and the rows around them, e.g. `── 1,204 more ─────`
+fn skipped_line(skipped: usize, width: usize) -> String {
+    let line = format!("── {} more ", thousands(skipped as u64));
+    let rest = width.saturating_sub(line.width());
+    line + &"─".repeat(rest)
+}
 
-struct LogsWidget {
-    pub logs: Vec<String>,
+/// Draws the logs it's given from the app's own, only reading the ones on
+/// screen
+struct LogsWidget<'a> {
+    pub logs: Vec<&'a LogLine>,
+    /// Screen lines before each log, and then all of them, see `LineCounts`
+    pub line_offsets: &'a [usize],
     pub scroll_y: usize,
+    /// Laid over the lines in turn, so later ones win where they overlap
+    pub highlights: Vec<Highlight>,
+    /// Show each file's prefix in a color of its own, see `file_color`
+    pub color_files: bool,
+    pub line_numbers: Option<LineNumbers>,
+    /// Each log is shown on one screen line, from this char on, rather
+    /// than wrapped, see `App::no_wrap`
+    pub h_scroll: Option<usize>,
+    /// Index of the log the cursor is on, see `App::selected`
+    pub selected: Option<usize>,
+    /// How logs are wrapped, when they are
+    pub wrap: Wrap,
+    /// How lines are shown by their level, or none to show them all the same
+    pub level_styles: Option<&'a LevelStyles>,
+}
+
+/// The gutter of line numbers left of the logs, see `App::line_numbers`
+#[derive(Clone, Copy)]
+struct LineNumbers {
+    /// Number of the first log that has one
+    first: usize,
+    /// Logs at the start that don't have one, i.e. `App::top_note`
+    skip: usize,
+    /// Columns taken, the widest number and a space
+    width: u16,
 }
 
 #[derive(Default)]
 pub struct LogsWidgetState {
     pub actual_scroll_y: usize,
     pub was_at_bottom: bool,
-    pub last_log_count: usize,
     pub height: u16,
+    pub width: u16,
+    /// Index of the log at the top of the screen
+    pub top_index: usize,
+    /// Id of the log at the top of the screen, and the char it's shown
+    /// from, counted from where its message starts so that it stays put
+    /// when what's shown before the message changes, see `FilePrefix`. No
+    /// char when it's the line above the log for a gap, see `gap_line`.
+    pub top: Option<(i64, Option<isize>)>,
+    /// Row of the terminal the logs start at
+    pub y: u16,
+    /// Index of the log on each screen line, from the top, so that a click
+    /// can be told which log it's on
+    pub lines: Vec<usize>,
+    /// Screen lines all the logs take, see `App::render_scrollbar`
+    pub line_count: usize,
 }
 
-impl LogsWidget {
-    pub fn new(logs: Vec<String>) -> Self {
-        LogsWidget { logs, scroll_y: 0 }
+impl<'a> LogsWidget<'a> {
+    pub fn new(logs: Vec<&'a LogLine>) -> Self {
+        LogsWidget { logs, line_offsets: &[], scroll_y: 0, highlights: vec![], color_files: false, line_numbers: None, h_scroll: None, selected: None, wrap: Wrap::default(), level_styles: None }
     }
 
     #[allow(unused)]
@@ -30,90 +270,168 @@ impl LogsWidget {
         let mut width_str = String::new();
         width_str.push('x');
         for _ in 0..width-2 {
-            width_str.push_str(format!("-").as_str())
+            width_str.push('-')
         }
         width_str.push('x');
         buf.set_stringn(area.x, area.y, width_str, usize::MAX, Style::default());
     }
 
     fn render_logs(&self, area: Rect, buf: &mut Buffer, state: &mut LogsWidgetState) {
-        // Check if new logs arrived and we were at bottom
-        let input_log_count = self.logs.len();
-        let new_logs_arrived = input_log_count > state.last_log_count;
-        
-        // Create a mutable copy of scroll_y for potential auto-scroll
-        let scroll_y = if new_logs_arrived && state.was_at_bottom {
-            // Auto-scroll to bottom when new logs arrive
-            usize::MAX
-        } else {
-            self.scroll_y
-        };
-
-        log::debug!("render with vals: input_lc={} last_lc={} new_logs?={} was_at_bottom={} scroll_in={} scroll={}",
-            input_log_count,
-            state.last_log_count,
-            new_logs_arrived,
+        log::debug!("render with vals: log_count={} was_at_bottom={} scroll={}",
+            self.logs.len(),
             state.was_at_bottom,
             self.scroll_y,
-            scroll_y,
         );
+        // the gutter is left of the logs, which wrap at what's left over
+        let gutter = self.line_numbers.map_or(0, |numbers| numbers.width);
+        let gutter_x = area.x;
+        let area = Rect { x: area.x + gutter, width: area.width.saturating_sub(gutter), ..area };
         
         let width: usize = area.width.into();
         let mut yy = 0;
-        let (log_idx, char_offset, scroll_y_actual, at_bottom) = LogsWidget::get_log_at_scroll_pos(&self.logs, area, scroll_y);        
+        let (log_idx, line_in_log, scroll_y_actual, at_bottom, line_count) = LogsWidget::get_log_at_scroll_pos(self.line_offsets, area, self.scroll_y);
         
         // Update state
         state.actual_scroll_y = scroll_y_actual;
-        state.last_log_count = input_log_count;
         state.was_at_bottom = at_bottom;
+        state.line_count = line_count;
         state.height = area.height;
+        state.width = area.width;
+        state.top_index = log_idx;
+        state.top = self.logs.get(log_idx).map(|log| {
+            // the line above it for a gap has no char
+            let top_char = line_in_log.checked_sub(gap_lines(log)).map(|line| match self.h_scroll {
+                Some(_) => 0,
+                None => line_starts(log, width, &self.wrap).get(line).copied().unwrap_or(0),
+            });
+            (log.id, top_char.map(|top_char| top_char as isize - log.message_start as isize))
+        });
+        state.y = area.y;
+        state.lines.clear();
 
-        let mut char_offset = char_offset;
         let logs_page = self.logs.get(log_idx..)
             .unwrap_or_default();
-        for log in logs_page.iter() {
-            let mut line = String::new();
-            for c in log.chars() {
-                if char_offset > 0 {
-                    // discard
-                    char_offset -= 1;
+        // screen lines of the log at the top that are scrolled past
+        let mut skip = line_in_log;
+        for (idx, log) in logs_page.iter().enumerate().map(|(i, log)| (log_idx + i, log)) {
+            if yy >= area.height {
+                break;
+            }
+            let above = match (log.skipped, log.gap) {
+                (Some(skipped), _) => Some(skipped_line(skipped, width)),
+                (None, Some(gap)) => Some(gap_line(gap, width)),
+                (None, None) => None,
+            };
+            if let Some(above) = above {
+                if skip == 0 {
+                    buf.set_stringn(area.x, area.y + yy, above, width, Style::default().dim());
+                    state.lines.push(idx);
+                    yy += 1;
+                } else {
+                    skip -= 1;
+                }
+            }
+            // indexed by char, each shown in the columns `expand` says
+            let chars: Vec<char> = log.text.chars().collect();
+            let starts = match self.h_scroll {
+                Some(h_scroll) => vec![h_scroll.min(chars.len())],
+                None => line_starts(log, width, &self.wrap),
+            };
+            let marker_start = dimmed_start(log);
+            let style = match &self.level_styles {
+                _ if log.is_event => Style::default().dim().italic(),
+                // only guessed for lines that are on screen, as the rest aren't
+                // gone through
+                Some(styles) => styles.get(log.level.or_else(|| severity::guess(log.message()))),
+                None => Style::default(),
+            };
+            let file_prefix = file_prefix(log).filter(|_| self.color_files);
+            let highlights: Vec<(Vec<(usize, usize)>, Style)> = self.highlights.iter()
+                .map(|highlight| (highlight::message_matches(log, &highlight.regex), highlight.style))
+                .collect();
+            for (i, &start) in starts.iter().enumerate() {
+                if i < skip {
                     continue;
                 }
-                line.push(c);
-                if line.len() >= width {
-                    let y_pos = area.y + yy;
-                    if y_pos < area.height {
-                        buf.set_stringn(area.x, area.y + yy, &line, usize::MAX, Style::default());
+                let y_pos = area.y + yy;
+                if y_pos >= area.bottom() {
+                    break;
+                }
+                let end = match self.h_scroll {
+                    Some(_) => fit_end(&chars, start, width, self.wrap.tab_width),
+                    None => starts.get(i + 1).copied().unwrap_or(chars.len()),
+                };
+                // a newline ends the screen line when wrapping, otherwise
+                // it's shown in place, taking a column like any other char
+                let line: String = chars[start..end].iter()
+                    .filter_map(|c| match *c {
+                        '\n' if self.h_scroll.is_some() => Some('↵'),
+                        '\n' => None,
+                        c => Some(c),
+                    })
+                    .collect();
+                // after the continuation, on screen lines the log is
+                // wrapped onto
+                let continued = self.h_scroll.is_none() && start > 0 && chars[start - 1] != '\n';
+                let continuation = match continued {
+                    true => self.wrap.continuation(&leading_whitespace(&chars[..start], log.message_start), width),
+                    false => String::new(),
+                };
+                let x = area.x + continuation.width() as u16;
+                state.lines.push(idx);
+                let (line, columns) = expand(&line, self.wrap.tab_width);
+                buf.set_stringn(area.x, y_pos, &continuation, width, Style::default().dim());
+                buf.set_stringn(x, y_pos, &line, usize::from(area.right().saturating_sub(x)), style);
+                // on the log's first screen line, the rest are left blank
+                if let Some(numbers) = self.line_numbers.filter(|numbers| i == 0 && idx >= numbers.skip) {
+                    let number = format!("{:>1$} ", numbers.first + idx - numbers.skip, usize::from(gutter) - 1);
+                    buf.set_stringn(gutter_x, y_pos, number, gutter.into(), Style::default().dim());
+                }
+                let shown = ScreenLine { x, y: y_pos, start, columns };
+                if let Some(prefix) = file_prefix {
+                    shown.style_chars(buf, area, prefix, Style::default().fg(color::file_color(&log.file)));
+                }
+                // a color set on an earlier screen line of the log still
+                // applies, so runs are clipped to this one rather than skipped
+                for (run, &(run_start, run_style)) in log.colors.iter().enumerate() {
+                    let run_end = log.colors.get(run + 1).map_or(end, |(next, _)| *next);
+                    shown.style_chars(buf, area, (run_start, run_end), run_style);
+                }
+                if let Some(marker_start) = marker_start {
+                    shown.style_chars(buf, area, (marker_start, end), Style::default().dim());
+                }
+                // a match that wraps is clipped to each screen line it's on
+                for (ranges, highlight_style) in &highlights {
+                    for &range in ranges {
+                        shown.style_chars(buf, area, range, *highlight_style);
                     }
-                    line = String::new();
-                    yy += 1;
-                } 
-            }
-            let y_pos = area.y + yy;
-            if y_pos < area.height {
-                buf.set_stringn(area.x, area.y + yy, &line, usize::MAX, Style::default());
+                }
+                if self.selected == Some(idx) {
+                    buf.set_style(Rect::new(area.x, y_pos, area.width, 1), Style::default().reversed());
+                }
+                yy += 1;
             }
-            yy += 1
+            skip = 0;
         }
     }
 
-    /// Calculates which log entry and character offset to start rendering from based on scroll position.
+    /// Calculates which log entry and which of its screen lines to start rendering from based on scroll position.
     /// 
-    /// This function handles text wrapping by calculating how many screen lines each log entry
-    /// occupies given the terminal width, then determines where to start rendering based on
-    /// the current scroll position.
+    /// This function goes by how many screen lines come before each log, given the terminal
+    /// width and how logs are wrapped, so that only the logs on screen are laid out.
     /// 
     /// # Arguments
-    /// * `logs` - The logs to operate on
+    /// * `offsets` - Lines before each log and then all of them, see `LineCounts`
     /// * `area` - The rendering area containing width and height information
     /// * `scroll_y` - The line to start from
     /// 
     /// # Returns
-    /// A tuple `(log_index, char_offset, line_offset, at_bottom)` where:
+    /// A tuple `(log_index, line_in_log, line_offset, at_bottom, line_count)` where:
     /// * `log_index` - Index of the log entry to start rendering from
-    /// * `char_offset` - Number of characters to skip within that log entry
+    /// * `line_in_log` - Number of that log's screen lines to skip, including the one for a gap before it
     /// * `line_offset` - Actual number of lines scrolled.
     /// * `at_bottom` - true if the line_offset returned is the last line
+    /// * `line_count` - Number of lines all the logs take
     /// 
     /// # Example
     /// Given logs with wrapping at width=10:
@@ -121,44 +439,289 @@ impl LogsWidget {
     /// - Log 1: "short" (5 chars = 1 line)
     /// - Log 2: "very long message here" (22 chars = 3 lines)
     /// 
-    /// If scroll_y=3, this would return (2, 10, 3, true) meaning start at log 2,
-    /// skip 10 characters (start from "message here").
-    fn get_log_at_scroll_pos(logs: &[String], area: Rect, scroll_y: usize) -> (usize, usize, usize, bool) {
-        let width: usize = area.width.into();
+    /// If scroll_y=3, this would return (2, 0, 3, true, 6) meaning start at log 2,
+    /// from its first line.
+    fn get_log_at_scroll_pos(offsets: &[usize], area: Rect, scroll_y: usize) -> (usize, usize, usize, bool, usize) {
         let height: usize = area.height.into();
-        let target_line = scroll_y.saturating_add(height);        
-        let mut lines = vec![];
-        let mut at_bottom = false;
-
-        'outer: for (log_idx, log) in logs.iter().enumerate() {
-            let is_last_log = log_idx == logs.len() - 1;
-            let log_chars = log.chars().count();
-            let lines_for_this_log = if log_chars == 0 { 1 } else { (log_chars + width - 1) / width };
-            for line_idx in 0..lines_for_this_log {
-                let char_offset = line_idx * width;
-                log::debug!("is_last_log={} idx={} last_idx={}", is_last_log, line_idx, lines_for_this_log.saturating_sub(1));
-                at_bottom = is_last_log && line_idx == lines_for_this_log.saturating_sub(1);
-                lines.push((log_idx, char_offset));
-                if lines.len() == target_line {
-                    break 'outer;
-                }
-            }
-        }
-        
-        let real_scroll_y = lines.len().saturating_sub(height);
-        let (log_idx, char_offset) = *lines.get(real_scroll_y).unwrap_or(&(0, 0));
-        return (log_idx, char_offset, real_scroll_y, at_bottom);
+        let total_lines = offsets.last().copied().unwrap_or(0);
+        let real_scroll_y = scroll_y.min(total_lines.saturating_sub(height));
+        // the last log that starts at or before the top line
+        let log_idx = offsets.partition_point(|before| *before <= real_scroll_y).saturating_sub(1);
+        let line_in_log = offsets.get(log_idx).map_or(0, |before| real_scroll_y - before);
+        let at_bottom = total_lines > 0 && real_scroll_y + height >= total_lines;
+        (log_idx, line_in_log, real_scroll_y, at_bottom, total_lines)
     }
 
+    #[must_use = "method moves the value of self and returns the modified value"]
+    fn line_offsets(mut self, line_offsets: &'a [usize]) -> Self {
+        self.line_offsets = line_offsets;
+        self
+    }
 
     #[must_use = "method moves the value of self and returns the modified value"]
     pub const fn scroll(mut self, y: usize) -> Self {
         self.scroll_y = y;
         self
     } 
+
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn highlights(mut self, highlights: Vec<Highlight>) -> Self {
+        self.highlights = highlights;
+        self
+    }
+
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub const fn color_files(mut self, color_files: bool) -> Self {
+        self.color_files = color_files;
+        self
+    }
+
+    #[must_use = "method moves the value of self and returns the modified value"]
+    const fn line_numbers(mut self, line_numbers: Option<LineNumbers>) -> Self {
+        self.line_numbers = line_numbers;
+        self
+    }
+
+    #[must_use = "method moves the value of self and returns the modified value"]
+    const fn h_scroll(mut self, h_scroll: Option<usize>) -> Self {
+        self.h_scroll = h_scroll;
+        self
+    }
+
+    #[must_use = "method moves the value of self and returns the modified value"]
+    const fn selected(mut self, selected: Option<usize>) -> Self {
+        self.selected = selected;
+        self
+    }
+
+    #[must_use = "method moves the value of self and returns the modified value"]
+    fn wrap(mut self, wrap: Wrap) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    #[must_use = "method moves the value of self and returns the modified value"]
+    fn level_styles(mut self, level_styles: Option<&'a LevelStyles>) -> Self {
+        self.level_styles = level_styles;
+        self
+    }
+}
+
+/// Where a log's chars from `start` on are on the screen line at `y`, from
+/// column `x`
+struct ScreenLine {
+    x: u16,
+    y: u16,
+    start: usize,
+    /// See `expand`
+    columns: Vec<usize>,
+}
+
+impl ScreenLine {
+    /// Style the chars `from..to` of the log, where they're on this line
+    fn style_chars(&self, buf: &mut Buffer, area: Rect, (from, to): (usize, usize), style: Style) {
+        let end = self.start + self.columns.len() - 1;
+        let (from, to) = (from.max(self.start), to.min(end));
+        if from >= to {
+            return;
+        }
+        let (from_x, to_x) = (self.columns[from - self.start], self.columns[to - self.start]);
+        let chars_area = Rect::new(self.x + from_x as u16, self.y, (to_x - from_x) as u16, 1);
+        buf.set_style(chars_area.intersection(area), style);
+    }
+}
+
+/// How `grapheme` is shown `column` columns into a screen line: a tab as
+/// spaces up to the next multiple of `tab_width`, and other control chars
+/// as their symbol from Unicode's Control Pictures, e.g. `␛` for an escape
+/// that wasn't stripped, as the terminal would otherwise act on them and
+/// garble the screen.
+fn shown_grapheme(grapheme: &str, column: usize, tab_width: usize) -> Cow<'_, str> {
+    if grapheme == "\t" {
+        let tab_width = tab_width.max(1);
+        return Cow::Owned(" ".repeat(tab_width - column % tab_width));
+    }
+    if !grapheme.chars().any(|c| c.is_ascii_control()) {
+        return Cow::Borrowed(grapheme);
+    }
+    Cow::Owned(grapheme.chars()
+        .map(|c| match c {
+            '\x7f' => '␡',
+            c if c.is_ascii_control() => char::from_u32(0x2400 + c as u32).unwrap_or(c),
+            c => c,
+        })
+        .collect())
+}
+
+/// `line` as it's shown, see `shown_grapheme`, with the column each of its
+/// chars is shown from and then where the line ends. Columns go by
+/// grapheme, as the terminal lays them out: a combining accent is in the
+/// column of the letter it's on, and CJK and most emoji take two.
+fn expand(line: &str, tab_width: usize) -> (String, Vec<usize>) {
+    let mut shown = String::with_capacity(line.len());
+    let mut columns = Vec::with_capacity(line.len() + 1);
+    let mut column = 0;
+    for grapheme in line.graphemes(true) {
+        let grapheme_shown = shown_grapheme(grapheme, column, tab_width);
+        columns.extend(std::iter::repeat_n(column, grapheme.chars().count()));
+        column += grapheme_shown.width();
+        shown.push_str(&grapheme_shown);
+    }
+    columns.push(column);
+    (shown, columns)
+}
+
+/// Char index up to which `chars` from `start` fit in `width` columns, by
+/// whole graphemes, for rows that aren't wrapped
+fn fit_end(chars: &[char], start: usize, width: usize, tab_width: usize) -> usize {
+    let rest: String = chars[start.min(chars.len())..].iter().collect();
+    let mut end = start;
+    let mut column = 0;
+    for grapheme in rest.graphemes(true) {
+        // shown as `↵`, see `render_logs`
+        column += if grapheme == "\n" { 1 } else { shown_grapheme(grapheme, column, tab_width).width() };
+        if column > width {
+            break;
+        }
+        end += grapheme.chars().count();
+    }
+    end.min(chars.len())
 }
 
-impl StatefulWidget for LogsWidget {
+/// Char range of `log.file` in its text, if it's shown
+fn file_prefix(log: &LogLine) -> Option<(usize, usize)> {
+    // a space between it and the message
+    let end = log.message_start.checked_sub(1)?;
+    let start = end.checked_sub(log.file.chars().count())?;
+    (!log.file.is_empty()).then_some((start, end))
+}
+
+/// Char index each screen line of `log` starts at, wrapping at `width`
+/// columns, as it's shown by `expand`, and at the newlines of multi-line
+/// records. Lines are only broken
+/// between graphemes, and one that's two columns wide goes on the next line
+/// whole rather than being split by the edge. When wrapping at words,
+/// they're broken after the last whitespace instead, if that doesn't take
+/// more than `WORD_WRAP_MAX_CARRY` of the line to the next one, so that only
+/// words longer than that are split. Screen lines a line is wrapped onto
+/// have `Wrap::continuation` before them, and less room for the rest.
+///
+/// Everything that lays out logs goes by this, so that what's scrolled
+/// through is what's drawn.
+fn line_starts(log: &LogLine, width: usize, wrap: &Wrap) -> Vec<usize> {
+    let mut starts = vec![0];
+    let mut column = 0;
+    // columns of the screen line left after the continuation, if any
+    let mut line_width = width;
+    // see `leading_whitespace`, and whether it's still going
+    let mut leading = String::new();
+    let mut in_leading = true;
+    let mut line_start = log.message_start;
+    // char index after the last whitespace on the line, and its column
+    let mut last_space: Option<(usize, usize)> = None;
+    let mut i = 0;
+    for grapheme in log.text.graphemes(true) {
+        let chars = grapheme.chars().count();
+        if grapheme == "\n" {
+            starts.push(i + chars);
+            column = 0;
+            line_width = width;
+            leading.clear();
+            in_leading = true;
+            line_start = i + chars;
+            last_space = None;
+            i += chars;
+            continue;
+        }
+        let mut grapheme_width = shown_grapheme(grapheme, column, wrap.tab_width).width();
+        if column + grapheme_width > line_width {
+            let carried = last_space.map(|(_, space_column)| column - space_column);
+            let wrapped = starts.len();
+            match last_space.zip(carried).filter(|(_, carried)| *carried <= line_width / WORD_WRAP_MAX_CARRY) {
+                Some(((after_space, _), carried)) if wrap.words => {
+                    starts.push(after_space);
+                    column = carried;
+                }
+                // one that's wider than the screen is all a line can have
+                _ if column > 0 => {
+                    starts.push(i);
+                    column = 0;
+                }
+                _ => {}
+            }
+            if starts.len() > wrapped {
+                line_width = width.saturating_sub(wrap.continuation(&leading, width).width());
+            }
+            last_space = None;
+            // a tab is as wide as it is where it's wrapped to
+            grapheme_width = shown_grapheme(grapheme, column, wrap.tab_width).width();
+            // the word carried over may be too long for the rest of the line
+            if column + grapheme_width > line_width && column > 0 {
+                starts.push(i);
+                column = 0;
+            }
+        }
+        let whitespace = grapheme.chars().all(char::is_whitespace);
+        if in_leading && i >= line_start {
+            in_leading = whitespace;
+            if whitespace {
+                leading.push_str(grapheme);
+            }
+        }
+        column += grapheme_width;
+        i += chars;
+        if wrap.words && whitespace {
+            last_space = Some((i, column));
+        }
+    }
+    starts
+}
+
+/// The whitespace the line that `before`, a log's chars up to some point,
+/// ends on starts with: the message, or a later line of a multi-line record
+fn leading_whitespace(before: &[char], message_start: usize) -> String {
+    let line_start = before.iter().rposition(|c| *c == '\n').map_or(message_start, |newline| newline + 1);
+    let line: String = before[line_start.min(before.len())..].iter().collect();
+    line.graphemes(true).take_while(|grapheme| grapheme.chars().all(char::is_whitespace)).collect()
+}
+
+/// Char index to show `log` from for its end to be at the right edge of
+/// `width` columns, when rows aren't wrapped. Tabs are counted as wide as
+/// they can be, as that depends on where the line starts.
+fn end_start(log: &str, width: usize, tab_width: usize) -> usize {
+    let mut start = log.chars().count();
+    let mut column = 0;
+    for grapheme in log.graphemes(true).rev() {
+        column += if grapheme == "\n" { 1 } else { shown_grapheme(grapheme, 0, tab_width).width() };
+        if column > width {
+            break;
+        }
+        start -= grapheme.chars().count();
+    }
+    start
+}
+
+/// Char index from which `log` is shown dimmed: its repeat count and the
+/// truncation marker before that, if it has either
+fn dimmed_start(log: &LogLine) -> Option<usize> {
+    if log.repeats == 0 {
+        return truncation_marker_start(&log.text);
+    }
+    let text = log.text.strip_suffix(&repeat_suffix(log.repeats))?;
+    truncation_marker_start(text).or(Some(text.chars().count()))
+}
+
+/// Char index of the `…[truncated N bytes]` marker the watcher adds to lines
+/// it cut short, if `log` ends with one
+fn truncation_marker_start(log: &str) -> Option<usize> {
+    if !log.ends_with(" bytes]") {
+        return None;
+    }
+    let byte_index = log.rfind("…[truncated ")?;
+    Some(log[..byte_index].chars().count())
+}
+
+impl StatefulWidget for LogsWidget<'_> {
     type State = LogsWidgetState;
     
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
@@ -169,26 +732,1182 @@ impl StatefulWidget for LogsWidget {
 
 #[derive(Default)]
 pub struct App {
+    /// The pane keys go to, see `Pane`
+    pane: Pane,
+    /// The other pane, when the screen is split, see `toggle_split`
+    split: Option<Pane>,
+    /// Rows stored since the screen was paused with `p`, which aren't shown
+    /// until it's unpaused, see `toggle_pause`
+    paused: Option<u64>,
+    pub stats: IngestStats,
+    show_stats: bool,
+    /// The keys are listed over everything else, until the next key press
+    pub show_help: bool,
+    /// Files without a new line for longer than this are marked stale
+    pub stale_after: Duration,
+    /// Lines `--timestamp-format` didn't match
+    pub unparsed_timestamps: usize,
+    /// Repeated lines are stored as a count on the line before
+    pub collapse_repeats: bool,
+    /// Events are shown in between lines. The store leaves them out
+    /// otherwise, see `LogStore::set_show_events`
+    pub show_events: bool,
+    /// Shown above the logs, e.g. to say older ones were dropped
+    top_note: Option<LogLine>,
+    /// A line is shown between rows further apart than `gap_after`, see
+    /// `mark_gaps`
+    pub show_gaps: bool,
+    pub gap_after: Duration,
+    /// The session's database, or none if it's in memory
+    pub db: Option<PathBuf>,
+    /// Text to pick out wherever it's shown, see `Args::highlights`. The
+    /// current search's matches are laid over them.
+    pub highlights: Vec<Highlight>,
+    /// Show each file's prefix in a color of its own, see `Args::color_files`
+    pub color_files: bool,
+    /// How lines are shown by their level, see `Args::level_styles`
+    pub level_styles: LevelStyles,
+    /// Show lines by their level at all, or all the same when it's wrong
+    /// too often
+    pub level_colors: bool,
+    /// Show each row's number left of it, counting the rows shown, the same
+    /// as the status bar does
+    pub line_numbers: bool,
+    /// Show each row on one screen line, scrolled sideways by each pane's
+    /// `h_scroll` chars, rather than wrapping them
+    no_wrap: bool,
+    /// How rows are wrapped, when they are, see `line_starts`
+    pub wrap: Wrap,
+    /// Bytes read and to read of each file whose history is still loading
+    loading: BTreeMap<String, (u64, u64)>,
+    /// What's being typed, shown instead of the status bar
+    pub prompt: Option<Prompt>,
+    /// Said in the status bar until the next key press, e.g. how an export
+    /// went
+    message: Option<String>,
+    /// The last search, see `set_matches`
+    search: Option<Search>,
+    /// Rows in the full-text index and rows there are, while it's built
+    /// for a search, see `LogStore::index_rows`
+    indexing: Option<(u64, u64)>,
+    /// The row the cursor was on, shown whole over everything else
+    pub details: Option<Details>,
+    /// The most common lines, over everything else, see `open_frequencies`
+    pub frequencies: Option<Frequencies>,
+    /// Rows marked to jump back to, see `set_mark`
+    marks: Marks,
+    /// The marks are listed over everything else, until the next key press
+    pub show_marks: bool,
+    /// The files, left of the logs, while it's open, see `toggle_sidebar`
+    sidebar: Option<Sidebar>,
+    /// Rows stored each second, see `Activity`
+    pub activity: Activity,
+    /// Where the sparkline of `activity` was last drawn, to open the stats
+    /// when it's clicked
+    sparkline_area: Option<Rect>,
+    /// Lines that matched `--alert`, see `Alerts`
+    pub alerts: Alerts,
+    /// Rows pinned above the logs, see `Pins`
+    pins: Pins,
+    /// Which file's rows each pane shows, see `Tabs`
+    tabs: Tabs,
+    /// Numbers picked out of lines, charted below the logs, see `Graphs`
+    pub graphs: Graphs,
+}
+
+/// Rows in a part of the screen of their own, scrolled and filtered apart
+/// from the others', see `App::toggle_split`
+#[derive(Default)]
+struct Pane {
+    /// Index of the pane, which the store knows it by, see `store::PANES`
+    id: usize,
     vertical_scroll_pos: usize,
-    logs: Vec<String>,
-    pub logs_widget_state: LogsWidgetState,
+    /// The window of rows the screen is in, see `wanted_window`
+    logs: Vec<LogLine>,
+    /// Row number of the first one in `logs`
+    window_start: usize,
+    /// `logs` goes up to the last row
+    at_end: bool,
+    /// Rows there are, past the pane's filters
+    total: usize,
+    /// The screen is at the last row and stays there as rows are added,
+    /// since it was scrolled to the bottom or because of `follow_mode`
+    following: bool,
+    /// Follow mode, turned on with `f`: `following` stays on until scrolling
+    /// up, even when the screen isn't at the bottom yet, e.g. while the
+    /// rows around it are queried. It's what the status bar shows.
+    follow_mode: bool,
+    /// Index in `logs` of the row at the top of the screen
+    top_row: usize,
+    logs_widget_state: LogsWidgetState,
+    /// Lines less severe than this are hidden, lines without a level count
+    /// as INFO. The store does the hiding, see `LogStore::set_min_level`
+    min_level: Option<Level>,
+    /// Chars rows are scrolled sideways by, when they aren't wrapped, see
+    /// `App::no_wrap`
+    h_scroll: usize,
+    /// Row to put at the top of the screen once the window has it, see
+    /// `jump_to`
+    jump: Option<Jump>,
+    /// Id of the row the cursor is on, which is shown reversed, see
+    /// `move_cursor`
+    selected: Option<i64>,
+    /// Where it was last drawn, with its scrollbar, see `App::click`
+    area: Rect,
+    /// Where the scrollbar was last drawn, see `render_scrollbar`
+    scrollbar: Rect,
+    /// Screen lines each of `shown` takes, as of when the screen was last
+    /// drawn
+    line_counts: LineCounts,
+    /// The scrollbar was clicked on, so dragging moves along it, until a
+    /// click somewhere else
+    dragging_scrollbar: bool,
+    /// Files whose rows are hidden, by file id. The store does the hiding,
+    /// see `LogStore::set_hidden_files`
+    hidden_files: BTreeSet<String>,
+    /// Only rows of this are shown, see `App::set_template`
+    template: Option<Template>,
+    /// The tab it's on: only this file's rows are shown, by file id, or
+    /// those of all files. The store does the filtering, see
+    /// `LogStore::set_file`
+    file: Option<String>,
+    /// Only the rows this matches are shown, with this many rows before
+    /// and after each, see `App::toggle_context`
+    context: Option<(Regex, usize)>,
+}
+
+impl Pane {
+    /// Where it is and how it's filtered, for the line above it when the
+    /// screen is split, as the status bar says for the focused one
+    fn title(&self) -> String {
+        let line = (self.window_start + self.top_row + 1).min(self.total);
+        let mut title = format!(" {}/{}", line, self.total);
+        if let Some(file) = &self.file {
+            title.push_str(&format!("  {}", file));
+        }
+        if self.follow_mode {
+            title.push_str("  FOLLOW");
+        }
+        if let Some(min_level) = self.min_level {
+            title.push_str(&format!("  {}+", min_level));
+        }
+        if !self.hidden_files.is_empty() {
+            title.push_str(&format!("  {} hidden", self.hidden_files.len()));
+        }
+        if self.template.is_some() {
+            title.push_str("  one line's");
+        }
+        if let Some((_, lines)) = &self.context {
+            title.push_str(&format!("  ±{} around matches", lines));
+        }
+        title.push(' ');
+        title
+    }
+
+    /// The rows to query for the screen, if the ones there are won't do:
+    /// rows were `added`, rows before the screen may have `moved` (e.g. they
+    /// were deleted or filtered out), or it's getting near the end of the
+    /// window. The screen's top row stays at the top of the screen when the
+    /// window changes, see `App::show_window`.
+    fn wanted_window(&self, added: bool, moved: bool) -> Option<Window> {
+        match self.jump {
+            Some(Jump::Order(order)) => return Some(Window::Around(order)),
+            Some(Jump::Row(row)) => return Some(Window::From(row.saturating_sub(WINDOW_ROWS / 2))),
+            None => {}
+        }
+        if self.following {
+            return (added || moved || !self.at_end).then_some(Window::Last);
+        }
+        if moved {
+            let top = self.logs.get(self.top_row).map(|log| log.order);
+            return Some(top.map_or(Window::From(0), Window::Around));
+        }
+        if added && self.at_end {
+            // there may be more after the last row, or room for it
+            return Some(Window::From(self.window_start));
+        }
+        let near_start = self.window_start > 0 && self.top_row < WINDOW_MARGIN;
+        let near_end = !self.at_end && self.top_row + WINDOW_MARGIN > self.logs.len();
+        (near_start || near_end).then(|| Window::From((self.window_start + self.top_row).saturating_sub(WINDOW_ROWS / 2)))
+    }
+}
+
+/// A row to put at the top of the screen, see `App::jump_to`
+#[derive(Clone, Copy)]
+enum Jump {
+    /// The row at this `LogLine::order`
+    Order((i64, i64)),
+    /// The row with this row number, counting from 0
+    Row(usize),
+}
+
+/// A line typed in place of the status bar
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub input: String,
+}
+
+/// What a `Prompt` is for
+#[derive(Clone, Copy, PartialEq)]
+pub enum PromptKind {
+    /// A search, after `/`, which Ctrl-r switches the mode of
+    Search(SearchMode),
+    /// A file to export the rows shown to, after `w`
+    Export,
+    /// A line to go to, after `:`, see `App::go_to_line`
+    Line,
+    /// A regex to chart the numbers of, after `a`, see `Graphs`
+    Graph,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind) -> Self {
+        Prompt { kind, input: String::new() }
+    }
+
+    fn label(&self) -> &'static str {
+        match self.kind {
+            PromptKind::Search(SearchMode::Text) => "/",
+            PromptKind::Search(SearchMode::Regex) => "regex /",
+            PromptKind::Search(SearchMode::Words) => "words /",
+            PromptKind::Export => "write to: ",
+            PromptKind::Line => ":",
+            PromptKind::Graph => "graph: ",
+        }
+    }
+
+    /// Search the next way, see `SearchMode::next`
+    pub fn switch_mode(&mut self) {
+        if let PromptKind::Search(mode) = &mut self.kind {
+            *mode = mode.next();
+        }
+    }
+}
+
+/// Rows found by a search, which `n` and `N` go through
+struct Search {
+    query: String,
+    mode: SearchMode,
+    /// Where in a row the query matches, see `SearchMode::matcher`
+    matcher: Option<Regex>,
+    /// `LogLine::order` of each, in the order they're shown, or why the
+    /// search failed
+    matches: Result<Vec<(i64, i64)>, String>,
+    /// Index in `matches` of the one last jumped to
+    current: usize,
 }
 
 impl App {
     pub fn scroll_down(&mut self, scroll_amount: usize) {
-        self.vertical_scroll_pos = self.vertical_scroll_pos.saturating_add(scroll_amount);
+        self.pane.vertical_scroll_pos = self.pane.vertical_scroll_pos.saturating_add(scroll_amount);
     }
 
     pub fn scroll_up(&mut self, scroll_amount: usize) {
-        self.vertical_scroll_pos = self.vertical_scroll_pos.saturating_sub(scroll_amount);
+        self.pane.vertical_scroll_pos = self.pane.vertical_scroll_pos.saturating_sub(scroll_amount);
+        self.pane.following = false;
+        self.pane.follow_mode = false;
+    }
+
+    /// Go to the last row, and stay there as rows are added
+    pub fn follow(&mut self) {
+        self.pane.following = true;
+        self.pane.vertical_scroll_pos = usize::MAX;
+    }
+
+    /// Go to the first row, including any note above it
+    pub fn go_to_top(&mut self) {
+        if self.pane.window_start == 0 {
+            self.pane.following = false;
+            self.pane.follow_mode = false;
+            self.pane.vertical_scroll_pos = 0;
+            self.pane.top_row = 0;
+        } else {
+            // before any row there is
+            self.jump_to(Jump::Order((i64::MIN, i64::MIN)));
+        }
+    }
+
+    /// Stop showing rows as they're stored, or show them again. The screen
+    /// goes to the last row when it's unpaused if it was following, and
+    /// stays where it is otherwise.
+    pub fn toggle_pause(&mut self) {
+        self.paused = match self.paused {
+            Some(_) => None,
+            None => Some(0),
+        };
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.is_some()
+    }
+
+    /// Count `rows` stored while paused, which the status bar says
+    pub fn add_paused(&mut self, rows: u64) {
+        if let Some(paused) = &mut self.paused {
+            *paused += rows;
+        }
+    }
+
+    /// Go to the last row, turning follow mode on
+    pub fn go_to_bottom(&mut self) {
+        self.pane.follow_mode = true;
+        self.follow();
+    }
+
+    /// Turn follow mode on, going to the last row, or off, staying where
+    /// the screen is, see `follow_mode`
+    pub fn toggle_follow_mode(&mut self) {
+        self.pane.follow_mode = !self.pane.follow_mode;
+        if self.pane.follow_mode {
+            self.follow();
+        } else {
+            self.pane.following = false;
+        }
+    }
+
+    /// Scroll to where `y` is along the scrollbar, if it was clicked at `x`,
+    /// or put the sidebar's cursor on the file there, otherwise select the
+    /// row shown there, see `select_at`. A click in the pane keys don't go
+    /// to has them go there.
+    pub fn click(&mut self, x: u16, y: u16) {
+        if self.pins.area.contains(Position { x, y }) {
+            if let Some(mark) = self.pins.at(y).cloned() {
+                self.jump_to_mark_from_here(mark);
+            }
+            return;
+        }
+        if self.sparkline_area.is_some_and(|area| area.contains(Position { x, y })) {
+            self.toggle_stats();
+            return;
+        }
+        if let Some(sidebar) = self.sidebar.as_mut().filter(|sidebar| sidebar.area.contains(Position { x, y })) {
+            // under the border at the top
+            let index = usize::from(y.saturating_sub(sidebar.area.y + 1));
+            if index < self.stats.file_count() {
+                sidebar.cursor = index;
+            }
+            return;
+        }
+        if self.split.as_ref().is_some_and(|split| split.area.contains(Position { x, y })) {
+            self.switch_pane();
+        }
+        self.pane.dragging_scrollbar = self.pane.scrollbar.contains(Position { x, y });
+        if self.pane.dragging_scrollbar {
+            self.scroll_along_bar(y);
+        } else {
+            self.select_at(y);
+        }
+    }
+
+    /// Scroll to where row `y` of the terminal is along the scrollbar, if
+    /// it's being dragged
+    pub fn drag(&mut self, y: u16) {
+        if self.pane.dragging_scrollbar {
+            self.scroll_along_bar(y);
+        }
+    }
+
+    /// Scroll so that the top of the screen is where the scrollbar's thumb
+    /// would start if it were on row `y` of the terminal, asking for the
+    /// rows there if the window doesn't have them, see `scroll_lines`
+    fn scroll_along_bar(&mut self, y: u16) {
+        let (before, lines) = self.scroll_lines();
+        let height = usize::from(self.pane.logs_widget_state.height);
+        let last = lines.saturating_sub(height);
+        let track = usize::from(self.pane.scrollbar.height.saturating_sub(1)).max(1);
+        let line = (usize::from(y.saturating_sub(self.pane.scrollbar.y)) * last).div_ceil(track).min(last);
+        let window_lines = self.pane.logs_widget_state.line_count;
+        if line < before {
+            self.jump_to(Jump::Row(line));
+        } else if line - before < window_lines || self.pane.at_end {
+            self.pane.following = false;
+            self.pane.follow_mode = false;
+            self.pane.vertical_scroll_pos = line - before;
+        } else {
+            let row = self.pane.window_start + self.pane.logs.len() + (line - before - window_lines);
+            self.jump_to(Jump::Row(row.min(self.pane.total.saturating_sub(1))));
+        }
+    }
+
+    /// Screen lines before the window, and in all, as the scrollbar counts
+    /// them: the window's as they're wrapped, and one for each row out of it
+    fn scroll_lines(&self) -> (usize, usize) {
+        let after = self.pane.total.saturating_sub(self.pane.window_start + self.pane.logs.len());
+        (self.pane.window_start, self.pane.window_start + self.pane.logs_widget_state.line_count + after)
+    }
+
+    /// Move the cursor `step` rows down, or up if it's negative, scrolling
+    /// as it goes off the screen. Without one on screen, it starts on the
+    /// top row going down and the bottom row going up.
+    pub fn move_cursor(&mut self, step: isize) {
+        let shown: Vec<&LogLine> = self.shown().collect();
+        let note_rows = shown.len() - self.pane.logs.len();
+        let cursor = self.pane.selected.and_then(|id| shown.iter().position(|log| log.id == id));
+        let state = &self.pane.logs_widget_state;
+        let idx = match cursor {
+            Some(cursor) => cursor.saturating_add_signed(step),
+            None if step > 0 => state.lines.first().copied().unwrap_or(0),
+            None => state.lines.last().copied().unwrap_or(0),
+        };
+        // the note isn't a row
+        let Some(log) = shown.get(idx.min(shown.len().saturating_sub(1)).max(note_rows)) else {
+            return;
+        };
+        self.pane.selected = Some(log.id);
+        self.pane.following = false;
+        self.pane.follow_mode = false;
+        self.scroll_to_cursor();
+    }
+
+    /// Scroll as little as it takes for all of the row under the cursor to
+    /// be on screen, or its first lines if it's taller than the screen
+    fn scroll_to_cursor(&mut self) {
+        let Some(idx) = self.pane.selected.and_then(|id| self.shown().position(|log| log.id == id)) else {
+            return;
+        };
+        let width = self.pane.logs_widget_state.width.into();
+        let height = usize::from(self.pane.logs_widget_state.height);
+        let mut line_counts = std::mem::take(&mut self.pane.line_counts);
+        line_counts.update(&self.shown().collect::<Vec<_>>(), width, |log| self.line_count(log, width));
+        let offsets = line_counts.offsets();
+        let (start, end, total) = (offsets[idx], offsets[idx + 1], offsets[offsets.len() - 1]);
+        self.pane.line_counts = line_counts;
+        // it can be past the bottom, until the screen is drawn
+        let top = self.pane.vertical_scroll_pos.min(total.saturating_sub(height));
+        if start < top {
+            self.pane.vertical_scroll_pos = start;
+        } else if end > top + height {
+            self.pane.vertical_scroll_pos = start.min(end.saturating_sub(height));
+        } else {
+            self.pane.vertical_scroll_pos = top;
+        }
+    }
+
+    /// The line of the row under the cursor, see `LogLine::line`
+    pub fn cursor_line(&self) -> Option<&str> {
+        let id = self.pane.selected?;
+        self.pane.logs.iter().find(|log| log.id == id).map(LogLine::line)
+    }
+
+    /// Each row on screen as it's shown, with what's in front of it, but
+    /// not wrapped
+    pub fn screen_lines(&self) -> Vec<&str> {
+        let shown: Vec<&LogLine> = self.shown().collect();
+        let mut lines: Vec<&str> = vec![];
+        let mut last = None;
+        for &idx in &self.pane.logs_widget_state.lines {
+            // a row wrapped onto more than one screen line is only copied once
+            if last != Some(idx) {
+                lines.extend(shown.get(idx).map(|log| log.text.as_str()));
+            }
+            last = Some(idx);
+        }
+        lines
     }
 
-    pub fn set_scroll(&mut self, scroll_pos: usize) {
-      self.vertical_scroll_pos = scroll_pos;
+    /// Show all of the row under the cursor in a popup, see `Details`
+    pub fn open_details(&mut self) {
+        match self.pane.selected.and_then(|id| self.pane.logs.iter().find(|log| log.id == id)) {
+            Some(log) => self.details = Some(Details::new(log.clone())),
+            None => self.set_message(String::from("no line under the cursor, j and k move it")),
+        }
+    }
+
+    /// Put the cursor on the row shown on row `y` of the terminal, if any,
+    /// going by where the screen was last drawn
+    fn select_at(&mut self, y: u16) {
+        let state = &self.pane.logs_widget_state;
+        let Some(&idx) = y.checked_sub(state.y).and_then(|line| state.lines.get(usize::from(line))) else {
+            return;
+        };
+        let note_rows = self.shown().count() - self.pane.logs.len();
+        // the note isn't a row
+        if let Some(log) = idx.checked_sub(note_rows).and_then(|idx| self.pane.logs.get(idx)) {
+            self.pane.selected = Some(log.id);
+        }
+    }
+
+    pub fn toggle_stats(&mut self) {
+        self.show_stats = !self.show_stats;
+    }
+
+    /// Wrap rows, or show each on one screen line, keeping the row at the
+    /// top of the screen there
+    pub fn toggle_wrap(&mut self) {
+        self.no_wrap = !self.no_wrap;
+        for pane in self.pane_ids() {
+            self.with_pane(pane, |app| {
+                app.pane.h_scroll = 0;
+                app.pane.line_counts.clear();
+                app.keep_top_row();
+            });
+        }
+    }
+
+    /// Wrap rows at words, or anywhere, keeping the row at the top of the
+    /// screen there
+    pub fn toggle_word_wrap(&mut self) {
+        self.wrap.words = !self.wrap.words;
+        for pane in self.pane_ids() {
+            self.with_pane(pane, |app| {
+                app.pane.line_counts.clear();
+                app.keep_top_row();
+            });
+        }
+    }
+
+    /// Scroll to the top of the row at the top of the screen, after rows
+    /// were laid out differently
+    fn keep_top_row(&mut self) {
+        let top_idx = self.pane.logs_widget_state.top_index;
+        let width = self.pane.logs_widget_state.width.into();
+        let shown: Vec<&LogLine> = self.shown().collect();
+        self.pane.vertical_scroll_pos = shown[..top_idx.min(shown.len())].iter()
+            .map(|log| self.line_count(log, width))
+            .sum();
     }
 
-    pub fn set_log_lines(&mut self, logs: Vec<String>) {
-        self.logs = logs;
+    /// Scroll sideways by half the screen, to the right if `right`, when rows
+    /// aren't wrapped
+    pub fn scroll_sideways(&mut self, right: bool) {
+        if !self.no_wrap {
+            return;
+        }
+        let half = usize::from(self.pane.logs_widget_state.width / 2).max(1);
+        self.pane.h_scroll = if right { self.pane.h_scroll + half } else { self.pane.h_scroll.saturating_sub(half) };
+    }
+
+    /// Scroll sideways to the start of rows, or so the end of the longest
+    /// one on screen is at the right edge, when rows aren't wrapped
+    pub fn scroll_to_side(&mut self, end: bool) {
+        if !self.no_wrap {
+            return;
+        }
+        let width = self.pane.logs_widget_state.width.into();
+        let on_screen = self.shown()
+            .skip(self.pane.logs_widget_state.top_index)
+            .take(self.pane.logs_widget_state.height.into());
+        let last_start = on_screen.map(|log| end_start(&log.text, width, self.wrap.tab_width)).max().unwrap_or(0);
+        self.pane.h_scroll = if end { last_start } else { 0 };
+    }
+
+    /// Char index each screen line of `log` starts at, see `line_starts`,
+    /// only the first when rows aren't wrapped
+    fn line_starts(&self, log: &LogLine, width: usize) -> Vec<usize> {
+        if self.no_wrap {
+            vec![0]
+        } else {
+            line_starts(log, width, &self.wrap)
+        }
+    }
+
+    /// Screen lines `log` takes, its own and the one above it for a gap
+    fn line_count(&self, log: &LogLine, width: usize) -> usize {
+        gap_lines(log) + self.line_starts(log, width).len()
+    }
+
+    /// Say how long after the row before each row of the window came, when
+    /// it's longer than `gap_after`, going by when it was logged if that's
+    /// known and when it was read otherwise. The window's first row is left
+    /// without, as the one before isn't there.
+    fn mark_gaps(&mut self) {
+        let gap_after = self.show_gaps.then_some(self.gap_after.as_millis() as i64);
+        let mut before = None;
+        for log in &mut self.pane.logs {
+            let at = log.line_ts.unwrap_or(log.ts);
+            log.gap = match (gap_after, before) {
+                // the row before isn't the one shown before it
+                _ if log.skipped.is_some() => None,
+                (Some(gap_after), Some(before)) if at - before > gap_after => Some(at - before),
+                _ => None,
+            };
+            before = Some(at);
+        }
+    }
+
+    /// Show a line between rows far apart in time, or stop, keeping the row
+    /// at the top of the screen there
+    pub fn toggle_gaps(&mut self) {
+        self.show_gaps = !self.show_gaps;
+        for pane in self.pane_ids() {
+            self.with_pane(pane, |app| {
+                app.mark_gaps();
+                app.pane.line_counts.clear();
+                app.keep_top_row();
+            });
+        }
+    }
+
+    /// Show lines from DEBUG up, then INFO, WARN and ERROR, then all again
+    pub fn cycle_min_level(&mut self) {
+        self.pane.min_level = match self.pane.min_level {
+            None | Some(Level::Trace) => Some(Level::Debug),
+            Some(Level::Debug) => Some(Level::Info),
+            Some(Level::Info) => Some(Level::Warn),
+            Some(Level::Warn) => Some(Level::Error),
+            Some(Level::Error | Level::Fatal) => None,
+        };
+    }
+
+    pub fn min_level(&self) -> Option<Level> {
+        self.pane.min_level
+    }
+
+    /// Progress of a file's initial read, which is done once `done` reaches `total`
+    pub fn set_loading(&mut self, file_id: &str, done: u64, total: u64) {
+        if done >= total {
+            self.loading.remove(file_id);
+        } else {
+            self.loading.insert(file_id.to_owned(), (done, total));
+        }
+    }
+
+    /// Say `message` in the status bar, until the next key press
+    pub fn set_message(&mut self, message: String) {
+        self.message = Some(message);
+    }
+
+    pub fn clear_message(&mut self) {
+        self.message = None;
+    }
+
+    /// Progress of building the full-text index, until the search it's for
+    /// comes back
+    pub fn set_indexing(&mut self, done: u64, total: u64) {
+        self.indexing = Some((done, total));
+    }
+
+    /// The query of the last search and how it matched, to run again when
+    /// rows are filtered differently
+    pub fn last_search(&self) -> Option<(&str, SearchMode)> {
+        self.search.as_ref().map(|search| (search.query.as_str(), search.mode))
+    }
+
+    /// Show the rows found for `query`, and go to the first one from the
+    /// top of the screen on
+    pub fn set_matches(&mut self, query: String, mode: SearchMode, matches: Result<Vec<(i64, i64)>, String>) {
+        self.indexing = None;
+        let top = self.pane.logs.get(self.pane.top_row).map_or((i64::MIN, i64::MIN), |log| log.order);
+        let current = match &matches {
+            Ok(matches) => matches.partition_point(|order| *order < top) % matches.len().max(1),
+            Err(_) => 0,
+        };
+        let matcher = mode.matcher(&query).ok();
+        self.search = Some(Search { query, mode, matcher, matches, current });
+        self.jump_to_match(0);
+    }
+
+    /// Go `step` matches on from the current one, backwards if negative,
+    /// wrapping around at either end, which is said in the status bar
+    pub fn jump_to_match(&mut self, step: isize) {
+        let Some(Search { matches: Ok(matches), current, .. }) = &mut self.search else {
+            return;
+        };
+        if matches.is_empty() {
+            return;
+        }
+        let next = (*current as isize + step).rem_euclid(matches.len() as isize) as usize;
+        if step > 0 && next <= *current {
+            self.message = Some(String::from("search hit the bottom, continuing at the top"));
+        } else if step < 0 && next >= *current {
+            self.message = Some(String::from("search hit the top, continuing at the bottom"));
+        }
+        *current = next;
+        let order = matches[next];
+        self.jump_to(Jump::Order(order));
+    }
+
+    /// Char index in `log` the current search first matches at, so that a
+    /// match further down a long line that wraps, or off to the side of one
+    /// that doesn't, is on screen when it's jumped to
+    fn match_char(&self, log: &LogLine) -> Option<usize> {
+        let matcher = self.search.as_ref()?.matcher.as_ref()?;
+        // the store matched the message, not the file it's from
+        highlight::message_matches(log, matcher).first().map(|(start, _)| *start)
+    }
+
+    /// Put the line `input` says at the top of the screen: a line number,
+    /// counting the lines shown as the status bar does, how far down they
+    /// are as a percentage like `50%`, or `+N` / `-N` lines from the top
+    /// one. Says what's wrong with `input` in the status bar if it's none of
+    /// those.
+    pub fn go_to_line(&mut self, input: &str) {
+        let input = input.trim();
+        // from where the last jump goes, if the window doesn't have it yet
+        let current = match self.pane.jump {
+            Some(Jump::Row(row)) => row,
+            _ => self.pane.window_start + self.pane.top_row,
+        };
+        let row = if let Some(percent) = input.strip_suffix('%') {
+            percent.trim().parse::<f64>().ok()
+                .filter(|percent| (0.0..=100.0).contains(percent))
+                .map(|percent| (self.pane.total as f64 * percent / 100.0) as usize)
+        } else if let Some(lines) = input.strip_prefix('+') {
+            lines.parse::<usize>().ok().map(|lines| current.saturating_add(lines))
+        } else if let Some(lines) = input.strip_prefix('-') {
+            lines.parse::<usize>().ok().map(|lines| current.saturating_sub(lines))
+        } else {
+            input.parse::<usize>().ok().map(|line| line.saturating_sub(1))
+        };
+        let Some(row) = row else {
+            self.message = Some(format!("can't go to '{}', expected a line number, a percentage like 50% or +N / -N lines", input));
+            return;
+        };
+        let row = row.min(self.pane.total.saturating_sub(1));
+        if let Some(min_level) = self.pane.min_level {
+            self.message = Some(format!("line {} of the ones at {} and above", row + 1, min_level));
+        }
+        self.jump_to(Jump::Row(row));
+    }
+
+    /// Open the list of files left of the logs, or close it
+    pub fn toggle_sidebar(&mut self) {
+        self.sidebar = match self.sidebar {
+            Some(_) => None,
+            None => Some(Sidebar::default()),
+        };
+    }
+
+    pub fn sidebar_open(&self) -> bool {
+        self.sidebar.is_some()
+    }
+
+    /// Move the sidebar's cursor `step` files down, or up if negative
+    pub fn move_sidebar_cursor(&mut self, step: isize) {
+        let files = self.stats.file_count();
+        if let Some(sidebar) = &mut self.sidebar {
+            sidebar.cursor = sidebar.cursor.saturating_add_signed(step).min(files.saturating_sub(1));
+        }
+    }
+
+    /// Hide the rows of the file the sidebar's cursor is on, or show them
+    /// again, see `toggle_file`
+    pub fn toggle_sidebar_file(&mut self) {
+        if let Some(index) = self.sidebar.as_ref().map(|sidebar| sidebar.cursor) {
+            self.toggle_file(index);
+        }
+    }
+
+    /// Hide the rows of the file at `index`, in the order the sidebar lists
+    /// them, or show them again
+    pub fn toggle_file(&mut self, index: usize) {
+        let Some((file_id, _)) = self.stats.files().nth(index) else {
+            self.message = Some(format!("no file {}, there are {}", index + 1, self.stats.file_count()));
+            return;
+        };
+        let file_id = file_id.to_owned();
+        if self.pane.hidden_files.remove(&file_id) {
+            self.message = Some(format!("showing {}", file_id));
+        } else {
+            self.message = Some(format!("hiding {}", file_id));
+            self.pane.hidden_files.insert(file_id);
+        }
+    }
+
+    pub fn hidden_files(&self) -> &BTreeSet<String> {
+        &self.pane.hidden_files
+    }
+
+    /// List the most common lines, which the store is asked to count, see
+    /// `Frequencies`
+    pub fn open_frequencies(&mut self) {
+        self.frequencies = Some(Frequencies::default());
+    }
+
+    /// Show the most common lines the store counted, if they're still
+    /// listed
+    pub fn set_frequencies(&mut self, counted: Result<(u64, Vec<crate::store::Frequency>), String>) {
+        if let Some(frequencies) = &mut self.frequencies {
+            frequencies.set(counted);
+        }
+    }
+
+    /// Only show rows of `template`, e.g. all the `request <HEX> took <N>ms`
+    /// lines, or all rows again. The store does the hiding, see
+    /// `LogStore::set_template`
+    pub fn set_template(&mut self, template: Option<Template>) {
+        self.message = Some(match &template {
+            Some(template) => format!("only showing lines like {}", template.text),
+            None => String::from("showing all lines"),
+        });
+        self.pane.template = template;
+    }
+
+    pub fn template(&self) -> Option<&Template> {
+        self.pane.template.as_ref()
+    }
+
+    /// Only show the rows the search matches with `CONTEXT_LINES` rows
+    /// before and after each, like `grep -C`, with a line saying how many
+    /// were left out in between, or all rows again. The store does the
+    /// hiding, see `LogStore::set_context`. Returns whether there was a
+    /// search to do it for.
+    pub fn toggle_context(&mut self) -> bool {
+        // the top row stays where it is, rather than the screen going to
+        // the bottom of the other rows for being at the bottom of these,
+        // unless it's in follow mode. Which rows are at the end is only
+        // known once they come.
+        let following = std::mem::replace(&mut self.pane.following, self.pane.follow_mode);
+        let at_end = std::mem::replace(&mut self.pane.at_end, false);
+        if self.pane.context.take().is_some() {
+            self.message = Some(String::from("showing all lines"));
+            return true;
+        }
+        let Some(matcher) = self.search.as_ref().and_then(|search| search.matcher.clone()) else {
+            self.message = Some(String::from("nothing to show the lines around, search first"));
+            (self.pane.following, self.pane.at_end) = (following, at_end);
+            return false;
+        };
+        self.pane.context = Some((matcher, CONTEXT_LINES));
+        self.message = Some(format!("only showing matches and {} lines around them, +/- for more or fewer", CONTEXT_LINES));
+        true
+    }
+
+    /// Show `step` more rows around each match, or fewer if negative, down
+    /// to none. Returns whether rows around matches are being shown.
+    pub fn change_context(&mut self, step: isize) -> bool {
+        let Some((_, lines)) = &mut self.pane.context else {
+            self.message = Some(String::from("o shows the lines around matches first"));
+            return false;
+        };
+        *lines = lines.saturating_add_signed(step).min(MAX_CONTEXT_LINES);
+        self.message = Some(format!("{} lines around matches", lines));
+        true
+    }
+
+    /// Show the rows around the matches of `query` in place of the ones
+    /// around the last search's, if rows around matches are being shown.
+    /// Returns whether they are.
+    pub fn search_context(&mut self, query: &str, mode: SearchMode) -> bool {
+        let Some((matcher, _)) = &mut self.pane.context else {
+            return false;
+        };
+        if let Ok(new) = mode.matcher(query) {
+            *matcher = new;
+        }
+        true
+    }
+
+    /// What the focused pane shows the rows around, and how many, see
+    /// `toggle_context`
+    pub fn context(&self) -> Option<(Regex, usize)> {
+        self.pane.context.clone()
+    }
+
+    /// Split the screen in two, the pane below following the last row and
+    /// filtered the same as this one to start with, or close the pane
+    /// that keys don't go to
+    pub fn toggle_split(&mut self) {
+        if let Some(split) = self.split.take() {
+            self.tabs.forget(split.id);
+            return;
+        }
+        self.split = Some(Pane {
+            // the one the store has that isn't taken
+            id: 1 - self.pane.id,
+            following: true,
+            follow_mode: true,
+            vertical_scroll_pos: usize::MAX,
+            min_level: self.pane.min_level,
+            hidden_files: self.pane.hidden_files.clone(),
+            template: self.pane.template.clone(),
+            file: self.pane.file.clone(),
+            ..Pane::default()
+        });
+    }
+
+    /// Put the focused pane on the tab at `index`, see `Tabs`. Returns
+    /// whether it's on another one now, for the store to be told.
+    pub fn switch_tab(&mut self, index: usize) -> bool {
+        let Some(file) = Tabs::file(&self.stats, index) else {
+            self.message = Some(format!("no tab {}, there are {}", index, self.stats.file_count() + 1));
+            return false;
+        };
+        if file == self.pane.file {
+            return false;
+        }
+        self.tabs.switch(&mut self.pane, file);
+        true
+    }
+
+    /// Put the focused pane on the tab `step` to the right, or to the left
+    /// if negative, going round. Returns whether it's on another one now.
+    pub fn cycle_tab(&mut self, step: isize) -> bool {
+        let tabs = self.stats.file_count() + 1;
+        let index = Tabs::index(&self.stats, self.pane.file.as_deref());
+        self.switch_tab((index as isize + step).rem_euclid(tabs as isize) as usize)
+    }
+
+    /// The file whose rows the focused pane shows, or none for all files
+    pub fn tab_file(&self) -> Option<&str> {
+        self.pane.file.as_deref()
+    }
+
+    /// Have keys go to the other pane, when the screen is split
+    pub fn switch_pane(&mut self) {
+        if let Some(split) = &mut self.split {
+            std::mem::swap(&mut self.pane, split);
+        }
+    }
+
+    /// Id of the pane keys go to, which the store filters and searches by
+    pub fn focused_pane(&self) -> usize {
+        self.pane.id
+    }
+
+    /// Ids of the panes on screen
+    pub fn pane_ids(&self) -> Vec<usize> {
+        self.panes().map(|pane| pane.id).collect()
+    }
+
+    /// Screen lines the focused pane shows, as of when it was last drawn
+    pub fn page_size(&self) -> u16 {
+        self.pane.logs_widget_state.height
+    }
+
+    fn panes(&self) -> impl Iterator<Item = &Pane> {
+        std::iter::once(&self.pane).chain(&self.split)
+    }
+
+    fn panes_mut(&mut self) -> impl Iterator<Item = &mut Pane> {
+        std::iter::once(&mut self.pane).chain(&mut self.split)
+    }
+
+    /// Run `f` with the pane `id` in place of the focused one, if it's on
+    /// screen, for what's done to both panes or comes from the store for
+    /// either
+    fn with_pane<R>(&mut self, id: usize, f: impl FnOnce(&mut App) -> R) -> Option<R> {
+        if self.pane.id == id {
+            return Some(f(self));
+        }
+        let mut pane = self.split.take_if(|split| split.id == id)?;
+        std::mem::swap(&mut self.pane, &mut pane);
+        let result = f(self);
+        std::mem::swap(&mut self.pane, &mut pane);
+        self.split = Some(pane);
+        Some(result)
+    }
+
+    /// Mark the row at the top of the screen with `letter`, to jump back to
+    /// with `jump_to_mark`
+    pub fn set_mark(&mut self, letter: char) {
+        let Some(log) = self.pane.logs.get(self.pane.top_row) else {
+            self.message = Some(String::from("no line to mark"));
+            return;
+        };
+        self.marks.set(letter, Mark::new(log));
+        self.message = Some(format!("mark {} set, ' and {} jumps back to it", letter, letter));
+    }
+
+    /// Put the row marked with `letter` at the top of the screen, or the
+    /// one after it if it's filtered out
+    pub fn jump_to_mark(&mut self, letter: char) {
+        let Some(mark) = self.marks.get(letter).cloned() else {
+            self.message = Some(format!("no mark {}, m and a letter sets one", letter));
+            return;
+        };
+        self.jump_to_mark_from_here(mark);
+    }
+
+    /// Go back to where the screen was before the last jump to a mark, or
+    /// back from there
+    pub fn jump_back(&mut self) {
+        let Some(mark) = self.marks.back.clone() else {
+            self.message = Some(String::from("no jump to go back from, ' and a letter jumps to a mark"));
+            return;
+        };
+        self.jump_to_mark_from_here(mark);
+    }
+
+    /// Pin the row under the cursor above the logs, or unpin it, see `Pins`
+    pub fn toggle_pin(&mut self) {
+        let Some(log) = self.pane.selected.and_then(|id| self.pane.logs.iter().find(|log| log.id == id)) else {
+            self.message = Some(String::from("no line under the cursor, j and k move it"));
+            return;
+        };
+        self.message = Some(match self.pins.toggle(log) {
+            true => String::from("pinned, ` and Enter on it jumps back to it"),
+            false => String::from("unpinned"),
+        });
+    }
+
+    /// Have keys go to the pinned rows, to jump to one, or back to the logs
+    pub fn toggle_pins_focus(&mut self) {
+        if !self.pins.toggle_focus() && self.pins.height() == 0 {
+            self.message = Some(String::from("nothing pinned, P pins the line under the cursor"));
+        }
+    }
+
+    pub fn pins_focused(&self) -> bool {
+        self.pins.is_focused()
+    }
+
+    /// Move the cursor in the pinned rows `step` down, or up if negative
+    pub fn move_pins_cursor(&mut self, step: isize) {
+        self.pins.move_cursor(step);
+    }
+
+    /// Unpin the pinned row the cursor is on
+    pub fn unpin_selected(&mut self) {
+        self.pins.unpin_selected();
+    }
+
+    /// Put the pinned row the cursor is on at the top of the screen, with
+    /// keys going back to the logs, as with `jump_to_mark`
+    pub fn jump_to_pin(&mut self) {
+        let mark = self.pins.selected().cloned();
+        self.pins.toggle_focus();
+        if let Some(mark) = mark {
+            self.jump_to_mark_from_here(mark);
+        }
+    }
+
+    /// Put the alert the cursor is on in the alerts popup at the top of the
+    /// screen, closing the popup, as with `jump_to_mark`
+    pub fn jump_to_alert(&mut self) {
+        let mark = self.alerts.selected().cloned();
+        self.alerts.close();
+        if let Some(mark) = mark {
+            self.jump_to_mark_from_here(mark);
+        }
+    }
+
+    /// Jump to `mark`, remembering where the screen was for `jump_back`,
+    /// unless it's there already
+    fn jump_to_mark_from_here(&mut self, mark: Mark) {
+        if let Some(log) = self.pane.logs.get(self.pane.top_row).filter(|log| log.id != mark.id) {
+            self.marks.back = Some(Mark::new(log));
+        }
+        self.jump_to(Jump::Order(mark.order));
+    }
+
+    /// Put the row `jump` is for at the top of the screen, asking for the
+    /// rows around it if the window doesn't have it, see `wanted_window`
+    fn jump_to(&mut self, jump: Jump) {
+        self.pane.following = false;
+        self.pane.follow_mode = false;
+        self.pane.jump = Some(jump);
+        self.place_jump();
+    }
+
+    /// Scroll to the row `jump` is for, if the window has where it goes.
+    /// Returns whether it did.
+    fn place_jump(&mut self) -> bool {
+        let Some(jump) = self.pane.jump else {
+            return false;
+        };
+        // how many rows of the window are before it
+        let before = match jump {
+            Jump::Order(order) => {
+                let after_start = self.pane.window_start == 0 || self.pane.logs.first().is_some_and(|log| log.order <= order);
+                let before_end = self.pane.at_end || self.pane.logs.last().is_some_and(|log| log.order >= order);
+                (after_start && before_end).then(|| self.pane.logs.partition_point(|log| log.order < order))
+            }
+            Jump::Row(row) => row.checked_sub(self.pane.window_start)
+                .filter(|before| self.pane.at_end || *before < self.pane.logs.len())
+                .map(|before| before.min(self.pane.logs.len())),
+        };
+        let Some(before) = before else {
+            return false;
+        };
+        self.pane.jump = None;
+        let width = self.pane.logs_widget_state.width.into();
+        let shown: Vec<&LogLine> = self.shown().collect();
+        let note_rows = shown.len() - self.pane.logs.len();
+        // the note goes with the first row, see `go_to_top`
+        let top_idx = if before == 0 && self.pane.window_start == 0 { 0 } else { note_rows + before };
+        let found_char = match jump {
+            Jump::Order(order) => shown.get(top_idx).filter(|log| log.order == order).and_then(|log| self.match_char(log)),
+            Jump::Row(_) => None,
+        };
+        let line_in_log = match (found_char, shown.get(top_idx)) {
+            (Some(found_char), Some(log)) => gap_lines(log) + self.line_starts(log, width).iter().rposition(|start| *start <= found_char).unwrap_or(0),
+            _ => 0,
+        };
+        let shown_end = shown.get(top_idx).map(|log| fit_end(&log.text.chars().collect::<Vec<_>>(), self.pane.h_scroll, width, self.wrap.tab_width));
+        self.pane.vertical_scroll_pos = shown[..top_idx].iter()
+            .map(|log| self.line_count(log, width))
+            .sum::<usize>() + line_in_log;
+        if let Some(found_char) = found_char.filter(|_| self.no_wrap) {
+            if found_char < self.pane.h_scroll || shown_end.is_some_and(|end| found_char >= end) {
+                // a little of what's before it too
+                self.pane.h_scroll = found_char.saturating_sub(width / 4);
+            }
+        }
+        self.pane.top_row = top_idx.saturating_sub(note_rows);
+        true
+    }
+
+    /// The rows to query for `pane`, if it's on screen, see
+    /// `Pane::wanted_window`
+    pub fn wanted_window(&self, pane: usize, added: bool, moved: bool) -> Option<Window> {
+        self.panes().find(|shown| shown.id == pane)?.wanted_window(added, moved)
+    }
+
+    /// Show `logs` in `pane`, if it's still on screen, see `show_window`
+    pub fn set_window(&mut self, pane: usize, start: usize, logs: Vec<LogLine>, total: usize) {
+        self.with_pane(pane, |app| app.show_window(start, logs, total));
+    }
+
+    /// Show `logs`, which start at row number `start` out of `total`,
+    /// keeping the row that was at the top of the screen there
+    fn show_window(&mut self, start: usize, logs: Vec<LogLine>, total: usize) {
+        let top_order = self.pane.logs.get(self.pane.top_row).map(|log| log.order);
+        self.pane.window_start = start;
+        self.pane.at_end = start + logs.len() >= total;
+        self.pane.total = total;
+        let old = std::mem::replace(&mut self.pane.logs, logs);
+        self.mark_gaps();
+        // rows still in the window keep their line count, unless their
+        // text changed, e.g. as timestamps were turned on, or the row before
+        // them did
+        let old_lines: HashMap<i64, (&str, Option<i64>, Option<usize>)> = old.iter().map(|log| (log.id, (log.text.as_str(), log.gap, log.skipped))).collect();
+        for log in &self.pane.logs {
+            if old_lines.get(&log.id).is_some_and(|line| *line != (log.text.as_str(), log.gap, log.skipped)) {
+                self.pane.line_counts.forget(log.id);
+            }
+        }
+        // as well as any scrolling done since the screen was drawn
+        let scrolled = self.pane.vertical_scroll_pos as isize - self.pane.logs_widget_state.actual_scroll_y as isize;
+        if self.pane.following {
+            self.pane.vertical_scroll_pos = usize::MAX;
+            return;
+        }
+        if self.place_jump() {
+            return;
+        }
+        let note_rows = self.shown().count() - self.pane.logs.len();
+        (self.pane.top_row, self.pane.vertical_scroll_pos) = match self.anchored_scroll(top_order, self.pane.logs_widget_state.width.into()) {
+            Some((top_idx, scroll)) => (top_idx.saturating_sub(note_rows), scroll.saturating_add_signed(scrolled)),
+            None => (0, 0),
+        };
+    }
+
+    /// Update the repeat count of the line with row `id`, see `repeat_suffix`
+    pub fn set_repeats(&mut self, id: i64, repeats: u64) {
+        for pane in self.panes_mut() {
+            // the line repeated is one of the last ones, look from the end.
+            // The ones that aren't in the window get theirs when they're
+            // queried
+            let Some(log) = pane.logs.iter_mut().rev().find(|log| log.id == id) else {
+                continue;
+            };
+            if log.repeats > 0 {
+                let len = log.text.len() - repeat_suffix(log.repeats).len();
+                log.text.truncate(len);
+            }
+            log.text.push_str(&repeat_suffix(repeats));
+            log.repeats = repeats;
+            pane.line_counts.forget(id);
+        }
+    }
+
+    /// Show `note` above the logs, see `top_note`
+    pub fn set_top_note(&mut self, note: LogLine) {
+        for pane in self.panes_mut() {
+            pane.line_counts.forget(note.id);
+        }
+        self.top_note = Some(note);
     }
 
     pub fn render(&mut self, frame: &mut Frame) {
@@ -199,19 +1918,487 @@ impl App {
         ])
         .split(area);
 
-        self.render_logs(frame, chunks[0]);
-        
-        let info_str = format!("  {}", self.logs_widget_state.actual_scroll_y.saturating_add(1));
-        let title = Block::new()
-            .title(Span::from("filewatch").underlined() + Span::from(info_str).blue());
-        frame.render_widget(title, chunks[1]);
+        let [tabs_area, main_area, graphs_area] = Layout::vertical([
+            Constraint::Length(Tabs::height(&self.stats)),
+            Constraint::Fill(1),
+            Constraint::Length(self.graphs.height()),
+        ])
+        .areas(chunks[0]);
+        self.graphs.render(frame, graphs_area);
+        let other = self.split.as_ref().map(|split| split.file.as_deref());
+        self.tabs.render(frame, tabs_area, &self.stats, self.pane.file.as_deref(), other);
+        let logs_area = match &mut self.sidebar {
+            Some(sidebar) => {
+                let width = Sidebar::width(&self.stats, main_area.width);
+                let [sidebar_area, logs_area] = Layout::horizontal([Constraint::Length(width), Constraint::Fill(1)]).areas(main_area);
+                sidebar.render(frame, sidebar_area, &self.stats, &self.pane.hidden_files);
+                logs_area
+            }
+            None => main_area,
+        };
+        // the logs get the rest, keeping the row under the cursor on screen
+        // as pins come and go, see `render_logs`
+        let [pins_area, logs_area] = Layout::vertical([Constraint::Length(self.pins.height()), Constraint::Fill(1)]).areas(logs_area);
+        self.pins.render(frame, pins_area);
+        match self.split.as_ref().map(|split| split.id) {
+            // the pane split off first stays on top, whichever keys go to
+            Some(split) => {
+                let [top, bottom] = Layout::vertical([Constraint::Fill(1), Constraint::Fill(1)]).areas(logs_area);
+                let (top_id, bottom_id) = (split.min(self.pane.id), split.max(self.pane.id));
+                let block = Block::new().borders(Borders::TOP).border_style(Style::default().dim());
+                let title = self.panes().find(|pane| pane.id == bottom_id).map(Pane::title).unwrap_or_default();
+                let title = if bottom_id == self.pane.id { Span::from(title).bold() } else { Span::from(title).dim() };
+                frame.render_widget(block.clone().title(title), bottom);
+                self.with_pane(top_id, |app| app.render_logs(frame, top));
+                self.with_pane(bottom_id, |app| app.render_logs(frame, block.inner(bottom)));
+            }
+            None => self.render_logs(frame, logs_area),
+        }
+
+        if let Some(prompt) = &self.prompt {
+            let input = format!("{}{}", prompt.label(), prompt.input);
+            // after the text, as long as it fits
+            let cursor_x = chunks[1].x.saturating_add(input.chars().count() as u16).min(chunks[1].right().saturating_sub(1));
+            frame.render_widget(Span::from(input), chunks[1]);
+            frame.set_cursor_position((cursor_x, chunks[1].y));
+            return;
+        }
+        let (segments, sparkline) = self.status_segments();
+        let drawn = status::render(frame, chunks[1], &segments);
+        self.sparkline_area = sparkline.and_then(|i| drawn[i]);
+
+        if self.show_stats {
+            self.stats.render_popup(frame, self.stale_after);
+        }
+        if self.show_help {
+            help::render_popup(frame);
+        }
+        if self.show_marks {
+            self.marks.render_popup(frame);
+        }
+        if let Some(details) = &self.details {
+            details.render_popup(frame);
+        }
+        if let Some(frequencies) = &self.frequencies {
+            frequencies.render_popup(frame);
+        }
+        if self.alerts.is_open() {
+            self.alerts.render_popup(frame);
+        }
+
+    }
+
+    /// What the status bar says, see `status::render`, and which of the
+    /// segments is the sparkline of how busy the files are, if any
+    fn status_segments(&self) -> (Vec<Segment>, Option<usize>) {
+        let mut segments = vec![Segment::new(Side::Left, 1, Span::from("filewatch").underlined())];
+        if let Some(alert) = self.alerts.status() {
+            segments.push(Segment::new(Side::Left, 10, alert));
+        }
+        if self.pane.total == 0 {
+            segments.push(Segment::new(Side::Left, 9, Span::from("no lines").blue()));
+        } else {
+            let line = (self.pane.window_start + self.pane.top_row + 1).min(self.pane.total);
+            segments.push(Segment::new(Side::Left, 9, Span::from(format!("{}/{}", line, self.pane.total)).blue()));
+            segments.push(Segment::new(Side::Left, 6, Span::from(format!("{}%", line * 100 / self.pane.total)).blue()));
+        }
+        if let Some(split) = &self.split {
+            let pane = if self.pane.id < split.id { "top pane" } else { "bottom pane" };
+            segments.push(Segment::new(Side::Left, 8, Span::from(pane).magenta()));
+        }
+        if self.pane.follow_mode {
+            segments.push(Segment::new(Side::Left, 8, Span::from("FOLLOW").green().bold()));
+        }
+        if let Some(rows) = self.paused {
+            segments.push(Segment::new(Side::Left, 8, Span::from(format!("PAUSED (+{} new)", thousands(rows))).yellow().bold()));
+        }
+        if let Some(min_level) = self.pane.min_level {
+            segments.push(Segment::new(Side::Left, 7, Span::from(format!("{}+", min_level)).bold()));
+        }
+        if let Some(template) = &self.pane.template {
+            segments.push(Segment::new(Side::Left, 7, Span::from(format!("like {}", template.text)).bold()));
+        }
+        if let Some((_, lines)) = &self.pane.context {
+            segments.push(Segment::new(Side::Left, 7, Span::from(format!("±{} around matches", lines)).bold()));
+        }
+        if let Some((done, total)) = self.indexing {
+            segments.push(Segment::new(Side::Left, 7, Span::from(format!("indexing for search: {}%", done * 100 / total.max(1))).cyan()));
+        } else if let Some(search) = &self.search {
+            segments.push(Segment::new(Side::Left, 7, match &search.matches {
+                Ok(matches) if matches.is_empty() => Span::from(format!("no match for {}", search.query)).yellow(),
+                Ok(matches) => Span::from(format!("/{}  match {}/{}", search.query, search.current + 1, matches.len())),
+                Err(err) => Span::from(format!("search failed: {}", err)).red(),
+            }));
+        }
+
+        if let Some((file_id, (done, total))) = self.loading.iter().next() {
+            let mut loading = format!("loading {}: {}%", file_id, done * 100 / total);
+            if self.loading.len() > 1 {
+                loading.push_str(&format!(" (+{} more)", self.loading.len() - 1));
+            }
+            segments.push(Segment::new(Side::Center, 5, Span::from(loading).cyan()));
+        }
+        if let Some(message) = &self.message {
+            segments.push(Segment::new(Side::Center, 10, Span::from(message.clone()).bold()));
+        }
 
+        if self.collapse_repeats {
+            segments.push(Segment::new(Side::Right, 3, Span::from("repeats collapsed").dim()));
+        }
+        if !self.show_events {
+            segments.push(Segment::new(Side::Right, 3, Span::from("events hidden").dim()));
+        }
+        if !self.pane.hidden_files.is_empty() {
+            let plural = if self.pane.hidden_files.len() == 1 { "" } else { "s" };
+            segments.push(Segment::new(Side::Right, 3, Span::from(format!("{} file{} hidden", self.pane.hidden_files.len(), plural)).dim()));
+        }
+        if !self.level_colors {
+            segments.push(Segment::new(Side::Right, 3, Span::from("level colors off").dim()));
+        }
+        if self.no_wrap {
+            segments.push(Segment::new(Side::Right, 3, Span::from(format!("no wrap, col {}", self.pane.h_scroll + 1)).dim()));
+        } else if self.wrap.words {
+            segments.push(Segment::new(Side::Right, 3, Span::from("word wrap").dim()));
+        }
+        if self.unparsed_timestamps > 0 {
+            segments.push(Segment::new(Side::Right, 2, Span::from(format!("{} without timestamp", self.unparsed_timestamps)).dim()));
+        }
+        let files = self.stats.file_count();
+        let sparkline = (files > 0).then(|| {
+            segments.push(Segment::new(Side::Right, 2, Span::from(self.activity.sparkline())));
+            segments.len() - 1
+        });
+        if files > 0 {
+            let stale = self.stats.stale_count(self.stale_after);
+            let plural = if files == 1 { "" } else { "s" };
+            segments.push(match stale {
+                0 => Segment::new(Side::Right, 4, Span::from(format!("{} file{}", files, plural))),
+                _ => Segment::new(Side::Right, 4, Span::from(format!("{} file{}, {} stale", files, plural, stale)).yellow()),
+            });
+        }
+        let db = self.db.as_ref().map_or(String::from("in memory"), |db| db.display().to_string());
+        segments.push(Segment::new(Side::Right, 1, Span::from(db).dim()));
+        (segments, sparkline)
     }
 
     fn render_logs(&mut self, frame: &mut Frame, area: Rect) {
-        let lw = LogsWidget::new(self.logs.clone())
-            .scroll(self.vertical_scroll_pos);
-        frame.render_stateful_widget(lw, area, &mut self.logs_widget_state);
-        self.vertical_scroll_pos = self.logs_widget_state.actual_scroll_y;
+        self.pane.area = area;
+        let [area, scrollbar] = Layout::horizontal([Constraint::Fill(1), Constraint::Length(1)]).areas(area);
+        let search = self.search.as_ref().and_then(|search| search.matcher.clone()).map(Highlight::search);
+        let line_numbers = self.line_numbers.then(|| LineNumbers {
+            first: self.pane.window_start + 1,
+            skip: self.shown().count() - self.pane.logs.len(),
+            width: self.pane.total.max(1).to_string().len() as u16 + 1,
+        });
+        // the logs wrap at what the gutter leaves, see `LogsWidget::render_logs`
+        let width = usize::from(area.width.saturating_sub(line_numbers.map_or(0, |numbers| numbers.width)));
+        // the terminal was resized, or the gutter got wider, so the logs wrap
+        // differently: the line at the top stays there, or the bottom when
+        // following
+        let drawn_width = usize::from(self.pane.logs_widget_state.width);
+        if drawn_width != 0 && width != drawn_width {
+            let scrolled = self.pane.vertical_scroll_pos as isize - self.pane.logs_widget_state.actual_scroll_y as isize;
+            if self.pane.following {
+                self.pane.vertical_scroll_pos = usize::MAX;
+            } else if let Some((_, scroll)) = self.anchored_scroll(None, width) {
+                self.pane.vertical_scroll_pos = scroll.saturating_add_signed(scrolled);
+            }
+        }
+        // rows were taken from the logs, or given back, e.g. by the pinned
+        // rows: the row under the cursor stays on screen, or the last one
+        // when following
+        let drawn_height = self.pane.logs_widget_state.height;
+        if drawn_height != 0 && area.height != drawn_height && !self.pane.following {
+            self.pane.logs_widget_state.height = area.height;
+            self.scroll_to_cursor();
+        }
+        // both are written to while the logs they're for are borrowed
+        let mut line_counts = std::mem::take(&mut self.pane.line_counts);
+        let mut state = std::mem::take(&mut self.pane.logs_widget_state);
+        let shown: Vec<&LogLine> = self.shown().collect();
+        line_counts.update(&shown, width, |log| self.line_count(log, width));
+        let lw = LogsWidget::new(shown)
+            .line_offsets(line_counts.offsets())
+            .scroll(self.pane.vertical_scroll_pos)
+            .highlights(self.highlights.iter().cloned().chain(search).collect())
+            .color_files(self.color_files)
+            .h_scroll(self.no_wrap.then_some(self.pane.h_scroll))
+            .wrap(self.wrap.clone())
+            .selected(self.pane.selected.and_then(|id| self.shown().position(|log| log.id == id)))
+            .level_styles(self.level_colors.then_some(&self.level_styles))
+            .line_numbers(line_numbers);
+        frame.render_stateful_widget(lw, area, &mut state);
+        self.pane.logs_widget_state = state;
+        self.pane.line_counts = line_counts;
+        self.pane.vertical_scroll_pos = self.pane.logs_widget_state.actual_scroll_y;
+        self.pane.top_row = self.pane.logs_widget_state.top_index.saturating_sub(self.shown().count() - self.pane.logs.len());
+        // until scrolling up again, see `scroll_up`, or jumping away from
+        // the bottom before the window there comes
+        self.pane.following |= self.pane.follow_mode || (self.pane.jump.is_none() && self.pane.logs_widget_state.was_at_bottom && self.pane.at_end);
+        self.render_scrollbar(frame, scrollbar);
+    }
+
+    /// Where the screen is in all the rows there are, along `area`, see
+    /// `scroll_lines`
+    fn render_scrollbar(&mut self, frame: &mut Frame, area: Rect) {
+        self.pane.scrollbar = area;
+        let (before, lines) = self.scroll_lines();
+        let height = usize::from(self.pane.logs_widget_state.height);
+        // ratatui counts the positions the top of the screen can be at
+        let mut state = ScrollbarState::new(lines.saturating_sub(height) + 1)
+            .position(before + self.pane.logs_widget_state.actual_scroll_y)
+            .viewport_content_length(height);
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .symbols(scrollbar::VERTICAL)
+            .begin_symbol(None)
+            .end_symbol(None)
+            .track_style(Style::default().dim());
+        frame.render_stateful_widget(scrollbar, area, &mut state);
+    }
+
+    /// What's in the window to show, which starts with `top_note` when it
+    /// starts at the first row
+    fn shown(&self) -> impl Iterator<Item = &LogLine> {
+        let note = self.top_note.as_ref().filter(|_| self.pane.window_start == 0);
+        note.into_iter().chain(&self.pane.logs)
+    }
+
+    /// Scroll position that puts the log that was at the top of the screen
+    /// back there, wherever it is in the window now, and its index in
+    /// `shown`, with logs wrapped at `width`. If it's gone, the one that's
+    /// now first after where it was is put there instead.
+    fn anchored_scroll(&self, top_order: Option<(i64, i64)>, width: usize) -> Option<(usize, usize)> {
+        let (top_id, top_char) = self.pane.logs_widget_state.top?;
+        let shown: Vec<&LogLine> = self.shown().collect();
+        let (top_idx, top_char) = match shown.iter().position(|log| log.id == top_id) {
+            Some(top_idx) => (top_idx, top_char.map(|top_char| shown[top_idx].message_start.saturating_add_signed(top_char))),
+            None => {
+                let top_order = top_order?;
+                (shown.partition_point(|log| log.order < top_order), None)
+            }
+        };
+        let lines_before: usize = shown[..top_idx].iter()
+            .map(|log| self.line_count(log, width))
+            .sum();
+        // or the line above it for a gap
+        let line_in_log = shown.get(top_idx).zip(top_char)
+            .and_then(|(log, top_char)| Some(gap_lines(log) + self.line_starts(log, width).iter().rposition(|start| *start <= top_char)?))
+            .unwrap_or(0);
+        Some((top_idx, lines_before + line_in_log))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::buffer::Cell;
+
+    use super::*;
+
+    /// A row of just `text`, with nothing before the message
+    fn row(text: &str) -> LogLine {
+        LogLine { text: text.to_owned(), ..LogLine::default() }
+    }
+
+    /// Wrapping anywhere, with nothing before the screen lines wrapped onto
+    fn wrap(tab_width: usize) -> Wrap {
+        Wrap { tab_width, ..Wrap::default() }
+    }
+
+    /// Screen lines before each of `logs` and then all of them, as
+    /// `App::line_count` counts them
+    fn offsets(logs: &[LogLine], width: usize, wrap: &Wrap) -> Vec<usize> {
+        let mut line_counts = LineCounts::default();
+        let logs: Vec<&LogLine> = logs.iter().collect();
+        line_counts.update(&logs, width, |log| gap_lines(log) + line_starts(log, width, wrap).len());
+        line_counts.offsets().to_vec()
+    }
+
+    /// Draw `logs` wrapped by `wrap` in `area` of `buf`, scrolled to
+    /// `scroll_y`
+    fn render(logs: &[LogLine], wrap: Wrap, area: Rect, buf: &mut Buffer, scroll_y: usize) -> LogsWidgetState {
+        let offsets = offsets(logs, area.width.into(), &wrap);
+        let mut state = LogsWidgetState::default();
+        LogsWidget::new(logs.iter().collect())
+            .line_offsets(&offsets)
+            .scroll(scroll_y)
+            .wrap(wrap)
+            .render(area, buf, &mut state);
+        state
+    }
+
+    /// What the cells of row `y` of `buf` show, from `x` on
+    fn screen_row(buf: &Buffer, x: u16, y: u16) -> String {
+        (x..buf.area.right()).map(|x| buf[(x, y)].symbol()).collect()
+    }
+
+    #[test]
+    fn wide_grapheme_at_the_wrap_column_goes_to_the_next_line() {
+        // `中` would take columns 4 and 5 of 5
+        let log = row("abcd中ef");
+        assert_eq!(line_starts(&log, 5, &wrap(8)), [0, 4]);
+        let area = Rect::new(0, 0, 5, 2);
+        let mut buf = Buffer::empty(area);
+        render(&[log], wrap(8), area, &mut buf, 0);
+        assert_eq!(screen_row(&buf, 0, 0), "abcd ");
+        assert_eq!(buf[(0, 1)].symbol(), "中");
+        assert_eq!(buf[(2, 1)].symbol(), "e");
+        // nor is one split when it's the first thing on the line
+        assert_eq!(line_starts(&row("中中中"), 5, &wrap(8)), [0, 2]);
+    }
+
+    #[test]
+    fn combining_accent_at_the_wrap_column_stays_with_its_letter() {
+        // `é` as `e` and U+0301, which takes no column of its own
+        let log = row("abcde\u{301}f");
+        assert_eq!(line_starts(&log, 5, &wrap(8)), [0, 6]);
+        assert_eq!(expand("abcde\u{301}", 8).1, [0, 1, 2, 3, 4, 4, 5]);
+        let area = Rect::new(0, 0, 5, 2);
+        let mut buf = Buffer::empty(area);
+        render(&[log], wrap(8), area, &mut buf, 0);
+        assert_eq!(buf[(4, 0)].symbol(), "e\u{301}");
+        assert_eq!(screen_row(&buf, 0, 1), "f    ");
+        assert_eq!(line_starts(&row("abcde\u{301}"), 5, &wrap(8)), [0]);
+    }
+
+    #[test]
+    fn scroll_position_agrees_with_what_is_drawn() {
+        let mut logs = vec![
+            row("short"),
+            row("a line that wraps onto three screen lines"),
+            row("wide 中中中中中中中中中中中 chars"),
+            row("two\nlines"),
+            row("ends"),
+        ];
+        logs[3].gap = Some(60_000);
+        for (id, log) in logs.iter_mut().enumerate() {
+            log.id = id as i64;
+        }
+        let width = 16;
+        let offsets = offsets(&logs, width, &wrap(8));
+        let total = *offsets.last().unwrap();
+        let area = Rect::new(0, 0, width as u16, total as u16 + 2);
+        for height in [1, 3, total as u16, area.height] {
+            for scroll_y in 0..=total + 1 {
+                let area = Rect { height, ..area };
+                let (log_idx, _, scroll_y_actual, at_bottom, line_count) = LogsWidget::get_log_at_scroll_pos(&offsets, area, scroll_y);
+                let mut buf = Buffer::empty(area);
+                let state = render(&logs, wrap(8), area, &mut buf, scroll_y);
+                assert_eq!(line_count, total);
+                assert_eq!(state.line_count, total);
+                assert_eq!(state.actual_scroll_y, scroll_y_actual);
+                assert_eq!(state.lines.len(), (total - scroll_y_actual).min(height.into()), "height {} scroll {}", height, scroll_y);
+                assert_eq!(state.lines.first(), Some(&log_idx));
+                // the rows drawn are the ones counted, from the log it says
+                let expected: Vec<usize> = (0..logs.len())
+                    .flat_map(|idx| std::iter::repeat_n(idx, offsets[idx + 1] - offsets[idx]))
+                    .skip(scroll_y_actual)
+                    .take(height.into())
+                    .collect();
+                assert_eq!(state.lines, expected);
+                assert_eq!(at_bottom, scroll_y_actual + usize::from(height) >= total);
+            }
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn nothing_is_drawn_outside_the_area() {
+        let mut logs: Vec<LogLine> = (0..20)
+            .map(|n| row(&format!("app.log line {} with\ttabs and a 中 and enough words to wrap", n)))
+            .collect();
+        for (id, log) in logs.iter_mut().enumerate() {
+            log.id = id as i64;
+            log.file = String::from("app.log");
+            log.message_start = 8;
+            log.colors = vec![(0, Style::default().red()), (12, Style::default())];
+        }
+        logs[3].gap = Some(60_000);
+        logs[4].skipped = Some(12);
+        let wrap = Wrap { indicator: String::from("↪ "), indent: true, tab_width: 8, ..Wrap::default() };
+        let highlights = vec![Highlight::new("words to", Style::default().bold()).unwrap()];
+        let line_numbers = LineNumbers { first: 1, skip: 0, width: 3 };
+        // inside a border and below a header, as if there were one
+        let outside = Cell::new("#");
+        let area = Rect::new(3, 4, 24, 7);
+        for scroll_y in [0, 1, 5, 17, usize::MAX] {
+            let mut buf = Buffer::filled(Rect::new(0, 0, 30, 14), outside.clone());
+            let offsets = offsets(&logs, usize::from(area.width - line_numbers.width), &wrap);
+            let mut state = LogsWidgetState::default();
+            LogsWidget::new(logs.iter().collect())
+                .line_offsets(&offsets)
+                .scroll(scroll_y)
+                .wrap(wrap.clone())
+                .highlights(highlights.clone())
+                .color_files(true)
+                .line_numbers(Some(line_numbers))
+                .selected(Some(4))
+                .render(area, &mut buf, &mut state);
+            for position in buf.area.positions().filter(|position| !area.contains(*position)) {
+                assert_eq!(buf[position], outside, "scroll {}: {:?} was drawn on", scroll_y, position);
+            }
+            assert_eq!(state.y, area.y);
+            assert_eq!(state.lines.len(), usize::from(area.height));
+            assert_ne!(screen_row(&buf, area.x, area.y).trim_start_matches('#'), "", "scroll {}: nothing drawn", scroll_y);
+        }
+    }
+
+    #[test]
+    fn tabs_go_to_the_next_stop_of_each_width() {
+        for (tab_width, shown, columns) in [
+            (1, "a b", [0, 1, 2, 3]),
+            (4, "a   b", [0, 1, 4, 5]),
+            (8, "a       b", [0, 1, 8, 9]),
+        ] {
+            assert_eq!(expand("a\tb", tab_width), (String::from(shown), columns.to_vec()), "tab width {}", tab_width);
+            // one already at a stop goes all the way to the next
+            assert_eq!(expand("\t", tab_width).1, [0, tab_width]);
+            assert_eq!(expand(&format!("{}\t", "x".repeat(tab_width)), tab_width).1.last(), Some(&(2 * tab_width)));
+        }
+        // as tabs do in a terminal, 0 is taken as 1
+        assert_eq!(expand("a\tb", 0).0, "a b");
+    }
+
+    #[test]
+    fn tab_crossing_the_wrap_column_goes_to_the_next_line() {
+        for (tab_width, text, starts) in [
+            // up to column 9 of 10, and only one column to the stop
+            (1, "abcdefghi\tx", vec![0, 10]),
+            (4, "abcdefg\tx", vec![0]),
+            // to column 12 or 16, past the edge, so the tab starts the next
+            // line and is as wide as it is there
+            (4, "abcdefgh\tx", vec![0, 8]),
+            (4, "abcdefghi\tx", vec![0, 9]),
+            (8, "abcdefghi\tx", vec![0, 9]),
+            // ending right at the edge, with what's after it on the next
+            (5, "abcdefg\tx", vec![0, 8]),
+        ] {
+            let log = row(text);
+            assert_eq!(line_starts(&log, 10, &wrap(tab_width)), starts, "tab width {}: {:?}", tab_width, text);
+            let area = Rect::new(0, 0, 10, starts.len() as u16);
+            let mut buf = Buffer::empty(area);
+            render(&[log], wrap(tab_width), area, &mut buf, 0);
+            let chars: Vec<char> = text.chars().collect();
+            for (y, &start) in starts.iter().enumerate() {
+                let end = starts.get(y + 1).copied().unwrap_or(chars.len());
+                let line: String = chars[start..end].iter().collect();
+                let (shown, _) = expand(&line, tab_width);
+                assert_eq!(screen_row(&buf, 0, y as u16), format!("{:<10}", shown), "tab width {}: {:?}", tab_width, text);
+            }
+        }
+        // after the indicator, tabs go to the stops of the 8 columns left
+        let wrap = Wrap { indicator: String::from("> "), ..wrap(4) };
+        assert_eq!(line_starts(&row("abcdefghi\tabc"), 10, &wrap), [0, 9]);
+        assert_eq!(line_starts(&row("abcdefghi\t\tx"), 10, &wrap), [0, 9, 11]);
+    }
+
+    #[test]
+    fn carriage_return_takes_a_column() {
+        // one left in with `--carriage-returns keep` is shown as a symbol
+        let (shown, columns) = expand("50%\r100%", 8);
+        assert_eq!(shown, "50%␍100%");
+        assert_eq!(columns.last(), Some(&8));
+        assert_eq!(line_starts(&row("50%\r100%"), 8, &wrap(8)), [0]);
+        assert_eq!(line_starts(&row("50%\r100%!"), 8, &wrap(8)), [0, 8]);
+        // and once a `\r\n`'s is stripped, a line as wide as the screen fits
+        assert_eq!(line_starts(&row(&"x".repeat(10)), 10, &wrap(8)), [0]);
+    }
+}
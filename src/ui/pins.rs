@@ -0,0 +1,147 @@
+use ratatui::layout::Rect;
+use ratatui::style::{Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::Frame;
+
+use super::color::file_color;
+use super::marks::Mark;
+use super::LogLine;
+
+/// Pins the panel shows at once, beyond which it scrolls
+const MAX_ROWS: usize = 5;
+
+/// A row pinned with `P`, see `Pins`
+struct Pin {
+    /// Where it is, to jump to it as to a mark
+    mark: Mark,
+    file_id: String,
+    /// Its file's prefix, as it was shown when it was pinned
+    file: String,
+}
+
+/// Rows pinned with `P`, in a panel above the logs that's there as long as
+/// any are, in the order they're shown in. They're kept by row id, so
+/// filters and following don't lose them. Keys go to the panel after `` ` ``,
+/// to jump to a pin with Enter.
+#[derive(Default)]
+pub struct Pins {
+    pins: Vec<Pin>,
+    /// Index of the pin the cursor is on, while keys go to the panel
+    cursor: Option<usize>,
+    /// Index of the first pin shown, when there are more than fit
+    first: usize,
+    /// Where it was last drawn, to tell which pin a click is on
+    pub area: Rect,
+}
+
+impl Pins {
+    /// Pin `log`, or unpin it if it's pinned. Returns whether it's pinned.
+    pub fn toggle(&mut self, log: &LogLine) -> bool {
+        if let Some(i) = self.pins.iter().position(|pin| pin.mark.id == log.id) {
+            self.remove(i);
+            return false;
+        }
+        let pin = Pin { mark: Mark::new(log), file_id: log.file_id.clone(), file: log.file.clone() };
+        let i = self.pins.partition_point(|pin| pin.mark.order < log.order);
+        self.pins.insert(i, pin);
+        if let Some(cursor) = self.cursor.as_mut().filter(|cursor| **cursor >= i) {
+            *cursor += 1;
+        }
+        // shown, as it was just pinned
+        self.first = self.first.clamp(i.saturating_sub(MAX_ROWS - 1), i);
+        true
+    }
+
+    /// Unpin the pin the cursor is on
+    pub fn unpin_selected(&mut self) {
+        if let Some(i) = self.cursor {
+            self.remove(i);
+        }
+    }
+
+    fn remove(&mut self, i: usize) {
+        self.pins.remove(i);
+        self.first = self.first.min(self.pins.len().saturating_sub(MAX_ROWS));
+        self.cursor = match self.cursor {
+            // keys stop going to the panel once it's gone
+            _ if self.pins.is_empty() => None,
+            Some(cursor) => Some(cursor.min(self.pins.len() - 1)),
+            None => None,
+        };
+    }
+
+    /// Screen rows the panel takes, none without pins
+    pub fn height(&self) -> u16 {
+        match self.pins.len() {
+            0 => 0,
+            // and the line under it
+            pins => pins.min(MAX_ROWS) as u16 + 1,
+        }
+    }
+
+    /// Have keys go to the panel, with the cursor on the first pin shown,
+    /// or back to the logs. Returns whether they go to the panel.
+    pub fn toggle_focus(&mut self) -> bool {
+        self.cursor = match self.cursor {
+            None if !self.pins.is_empty() => Some(self.first),
+            _ => None,
+        };
+        self.cursor.is_some()
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.cursor.is_some()
+    }
+
+    /// Move the cursor `step` pins down, or up if negative, scrolling the
+    /// panel to keep it shown
+    pub fn move_cursor(&mut self, step: isize) {
+        let Some(cursor) = &mut self.cursor else {
+            return;
+        };
+        *cursor = cursor.saturating_add_signed(step).min(self.pins.len().saturating_sub(1));
+        self.first = self.first.clamp(cursor.saturating_sub(MAX_ROWS - 1), *cursor);
+    }
+
+    /// Where the pin the cursor is on is
+    pub fn selected(&self) -> Option<&Mark> {
+        self.pins.get(self.cursor?).map(|pin| &pin.mark)
+    }
+
+    /// Where the pin shown on row `y` of the terminal is, going by where
+    /// the panel was last drawn
+    pub fn at(&self, y: u16) -> Option<&Mark> {
+        let row = usize::from(y.checked_sub(self.area.y)?);
+        self.pins.get(self.first + row).filter(|_| row < MAX_ROWS).map(|pin| &pin.mark)
+    }
+
+    /// A line for each pin shown, with its file's prefix in its color, and
+    /// a line under them saying how many there are and what keys do
+    pub fn render(&mut self, frame: &mut Frame, area: Rect) {
+        self.area = area;
+        let lines: Vec<Line> = self.pins.iter().enumerate()
+            .skip(self.first)
+            .take(MAX_ROWS)
+            .map(|(i, pin)| {
+                let mut spans = vec![Span::from("▍").fg(file_color(&pin.file_id))];
+                if !pin.file.is_empty() {
+                    spans.push(Span::from(format!("{} ", pin.file)).fg(file_color(&pin.file_id)));
+                }
+                spans.push(Span::from(pin.mark.preview.clone()));
+                let line = Line::from(spans);
+                if self.cursor == Some(i) { line.reversed() } else { line }
+            })
+            .collect();
+        let shown = match self.pins.len() {
+            pins if pins > MAX_ROWS => format!(" pinned, {}-{} of {} ", self.first + 1, (self.first + MAX_ROWS).min(pins), pins),
+            pins => format!(" pinned, {} ", pins),
+        };
+        let keys = if self.is_focused() { " j/k: move  Enter: jump to it  P: unpin  Esc: back " } else { " `: to jump to one " };
+        let block = Block::new()
+            .borders(Borders::BOTTOM)
+            .border_style(Style::default().dim())
+            .title_bottom(Line::from(vec![Span::from(shown), Span::from(keys)]).dim());
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
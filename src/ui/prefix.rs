@@ -0,0 +1,115 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+
+/// How files are shown in front of their lines, which `i` cycles through
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FilePrefix {
+    /// As given, or its label
+    #[default]
+    Full,
+    /// The file's name, without the directories it's in
+    Name,
+    /// As much of the name as it takes to tell it from the others, at least
+    /// `SHORT_MIN_CHARS` of it
+    Short,
+    Hidden,
+}
+
+/// Chars a `FilePrefix::Short` prefix is cut to at the least, fewer are
+/// hard to tell apart
+const SHORT_MIN_CHARS: usize = 3;
+
+impl FilePrefix {
+    fn next(self) -> Self {
+        match self {
+            FilePrefix::Full => FilePrefix::Name,
+            FilePrefix::Name => FilePrefix::Short,
+            FilePrefix::Short => FilePrefix::Hidden,
+            FilePrefix::Hidden => FilePrefix::Full,
+        }
+    }
+}
+
+impl fmt::Display for FilePrefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FilePrefix::Full => "full path",
+            FilePrefix::Name => "file name",
+            FilePrefix::Short => "short name",
+            FilePrefix::Hidden => "hidden",
+        })
+    }
+}
+
+/// What each file is shown as in front of its lines, as `FilePrefix` has it
+pub struct FilePrefixes {
+    mode: FilePrefix,
+    /// How each file rows came from so far is tagged, by file id, see
+    /// `get_file_tags` in main
+    tags: BTreeMap<String, String>,
+    /// The prefix of each of `tags`
+    prefixes: HashMap<String, String>,
+}
+
+impl FilePrefixes {
+    pub fn new(tags: HashMap<String, String>) -> Self {
+        let mut prefixes = FilePrefixes { mode: FilePrefix::default(), tags: tags.into_iter().collect(), prefixes: HashMap::new() };
+        prefixes.update();
+        prefixes
+    }
+
+    pub fn mode(&self) -> FilePrefix {
+        self.mode
+    }
+
+    /// Show files the next way, see `FilePrefix`
+    pub fn cycle(&mut self) {
+        self.mode = self.mode.next();
+        self.update();
+    }
+
+    /// The prefix of rows from `file_id`. Files found in watched directories
+    /// are tagged with their own path, and short names are worked out again
+    /// when one turns up.
+    pub fn get(&mut self, file_id: &str) -> &str {
+        if !self.tags.contains_key(file_id) {
+            self.tags.insert(file_id.to_owned(), file_id.to_owned());
+            self.update();
+        }
+        &self.prefixes[file_id]
+    }
+
+    fn update(&mut self) {
+        let names: Vec<(&String, &str)> = self.tags.iter().map(|(file_id, tag)| (file_id, file_name(tag))).collect();
+        self.prefixes = names.iter()
+            .map(|&(file_id, name)| {
+                let prefix = match self.mode {
+                    FilePrefix::Full => self.tags[file_id].clone(),
+                    FilePrefix::Name => name.to_owned(),
+                    FilePrefix::Short => short_name(name, names.iter().filter(|other| other.0 != file_id).map(|other| other.1)),
+                    FilePrefix::Hidden => String::new(),
+                };
+                (file_id.clone(), prefix)
+            })
+            .collect();
+    }
+}
+
+/// The last part of `tag` if it's a path, otherwise all of it
+fn file_name(tag: &str) -> &str {
+    Path::new(tag).file_name().and_then(|name| name.to_str()).unwrap_or(tag)
+}
+
+/// The start of `name` that none of `others` start with, or all of it if
+/// it takes that
+fn short_name<'a>(name: &str, others: impl Iterator<Item = &'a str> + Clone) -> String {
+    let ends = name.char_indices().map(|(i, _)| i).chain([name.len()]).skip(SHORT_MIN_CHARS);
+    for end in ends {
+        let prefix = &name[..end];
+        if !others.clone().any(|other| other.starts_with(prefix)) {
+            return prefix.to_owned();
+        }
+    }
+    name.to_owned()
+}
@@ -0,0 +1,64 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+use filewatch_rs::file_watch::Level;
+use ratatui::style::{Style, Stylize};
+use regex::Regex;
+
+use super::color;
+
+/// The more severe level names in any case, see `guess`
+static LEVEL_WORDS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(warn|warning|error|err|crit|critical|fatal|panic)\b").expect("level words regex is valid")
+});
+
+/// How lines are shown by their level, see `Args::level_styles`
+#[derive(Clone, Debug)]
+pub struct LevelStyles(BTreeMap<Level, Style>);
+
+impl Default for LevelStyles {
+    fn default() -> Self {
+        LevelStyles(BTreeMap::from([
+            (Level::Warn, Style::default().yellow()),
+            (Level::Error, Style::default().red()),
+            (Level::Fatal, Style::default().red()),
+        ]))
+    }
+}
+
+impl LevelStyles {
+    pub fn set(&mut self, LevelStyle { level, style }: LevelStyle) {
+        self.0.insert(level, style);
+    }
+
+    /// The style of a line of `level`, or of one without a level
+    pub fn get(&self, level: Option<Level>) -> Style {
+        level.and_then(|level| self.0.get(&level)).copied().unwrap_or_default()
+    }
+}
+
+/// A level and how its lines are shown, as `--style` takes it
+#[derive(Clone, Debug)]
+pub struct LevelStyle {
+    pub level: Level,
+    pub style: Style,
+}
+
+impl FromStr for LevelStyle {
+    type Err = String;
+
+    /// `LEVEL=STYLE`, e.g. `error=red bold` or `debug=dim`, see `parse_style`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (level, style) = s.split_once('=').ok_or_else(|| format!("expected LEVEL=STYLE, e.g. 'error=red bold', not '{}'", s))?;
+        Ok(LevelStyle { level: level.trim().parse()?, style: color::parse_style(style)? })
+    }
+}
+
+/// The level of a line the store didn't find one in, going by the first
+/// warning or worse named in `message` in any case. Only done for lines on
+/// screen, and often enough wrong (`no error`) that it can be turned off
+/// along with the rest of the coloring.
+pub fn guess(message: &str) -> Option<Level> {
+    LEVEL_WORDS.find(message).and_then(|found| found.as_str().parse().ok())
+}
@@ -0,0 +1,62 @@
+use std::collections::BTreeSet;
+
+use ratatui::layout::Rect;
+use ratatui::style::{Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::Frame;
+
+use super::color::file_color;
+use super::IngestStats;
+
+/// Columns the sidebar takes at the most, as a share of the screen's
+const MAX_WIDTH_PERCENT: u16 = 40;
+
+/// The files being watched, left of the logs, with whether their rows are
+/// shown and how many lines were read from them, see `App::toggle_sidebar`
+#[derive(Default)]
+pub struct Sidebar {
+    /// Index of the file the cursor is on, in the order `IngestStats::files`
+    /// lists them
+    pub cursor: usize,
+    /// Where it was last drawn, to tell which file a click is on
+    pub area: Rect,
+}
+
+impl Sidebar {
+    /// Columns it takes, of a screen `screen_width` wide: as many as the
+    /// longest line needs, as long as that leaves room for the logs
+    pub fn width(stats: &IngestStats, screen_width: u16) -> u16 {
+        let widest = stats.files().map(|(file_id, lines)| line(0, file_id, lines, false).width()).max().unwrap_or(0);
+        // and the borders, or the title if that's wider
+        let width = (widest as u16 + 2).max(24);
+        width.min(screen_width * MAX_WIDTH_PERCENT / 100)
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, stats: &IngestStats, hidden_files: &BTreeSet<String>) {
+        self.area = area;
+        let mut lines: Vec<Line> = stats.files().enumerate()
+            .map(|(i, (file_id, lines))| {
+                let line = line(i, file_id, lines, hidden_files.contains(file_id));
+                if i == self.cursor { line.reversed() } else { line }
+            })
+            .collect();
+        if lines.is_empty() {
+            lines.push(Line::from("nothing read yet").dim());
+        }
+        let block = Block::bordered()
+            .title(" files (Tab to close) ")
+            .title_bottom(Line::from(" Space to show/hide ").dim());
+        frame.render_widget(Paragraph::new(lines).block(block).style(Style::default()), area);
+    }
+}
+
+/// The file at `index`'s number for the `1`-`9` keys, if it has one, its
+/// checkbox, its id in its color and how many lines were read from it
+fn line(index: usize, file_id: &str, lines: u64, hidden: bool) -> Line<'_> {
+    let number = if index < 9 { format!("{} ", index + 1) } else { String::from("  ") };
+    let checkbox = if hidden { "[ ] " } else { "[x] " };
+    let name = Span::from(file_id).fg(file_color(file_id));
+    let name = if hidden { name.dim() } else { name };
+    Line::from(vec![Span::from(number).dim(), Span::from(checkbox), name, Span::from(format!(" {}", lines)).dim()])
+}
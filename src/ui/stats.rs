@@ -0,0 +1,117 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant, SystemTime};
+
+use filewatch_rs::file_watch::ReadStats;
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Clear, Paragraph};
+use ratatui::Frame;
+
+/// Window the lines per second in the popup are averaged over
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+
+/// The `ReadStats` of each file, as last sent by its watcher, and enough of
+/// their history to tell how fast lines are coming in
+#[derive(Default)]
+pub struct IngestStats {
+    files: BTreeMap<String, FileStats>,
+}
+
+struct FileStats {
+    latest: ReadStats,
+    /// When stats arrived and the line count at that point, oldest first.
+    /// One sample from before `RATE_WINDOW` is kept as the baseline.
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl IngestStats {
+    pub fn update(&mut self, file_id: &str, stats: ReadStats) {
+        let now = Instant::now();
+        let file = self.files.entry(file_id.to_owned())
+            .or_insert_with(|| FileStats { latest: stats, samples: VecDeque::new() });
+        file.latest = stats;
+        file.samples.push_back((now, stats.lines));
+        while file.samples.get(1).is_some_and(|(at, _)| now.duration_since(*at) >= RATE_WINDOW) {
+            file.samples.pop_front();
+        }
+    }
+
+    /// Files stats were sent for
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Each file stats were sent for, by file id, and the lines read from it
+    pub fn files(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.files.iter().map(|(file_id, file)| (file_id.as_str(), file.latest.lines))
+    }
+
+    /// Files that haven't had a new line for longer than `stale_after`
+    pub fn stale_count(&self, stale_after: Duration) -> usize {
+        self.files.values()
+            .filter(|file| file.is_stale(stale_after))
+            .count()
+    }
+
+    pub fn render_popup(&self, frame: &mut Frame, stale_after: Duration) {
+        let mut lines = vec![
+            Line::from(format!("{:<32} {:>10} {:>12} {:>8} {:>10}", "file", "lines", "bytes", "lines/s", "last line")).bold(),
+        ];
+        for (file_id, file) in &self.files {
+            let last_line = match file.since_last_line() {
+                Some(since) => format!("{}s ago", since.as_secs()),
+                None => String::from("never"),
+            };
+            let text = format!("{:<32} {:>10} {:>12} {:>8.1} {:>10}",
+                file_id, file.latest.lines, file.latest.bytes, file.lines_per_sec(), last_line);
+            if file.is_stale(stale_after) {
+                lines.push(Line::from(text + "  stale").yellow());
+            } else {
+                lines.push(Line::from(text));
+            }
+        }
+        if self.files.is_empty() {
+            lines.push(Line::from("nothing read yet").dim());
+        }
+
+        let width = lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 2;
+        let area = centered(frame.area(), width, lines.len() as u16 + 2);
+        frame.render_widget(Clear, area);
+        let block = Block::bordered().title(" stats (s to close) ");
+        frame.render_widget(Paragraph::new(lines).block(block).style(Style::default()), area);
+    }
+}
+
+impl FileStats {
+    fn since_last_line(&self) -> Option<Duration> {
+        let at = self.latest.last_line_at?;
+        Some(SystemTime::now().duration_since(at).unwrap_or_default())
+    }
+
+    fn is_stale(&self, stale_after: Duration) -> bool {
+        self.since_last_line().is_none_or(|since| since > stale_after)
+    }
+
+    /// Average over `RATE_WINDOW`, or since the first stats if that's shorter
+    fn lines_per_sec(&self) -> f64 {
+        let now = Instant::now();
+        let Some(&(baseline_at, baseline)) = self.samples.iter()
+            .rfind(|(at, _)| now.duration_since(*at) >= RATE_WINDOW)
+            .or(self.samples.front()) else {
+            return 0.0;
+        };
+        let elapsed = now.duration_since(baseline_at).min(RATE_WINDOW).as_secs_f64();
+        if elapsed == 0.0 {
+            return 0.0;
+        }
+        (self.latest.lines - baseline) as f64 / elapsed
+    }
+}
+
+/// A `width` by `height` popup in the middle of `area`, cut to fit it
+pub(super) fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let [area] = Layout::horizontal([Constraint::Length(width)]).flex(Flex::Center).areas(area);
+    let [area] = Layout::vertical([Constraint::Length(height)]).flex(Flex::Center).areas(area);
+    area
+}
@@ -0,0 +1,73 @@
+use ratatui::layout::Rect;
+use ratatui::text::Span;
+use ratatui::Frame;
+
+/// Columns left between segments
+const GAP: usize = 2;
+
+/// Which part of the status bar a `Segment` goes in
+#[derive(Clone, Copy, PartialEq)]
+pub enum Side {
+    Left,
+    Center,
+    Right,
+}
+
+/// A piece of the status bar. When they don't all fit, the ones with the
+/// lowest `priority` are left out first, the later ones of those first.
+pub struct Segment {
+    pub side: Side,
+    pub priority: u8,
+    pub span: Span<'static>,
+}
+
+impl Segment {
+    pub fn new(side: Side, priority: u8, span: Span<'static>) -> Self {
+        Segment { side, priority, span }
+    }
+}
+
+/// `segments` along `area`, from the left and right edges and in the
+/// middle where there's room, in the order they're given on each side.
+/// Returns where each of them was drawn, none for those left out.
+pub fn render(frame: &mut Frame, area: Rect, segments: &[Segment]) -> Vec<Option<Rect>> {
+    let mut shown = vec![true; segments.len()];
+    let width = |shown: &[bool], side: Option<Side>| -> usize {
+        segments.iter().zip(shown)
+            .filter(|(segment, shown)| **shown && side.is_none_or(|side| segment.side == side))
+            .map(|(segment, _)| segment.span.width() + GAP).sum::<usize>().saturating_sub(GAP)
+    };
+    while width(&shown, None) > area.width.into() {
+        let Some(least) = segments.iter().enumerate().rev()
+            .filter(|(i, _)| shown[*i])
+            .min_by_key(|(_, segment)| segment.priority).map(|(i, _)| i) else {
+            break;
+        };
+        shown[least] = false;
+    }
+    let (left_width, center_width, right_width) = (
+        width(&shown, Some(Side::Left)) as u16,
+        width(&shown, Some(Side::Center)) as u16,
+        width(&shown, Some(Side::Right)) as u16,
+    );
+    let right_x = area.right().saturating_sub(right_width);
+    // in the middle, unless it'd run into either side
+    let gap = GAP as u16;
+    let center_x = (area.x + area.width.saturating_sub(center_width) / 2)
+        .min(right_x.saturating_sub(center_width + gap))
+        .max(area.x + left_width + if left_width > 0 { gap } else { 0 });
+    let mut x = [(Side::Left, area.x), (Side::Center, center_x), (Side::Right, right_x)];
+    segments.iter().zip(shown)
+        .map(|(segment, shown)| {
+            if !shown {
+                return None;
+            }
+            let (_, x) = x.iter_mut().find(|(side, _)| *side == segment.side).expect("every side has an x");
+            let width = segment.span.width() as u16;
+            let drawn = Rect { x: *x, width: width.min(area.right().saturating_sub(*x)), ..area };
+            frame.render_widget(segment.span.clone(), drawn);
+            *x = x.saturating_add(width + gap);
+            Some(drawn)
+        })
+        .collect()
+}
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use filewatch_rs::file_watch::thousands;
+use ratatui::layout::Rect;
+use ratatui::style::Stylize;
+use ratatui::text::{Line, Span};
+use ratatui::Frame;
+
+use super::color::file_color;
+use super::{IngestStats, Pane};
+
+/// Tab 0 shows the rows of all files, tab N those of the Nth file, in the
+/// order the sidebar lists them. Each pane is on a tab, and is kept as it
+/// was left on the others, scrolled and following or not, to go back to.
+/// The tab bar is only there with more than one file.
+#[derive(Default)]
+pub struct Tabs {
+    /// Panes as they were left on the tabs they aren't on, by pane id and
+    /// the tab's file
+    left: HashMap<(usize, Option<String>), Pane>,
+    /// Lines read from each tab's file, or all of them, as of when it was
+    /// last on screen, to tell how many it hasn't shown yet
+    seen: HashMap<Option<String>, u64>,
+}
+
+impl Tabs {
+    /// The file of the tab at `index`, or none for tab 0, if there is such
+    /// a tab
+    pub fn file(stats: &IngestStats, index: usize) -> Option<Option<String>> {
+        match index {
+            0 => Some(None),
+            _ => stats.files().nth(index - 1).map(|(file_id, _)| Some(file_id.to_owned())),
+        }
+    }
+
+    /// Index of the tab of `file`, or of all files
+    pub fn index(stats: &IngestStats, file: Option<&str>) -> usize {
+        file.and_then(|file| stats.files().position(|(file_id, _)| file_id == file)).map_or(0, |i| i + 1)
+    }
+
+    /// Screen rows the tab bar takes
+    pub fn height(stats: &IngestStats) -> u16 {
+        u16::from(stats.file_count() > 1)
+    }
+
+    /// Put `pane` on the tab of `file`, as it was left there, or following
+    /// the last row and filtered as it is if it wasn't on it before
+    pub fn switch(&mut self, pane: &mut Pane, file: Option<String>) {
+        let on = self.left.remove(&(pane.id, file.clone())).unwrap_or_else(|| Pane {
+            id: pane.id,
+            file,
+            following: true,
+            follow_mode: true,
+            vertical_scroll_pos: usize::MAX,
+            min_level: pane.min_level,
+            hidden_files: pane.hidden_files.clone(),
+            template: pane.template.clone(),
+            ..Pane::default()
+        });
+        let left = std::mem::replace(pane, on);
+        self.left.insert((left.id, left.file.clone()), left);
+    }
+
+    /// Forget how the pane `id` was left on each tab, once it's closed
+    pub fn forget(&mut self, id: usize) {
+        self.left.retain(|(pane, _), _| *pane != id);
+    }
+
+    /// A label for each tab with its number, its file's name and the lines
+    /// read from it, and how many came in since it was last on screen if
+    /// any did. The tab keys go to is reversed, the other pane's is bold.
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, stats: &IngestStats, focused: Option<&str>, other: Option<Option<&str>>) {
+        let total = stats.files().map(|(_, lines)| lines).sum();
+        let tabs = std::iter::once((None, total)).chain(stats.files().map(|(file_id, lines)| (Some(file_id), lines)));
+        let mut spans = vec![];
+        for (i, (file, lines)) in tabs.enumerate() {
+            let on_screen = file == focused || other == Some(file);
+            let seen = self.seen.entry(file.map(str::to_owned)).or_insert(lines);
+            if on_screen {
+                *seen = lines;
+            }
+            if i > 0 {
+                spans.push(Span::from("│").dim());
+            }
+            let name = file.map_or("all", |file_id| Path::new(file_id).file_name().and_then(|name| name.to_str()).unwrap_or(file_id));
+            let name = match file {
+                Some(file_id) => Span::from(name.to_owned()).fg(file_color(file_id)),
+                None => Span::from(name),
+            };
+            let mut label = vec![Span::from(format!(" {} ", i)), name, Span::from(format!(" {}", thousands(lines)))];
+            if lines > *seen {
+                label.push(Span::from(format!(" +{}", thousands(lines - *seen))).yellow().bold());
+            }
+            label.push(Span::from(" "));
+            if file == focused {
+                spans.extend(label.into_iter().map(Span::reversed));
+            } else if on_screen {
+                spans.extend(label.into_iter().map(Span::bold));
+            } else {
+                spans.extend(label);
+            }
+        }
+        frame.render_widget(Line::from(spans), area);
+    }
+}
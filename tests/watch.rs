@@ -0,0 +1,447 @@
+//! Watching real files, written to the way loggers and rotation tools do,
+//! through the public `file_watch` API
+
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use filewatch_rs::file_watch::{self, Backend, Shutdown, SourceEvent, TextEncoding, WatchOptions};
+use filewatch_rs::{LogsMessage, WatchError};
+
+/// How long a test waits for lines before giving up
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A directory of its own for a test, deleted once it's done
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("filewatch-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Files being watched on a thread of their own until it's dropped
+struct Watching {
+    rx: Receiver<LogsMessage>,
+    shutdown: Shutdown,
+    thread: Option<JoinHandle<Result<(), WatchError>>>,
+}
+
+impl Watching {
+    fn start(paths: &[&Path], options: WatchOptions) -> Self {
+        Self::with_capacity(paths, options, file_watch::DEFAULT_CHANNEL_CAPACITY)
+    }
+
+    fn with_capacity(paths: &[&Path], options: WatchOptions, capacity: usize) -> Self {
+        let sources: Vec<_> = paths.iter()
+            .map(|path| (path.to_string_lossy().into_owned(), options.clone()))
+            .collect();
+        let (tx, rx) = mpsc::sync_channel(capacity);
+        let shutdown = Shutdown::default();
+        let watcher_shutdown = shutdown.clone();
+        let thread = thread::spawn(move || file_watch::watch_files(&sources, watcher_shutdown, tx));
+        // let the watches be set up before anything's written
+        thread::sleep(Duration::from_millis(200));
+        Watching { rx, shutdown, thread: Some(thread) }
+    }
+
+    /// Lines and events, described, until `done` says there are enough, with
+    /// the file id each is from
+    fn collect(&self, mut done: impl FnMut(&[Received]) -> bool) -> Vec<Received> {
+        let mut received = vec![];
+        let deadline = Instant::now() + TIMEOUT;
+        while !done(&received) {
+            let left = deadline.saturating_duration_since(Instant::now());
+            let Ok(message) = self.rx.recv_timeout(left) else {
+                panic!("timed out, got {:?}", received);
+            };
+            receive(message, &mut received);
+        }
+        received
+    }
+
+    /// Whatever has arrived so far
+    fn drain(&self) -> Vec<Received> {
+        let mut received = vec![];
+        while let Ok(message) = self.rx.try_recv() {
+            receive(message, &mut received);
+        }
+        received
+    }
+
+    /// Lines until there are `count` of them, without the events
+    fn lines(&self, count: usize) -> Vec<String> {
+        lines(&self.collect(|received| lines(received).len() >= count))
+    }
+}
+
+impl Drop for Watching {
+    fn drop(&mut self) {
+        self.shutdown.trigger();
+        // keep receiving, so the watcher isn't stuck sending
+        while !self.thread.as_ref().is_some_and(JoinHandle::is_finished) {
+            let _ = self.rx.recv_timeout(Duration::from_millis(10));
+        }
+        if let Some(thread) = self.thread.take() {
+            thread.join().unwrap().unwrap();
+        }
+    }
+}
+
+/// A line, or an event as it's described, with the id of the file it's from
+#[derive(Debug, Clone, PartialEq)]
+enum Received {
+    Line(String, String),
+    Event(String, String),
+}
+
+/// Add the lines or event of `message` to `received`, leaving out stats
+/// and progress
+fn receive(message: LogsMessage, received: &mut Vec<Received>) {
+    match message.event {
+        SourceEvent::Lines { lines, .. } => {
+            received.extend(lines.into_iter().map(|line| Received::Line(message.file_id.clone(), line)));
+        }
+        SourceEvent::Stats(_) | SourceEvent::Loading { .. } => {}
+        event => received.push(Received::Event(message.file_id, event.describe())),
+    }
+}
+
+fn lines(received: &[Received]) -> Vec<String> {
+    received.iter()
+        .filter_map(|received| match received {
+            Received::Line(_, line) => Some(line.clone()),
+            Received::Event(..) => None,
+        })
+        .collect()
+}
+
+fn events(received: &[Received]) -> Vec<String> {
+    received.iter()
+        .filter_map(|received| match received {
+            Received::Event(_, event) => Some(event.clone()),
+            Received::Line(..) => None,
+        })
+        .collect()
+}
+
+fn append(path: &Path, text: &str) {
+    let mut file = OpenOptions::new().create(true).append(true).open(path).unwrap();
+    file.write_all(text.as_bytes()).unwrap();
+}
+
+fn numbered(from: usize, to: usize) -> Vec<String> {
+    (from..to).map(|i| format!("line {}", i)).collect()
+}
+
+fn numbered_text(from: usize, to: usize) -> String {
+    numbered(from, to).iter().map(|line| format!("{}\n", line)).collect()
+}
+
+#[test]
+fn rotation_by_rename_and_create() {
+    let dir = TempDir::new("rename-rotation");
+    let log = dir.path("app.log");
+    append(&log, &numbered_text(0, 3));
+    let watching = Watching::start(&[&log], WatchOptions::default());
+    assert_eq!(watching.lines(3), numbered(0, 3));
+
+    append(&log, &numbered_text(3, 5));
+    fs::rename(&log, dir.path("app.log.1")).unwrap();
+    File::create(&log).unwrap();
+    append(&log, &numbered_text(5, 8));
+    let received = watching.collect(|received| lines(received).len() >= 5);
+    assert_eq!(lines(&received), numbered(3, 8));
+    assert!(events(&received).iter().any(|event| event.starts_with("file rotated")), "{:?}", received);
+}
+
+#[test]
+fn rotation_by_copytruncate() {
+    let dir = TempDir::new("copytruncate");
+    let log = dir.path("app.log");
+    append(&log, &numbered_text(0, 3));
+    let watching = Watching::start(&[&log], WatchOptions::default());
+    assert_eq!(watching.lines(3), numbered(0, 3));
+
+    fs::copy(&log, dir.path("app.log.1")).unwrap();
+    OpenOptions::new().write(true).open(&log).unwrap().set_len(0).unwrap();
+    // wait for the truncation to be seen, as logrotate can't either
+    thread::sleep(Duration::from_millis(300));
+    append(&log, &numbered_text(3, 6));
+    let received = watching.collect(|received| lines(received).len() >= 3);
+    assert_eq!(lines(&received), numbered(3, 6));
+    assert!(events(&received).iter().any(|event| event.starts_with("file rotated")), "{:?}", received);
+}
+
+#[test]
+fn line_written_in_pieces_is_one_line() {
+    let dir = TempDir::new("pieces");
+    let log = dir.path("app.log");
+    File::create(&log).unwrap();
+    let watching = Watching::start(&[&log], WatchOptions::default());
+    append(&log, "partial");
+    thread::sleep(Duration::from_millis(200));
+    append(&log, " rest\n");
+    append(&log, "next\n");
+    assert_eq!(watching.lines(2), ["partial rest", "next"]);
+}
+
+#[test]
+fn partial_line_is_sent_after_partial_flush() {
+    let dir = TempDir::new("partial-flush");
+    let log = dir.path("app.log");
+    File::create(&log).unwrap();
+    let options = WatchOptions { partial_flush: Duration::from_millis(500), ..WatchOptions::default() };
+    let watching = Watching::start(&[&log], options);
+    let written = Instant::now();
+    append(&log, "no newline yet");
+    assert_eq!(watching.lines(1), ["no newline yet"]);
+    assert!(written.elapsed() >= Duration::from_millis(400), "sent after {:?}", written.elapsed());
+}
+
+#[test]
+fn utf16_with_byte_order_mark() {
+    let dir = TempDir::new("utf16-bom");
+    let le = dir.path("le.log");
+    let be = dir.path("be.log");
+    let text = "first\r\nzweite \u{00FC}\r\n";
+    fs::write(&le, [&[0xFF, 0xFE][..], &text.encode_utf16().flat_map(u16::to_le_bytes).collect::<Vec<_>>()].concat()).unwrap();
+    fs::write(&be, [&[0xFE, 0xFF][..], &text.encode_utf16().flat_map(u16::to_be_bytes).collect::<Vec<_>>()].concat()).unwrap();
+    let options = WatchOptions { encoding: TextEncoding::Auto, ..WatchOptions::default() };
+    for path in [le, be] {
+        let watching = Watching::start(&[&path], options.clone());
+        assert_eq!(watching.lines(2), ["first", "zweite \u{00FC}"], "{}", path.display());
+    }
+}
+
+#[test]
+fn utf16_line_written_halfway_through_a_code_unit() {
+    let dir = TempDir::new("utf16-split");
+    let log = dir.path("app.log");
+    File::create(&log).unwrap();
+    let options = WatchOptions { encoding: TextEncoding::Fixed(encoding_rs::UTF_16LE), ..WatchOptions::default() };
+    let watching = Watching::start(&[&log], options);
+    let bytes: Vec<u8> = "split\r\n".encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let mut file = OpenOptions::new().append(true).open(&log).unwrap();
+    file.write_all(&bytes[..bytes.len() - 1]).unwrap();
+    thread::sleep(Duration::from_millis(200));
+    file.write_all(&bytes[bytes.len() - 1..]).unwrap();
+    assert_eq!(watching.lines(1), ["split"]);
+}
+
+/// Appends lines `0..count` to `path` at about `per_sec` lines a second,
+/// in a batch every 10ms, on a thread of its own
+fn write_at_rate(path: &Path, count: usize, per_sec: usize) -> JoinHandle<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path).unwrap();
+    let batch = (per_sec / 100).max(1);
+    thread::spawn(move || {
+        let start = Instant::now();
+        for (i, from) in (0..count).step_by(batch).enumerate() {
+            file.write_all(numbered_text(from, (from + batch).min(count)).as_bytes()).unwrap();
+            let due = start + Duration::from_millis(10) * (i as u32 + 1);
+            thread::sleep(due.saturating_duration_since(Instant::now()));
+        }
+    })
+}
+
+/// Lines until there are `count` of them, and how many messages they came in
+fn lines_and_messages(watching: &Watching, count: usize) -> (Vec<String>, usize) {
+    let mut lines = vec![];
+    let mut messages = 0;
+    let deadline = Instant::now() + TIMEOUT;
+    while lines.len() < count {
+        let left = deadline.saturating_duration_since(Instant::now());
+        let Ok(message) = watching.rx.recv_timeout(left) else {
+            panic!("timed out after {} lines", lines.len());
+        };
+        if let SourceEvent::Lines { lines: read, .. } = message.event {
+            lines.extend(read);
+            messages += 1;
+        }
+    }
+    (lines, messages)
+}
+
+#[test]
+fn fast_writer_lines_arrive_complete_and_in_order() {
+    let dir = TempDir::new("fast-writer");
+    let log = dir.path("app.log");
+    File::create(&log).unwrap();
+    let watching = Watching::start(&[&log], WatchOptions::default());
+    let count = 100_000;
+    let writer = write_at_rate(&log, count, 50_000);
+    let (lines, messages) = lines_and_messages(&watching, count);
+    writer.join().unwrap();
+    assert!(lines == numbered(0, count), "lost or out of order, first difference at {:?}",
+        lines.iter().zip(numbered(0, count)).position(|(line, expected)| *line != expected));
+    // batched by the debounce, about one a window rather than one for each
+    // of the 200 writes
+    assert!(messages < 100, "{} messages", messages);
+}
+
+/// How fast lines written as fast as they can be are read, with a write
+/// for each line, as loggers do. Timed rather than checked, run it with
+/// `cargo test --release --test watch -- --ignored --nocapture`.
+#[test]
+#[ignore]
+fn throughput() {
+    let dir = TempDir::new("throughput");
+    let log = dir.path("app.log");
+    File::create(&log).unwrap();
+    let watching = Watching::start(&[&log], WatchOptions::default());
+    let count = 500_000;
+    let start = Instant::now();
+    let mut file = OpenOptions::new().append(true).open(&log).unwrap();
+    let writer = thread::spawn(move || {
+        for line in numbered(0, count) {
+            file.write_all(format!("{} and some more to look like a log line\n", line).as_bytes()).unwrap();
+        }
+    });
+    let (lines, messages) = lines_and_messages(&watching, count);
+    let elapsed = start.elapsed();
+    writer.join().unwrap();
+    assert_eq!(lines.len(), count);
+    println!("{} lines in {} messages, {:?}, {:.0} lines/s", count, messages, elapsed, count as f64 / elapsed.as_secs_f64());
+}
+
+/// Replaces `notes.txt` the way vim saves it, with a new file written next
+/// to it and renamed over it, and returns what's received after that
+fn rename_over(name: &str, backend: Backend) -> Vec<Received> {
+    let dir = TempDir::new(name);
+    let log = dir.path("notes.txt");
+    append(&log, "old\n");
+    let watching = Watching::start(&[&log], WatchOptions { backend, ..WatchOptions::default() });
+    assert_eq!(watching.lines(1), ["old"]);
+
+    let swap = dir.path("notes.txt~");
+    fs::write(&swap, "new\nlines\n").unwrap();
+    fs::rename(&swap, &log).unwrap();
+    append(&log, "after\n");
+    let received = watching.collect(|received| lines(received).len() >= 3);
+    assert_eq!(lines(&received), ["new", "lines", "after"]);
+    received
+}
+
+#[test]
+fn file_renamed_over() {
+    // the rename is an event for the path, like a rotation
+    let received = rename_over("rename-over", Backend::Native);
+    assert_eq!(events(&received), ["file rotated"]);
+}
+
+#[test]
+fn file_renamed_over_without_an_event() {
+    // polling only sees the path's file change, and checks its inode
+    let received = rename_over("rename-over-poll", Backend::Poll(Duration::from_millis(100)));
+    assert_eq!(events(&received), ["file replaced"]);
+}
+
+#[test]
+fn file_truncated_and_rewritten() {
+    let dir = TempDir::new("rewritten");
+    let log = dir.path("app.log");
+    append(&log, "short\n");
+    let watching = Watching::start(&[&log], WatchOptions { debounce: Duration::from_millis(500), ..WatchOptions::default() });
+    assert_eq!(watching.lines(1), ["short"]);
+
+    // longer than before by the time it's read again, so the length alone
+    // doesn't tell it was truncated
+    fs::write(&log, "rewritten from the start\nand more\n").unwrap();
+    let received = watching.collect(|received| lines(received).len() >= 2);
+    assert_eq!(lines(&received), ["rewritten from the start", "and more"]);
+    assert!(events(&received).iter().any(|event| event == "file rewritten"), "{:?}", received);
+}
+
+#[test]
+fn follow_descriptor_keeps_reading_the_renamed_file() {
+    let dir = TempDir::new("follow-descriptor");
+    let log = dir.path("app.log");
+    append(&log, "first\n");
+    let options = WatchOptions { follow_descriptor: true, ..WatchOptions::default() };
+    let watching = Watching::start(&[&log], options);
+    assert_eq!(watching.lines(1), ["first"]);
+
+    let moved = dir.path("app.log.1");
+    fs::rename(&log, &moved).unwrap();
+    fs::write(&log, "in the new file\n").unwrap();
+    append(&moved, "still the old file\n");
+    let received = watching.collect(|received| !lines(received).is_empty());
+    assert_eq!(lines(&received), ["still the old file"]);
+    thread::sleep(Duration::from_millis(500));
+    let received = watching.drain();
+    assert!(!lines(&received).contains(&String::from("in the new file")), "{:?}", received);
+}
+
+#[test]
+fn hundred_files_appended_concurrently() {
+    let dir = TempDir::new("hundred-files");
+    let paths: Vec<PathBuf> = (0..100).map(|i| dir.path(&format!("{}.log", i))).collect();
+    for path in &paths {
+        File::create(path).unwrap();
+    }
+    let watching = Watching::start(&paths.iter().map(PathBuf::as_path).collect::<Vec<_>>(), WatchOptions::default());
+    let per_file = 50;
+    let writers: Vec<_> = paths.iter()
+        .map(|path| {
+            let path = path.clone();
+            thread::spawn(move || {
+                for line in numbered(0, per_file) {
+                    append(&path, &format!("{}\n", line));
+                    thread::sleep(Duration::from_millis(2));
+                }
+            })
+        })
+        .collect();
+    let received = watching.collect(|received| lines(received).len() >= paths.len() * per_file);
+    for writer in writers {
+        writer.join().unwrap();
+    }
+    for path in &paths {
+        let id = path.to_string_lossy();
+        let from_file: Vec<&String> = received.iter()
+            .filter_map(|received| match received {
+                Received::Line(file_id, line) if *file_id == id => Some(line),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(from_file, numbered(0, per_file).iter().collect::<Vec<_>>(), "{}", id);
+    }
+}
+
+#[test]
+fn slow_receiver_holds_back_reading() {
+    let dir = TempDir::new("slow-receiver");
+    let log = dir.path("app.log");
+    File::create(&log).unwrap();
+    // a message's room, so the watcher waits on the receiver rather than
+    // reading ahead
+    let watching = Watching::with_capacity(&[&log], WatchOptions::default(), 1);
+    let count = 20_000;
+    let writer = write_at_rate(&log, count, 20_000);
+    let mut received = vec![];
+    let deadline = Instant::now() + TIMEOUT;
+    while lines(&received).len() < count && Instant::now() < deadline {
+        if let Ok(message) = watching.rx.recv_timeout(Duration::from_secs(1)) {
+            receive(message, &mut received);
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+    writer.join().unwrap();
+    assert!(lines(&received) == numbered(0, count), "lost or out of order, {} lines", lines(&received).len());
+}